@@ -10,6 +10,12 @@ void SatStatsWrapper::satstat_add_watcher(size_t num_clause_total,
   sjqrusttools::satstat_add_watcher(satstats, num_clause_total,
                                     num_clause_read);
 }
+void SatStatsWrapper::satstat_add_watcher_level(size_t level) {
+  sjqrusttools::satstat_add_watcher_level(satstats, level);
+}
+void SatStatsWrapper::satstat_add_clause_length(size_t clause_len) {
+  sjqrusttools::satstat_add_clause_length(satstats, clause_len);
+}
 void SatStatsWrapper::end_decision(bool conflict) {
   sjqrusttools::end_decision(satstats, conflict);
 }
@@ -29,34 +35,100 @@ bool SimulatorWapper::run_single_task(SataccMinisatTaskWrapper &task) {
 // satacc minisat task wrapper
 SataccMinisatTaskWrapper::SataccMinisatTaskWrapper()
     : task(sjqrusttools::create_empty_task()) {}
+SataccMinisatTaskWrapper::SataccMinisatTaskWrapper(
+    sjqrusttools::SataccMinisatTask *task)
+    : task(task) {}
+SataccMinisatTaskWrapper SataccMinisatTaskWrapper::with_layout() {
+  return SataccMinisatTaskWrapper(sjqrusttools::create_task_with_layout());
+}
 SataccMinisatTaskWrapper::~SataccMinisatTaskWrapper() {
-  sjqrusttools::release_task(task);
+  if (task != nullptr) {
+    sjqrusttools::release_task(task);
+  }
 }
+bool SataccMinisatTaskWrapper::is_valid() const { return task != nullptr; }
 
-void SataccMinisatTaskWrapper::add_single_watcher_clause_value_addr(
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::add_single_watcher_clause_lit(
+    size_t lit, sjqrusttools::LitValue value) {
+  return sjqrusttools::add_single_watcher_clause_lit(task, lit, value);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::add_single_watcher_clause_value_addr(
     uint64_t value_addr, size_t clause_id) {
-  sjqrusttools::add_single_watcher_clause_value_addr(task, value_addr,
-                                                     clause_id);
-}
-void SataccMinisatTaskWrapper::add_single_watcher_task(uint64_t blocker_addr,
-                                                       uint64_t clause_addr,
-                                                       size_t clause_id,
-                                                       size_t processing_time,
-                                                       size_t watcher_id) {
-  sjqrusttools::add_single_watcher_task(task, blocker_addr, clause_addr,
-                                        clause_id, processing_time, watcher_id);
-}
-void SataccMinisatTaskWrapper::add_single_watcher_task_no_clause(
+  return sjqrusttools::add_single_watcher_clause_value_addr(task, value_addr,
+                                                            clause_id);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::add_single_watcher_clause_value_by_id(size_t var) {
+  return sjqrusttools::add_single_watcher_clause_value_by_id(task, var);
+}
+sjqrusttools::BuilderStatus SataccMinisatTaskWrapper::add_single_watcher_task(
+    uint64_t blocker_addr, uint64_t clause_addr, size_t clause_id,
+    size_t processing_time, size_t watcher_id) {
+  return sjqrusttools::add_single_watcher_task(
+      task, blocker_addr, clause_addr, clause_id, processing_time, watcher_id);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::add_single_watcher_task_by_id(size_t blocker_var,
+                                                        size_t clause_id,
+                                                        size_t clause_len,
+                                                        size_t watcher_id) {
+  return sjqrusttools::add_single_watcher_task_by_id(
+      task, blocker_var, clause_id, clause_len, watcher_id);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::add_single_watcher_task_no_clause(
     uint64_t blocker_addr, size_t watcher_id) {
-  sjqrusttools::add_single_watcher_task_no_clause(task, blocker_addr,
-                                                  watcher_id);
-}
-void SataccMinisatTaskWrapper::add_watcher_task(size_t level,
-                                                uint64_t meta_data_addr,
-                                                uint64_t watcher_addr,
-                                                size_t watcher_id) {
-  sjqrusttools::add_watcher_task(task, level, meta_data_addr, watcher_addr,
-                                 watcher_id);
+  return sjqrusttools::add_single_watcher_task_no_clause(task, blocker_addr,
+                                                         watcher_id);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::add_single_watcher_task_no_clause_by_id(
+    size_t blocker_var, size_t watcher_id) {
+  return sjqrusttools::add_single_watcher_task_no_clause_by_id(
+      task, blocker_var, watcher_id);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::add_single_watcher_task_untimed(
+    uint64_t blocker_addr, uint64_t clause_addr, size_t clause_id,
+    size_t watcher_id) {
+  return sjqrusttools::add_single_watcher_task_untimed(
+      task, blocker_addr, clause_addr, clause_id, watcher_id);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::add_watcher_task(size_t level,
+                                           uint64_t meta_data_addr,
+                                           uint64_t watcher_addr,
+                                           size_t watcher_id) {
+  return sjqrusttools::add_watcher_task(task, level, meta_data_addr,
+                                        watcher_addr, watcher_id);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::add_watcher_task_by_id(size_t level, size_t lit,
+                                                 size_t watch_list_len) {
+  return sjqrusttools::add_watcher_task_by_id(task, level, lit,
+                                              watch_list_len);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::set_assignment_value_addr(uint64_t value_addr) {
+  return sjqrusttools::set_assignment_value_addr(task, value_addr);
+}
+sjqrusttools::BuilderStatus SataccMinisatTaskWrapper::set_clause_conflict() {
+  return sjqrusttools::set_clause_conflict(task);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::set_clause_new_watch(size_t lit, uint64_t append_addr,
+                                               uint64_t compact_addr) {
+  return sjqrusttools::set_clause_new_watch(task, lit, append_addr,
+                                            compact_addr);
+}
+sjqrusttools::BuilderStatus
+SataccMinisatTaskWrapper::set_clause_new_watch_by_id(size_t lit,
+                                                     size_t append_index,
+                                                     size_t compact_index) {
+  return sjqrusttools::set_clause_new_watch_by_id(task, lit, append_index,
+                                                  compact_index);
 }
 bool SataccMinisatTaskWrapper::run_full_expr() {
   return sjqrusttools::run_full_expr(task);
@@ -7,6 +7,8 @@ public:
   ~SatStatsWrapper();
   void end_decision(bool conflict);
   void satstat_add_watcher(size_t num_clause_total, size_t num_clause_read);
+  void satstat_add_watcher_level(size_t level);
+  void satstat_add_clause_length(size_t clause_len);
   void save_data() const;
   void show_data() const;
 
@@ -16,22 +18,53 @@ private:
 class SataccMinisatTaskWrapper {
 public:
   SataccMinisatTaskWrapper();
+  /// the task of `create_task_with_layout`, check it by `is_valid`
+  static SataccMinisatTaskWrapper with_layout();
+  SataccMinisatTaskWrapper(const SataccMinisatTaskWrapper &) = delete;
+  SataccMinisatTaskWrapper &operator=(const SataccMinisatTaskWrapper &) = delete;
 
   ~SataccMinisatTaskWrapper();
-  void add_single_watcher_clause_value_addr(uint64_t value_addr,
-                                            size_t clause_id);
-  void add_single_watcher_task(uint64_t blocker_addr, uint64_t clause_addr,
-                               size_t clause_id, size_t processing_time,
-                               size_t watcher_id);
+  bool is_valid() const;
+  sjqrusttools::BuilderStatus
+  add_single_watcher_clause_lit(size_t lit, sjqrusttools::LitValue value);
+  sjqrusttools::BuilderStatus
+  add_single_watcher_clause_value_addr(uint64_t value_addr, size_t clause_id);
+  sjqrusttools::BuilderStatus add_single_watcher_clause_value_by_id(size_t var);
+  sjqrusttools::BuilderStatus
+  add_single_watcher_task(uint64_t blocker_addr, uint64_t clause_addr,
+                          size_t clause_id, size_t processing_time,
+                          size_t watcher_id);
+  sjqrusttools::BuilderStatus
+  add_single_watcher_task_by_id(size_t blocker_var, size_t clause_id,
+                                size_t clause_len, size_t watcher_id);
 
-  void add_single_watcher_task_no_clause(uint64_t blocker_addr,
-                                         size_t watcher_id);
-  void add_watcher_task(size_t level, uint64_t meta_data_addr,
-                        uint64_t watcher_addr, size_t watcher_id);
+  sjqrusttools::BuilderStatus
+  add_single_watcher_task_no_clause(uint64_t blocker_addr, size_t watcher_id);
+  sjqrusttools::BuilderStatus
+  add_single_watcher_task_no_clause_by_id(size_t blocker_var,
+                                          size_t watcher_id);
+  sjqrusttools::BuilderStatus
+  add_single_watcher_task_untimed(uint64_t blocker_addr, uint64_t clause_addr,
+                                  size_t clause_id, size_t watcher_id);
+  sjqrusttools::BuilderStatus add_watcher_task(size_t level,
+                                               uint64_t meta_data_addr,
+                                               uint64_t watcher_addr,
+                                               size_t watcher_id);
+  sjqrusttools::BuilderStatus
+  add_watcher_task_by_id(size_t level, size_t lit, size_t watch_list_len);
+  sjqrusttools::BuilderStatus set_assignment_value_addr(uint64_t value_addr);
+  sjqrusttools::BuilderStatus set_clause_conflict();
+  sjqrusttools::BuilderStatus set_clause_new_watch(size_t lit,
+                                                   uint64_t append_addr,
+                                                   uint64_t compact_addr);
+  sjqrusttools::BuilderStatus set_clause_new_watch_by_id(size_t lit,
+                                                         size_t append_index,
+                                                         size_t compact_index);
   bool run_full_expr();
   void start_new_assgin();
 
 private:
+  explicit SataccMinisatTaskWrapper(sjqrusttools::SataccMinisatTask *task);
   sjqrusttools::SataccMinisatTask *const task;
   friend class SimulatorWapper;
 };
@@ -15,9 +15,45 @@
 
 namespace sjqrusttools {
 
+/// the result of a builder call, the call is ignored if it's not [`BuilderStatus::Ok`]
+enum class BuilderStatus {
+  Ok,
+  /// `start_new_assgin` is not called yet
+  NoRound,
+  /// the round has no assignment yet
+  NoAssignment,
+  /// the last watcher of the assignment does not read a clause
+  NoClauseWatcher,
+  /// the task is not created by `create_task_with_layout`
+  NoLayout,
+  /// the watch list or the clause doesn't fit in its partition of the layout
+  PartitionFull,
+};
+
 enum class CacheType {
   Simple,
+  /// the cache with the ramulator of `ramu_cache_config`, it needs the `ramulator` feature
   Ramu,
+  /// the cache with the built-in dram model of `dram_config`
+  Dram,
+};
+
+/// how to choose the channel of a line
+enum class ChannelMapping {
+  /// the lowest bits of the line
+  BitSlice,
+  /// the xor of all the bits groups of the line, so the strided lines are spread across the channels
+  XorHash,
+};
+
+/// the way to keep the copies of the value array in the private caches fresh
+enum class CoherenceProtocol {
+  /// the writes are not sent to the private caches, the copies may be stale
+  NoCoherence,
+  /// the directory tracks the private caches that hold each value line, a write invalidates these copies
+  DirectoryInvalidate,
+  /// each write is broadcast to all the private caches, the copies are updated in place
+  BroadcastUpdate,
 };
 
 /// the type of the dram that used to read and write data
@@ -32,6 +68,22 @@ enum class IcntType {
   Ideal,
 };
 
+/// where the watch lists and the clauses are placed
+enum class LayoutPlacement {
+  /// the watch lists and the clauses are allocated one after another in one region
+  Packed,
+  /// each watcher pe owns `partition_bytes` of each region, a watch list is placed in the partition of its pe,
+  /// a clause is placed in the partition of the pe that first reads it
+  PerPePartitioned,
+};
+
+/// the value of a literal
+enum class LitValue {
+  True,
+  False,
+  Undef,
+};
+
 enum class PresetConfigs {
   ALDRAM,
   DDR4,
@@ -49,11 +101,30 @@ enum class PresetConfigs {
   WideIO,
 };
 
+/// the hardware prefetcher attached to a cache
+enum class PrefetcherType {
+  NoPrefetch,
+  /// fetch the next `prefetch_degree` lines after each demand access
+  NextLine,
+  /// detect the stride of each request type of each pe
+  Stride,
+  /// fetch the watcher list as soon as the watcher meta data is read
+  WatchList,
+};
+
 enum class RunMode {
   NoGapBtweenRounds,
   RealRoundGap,
 };
 
+/// how the variable values are packed in the value array
+enum class ValueEncoding {
+  /// one byte for each variable
+  Byte,
+  /// two bits for each variable like minisat's `lbool`, four variables share a byte
+  TwoBit,
+};
+
 /// The type for the watcher sending to the clase
 enum class WatcherToClauseType {
   /// - in this case, the watcher send clause to it's own clause unit
@@ -74,12 +145,182 @@ struct Satstat;
 
 struct SimulatorWapper;
 
+/// how a kind of request uses the cache
+struct ReqKindPolicy {
+  /// the ways that this kind can allocate lines in, bit i is way i, 0 means all the ways
+  /// - the lookup still searches all the ways, so a line allocated by other kinds can hit
+  uint64_t way_mask;
+  /// do not allocate lines for this kind, the missed requests go to the next level directly
+  bool bypass;
+};
+
+/// the [`ReqKindPolicy`] of each [`MemReqKind`]
+struct ReqKindPolicies {
+  ReqKindPolicy meta_data;
+  ReqKindPolicy watcher_data;
+  ReqKindPolicy blocker;
+  ReqKindPolicy clause_data;
+  ReqKindPolicy clause_value;
+  ReqKindPolicy watch_list_write;
+};
+
 struct CacheConfig {
   uint64_t sets;
   uint64_t associativity;
   uint64_t block_size;
+  /// the number of slices of the cache, the `sets` are evenly split into the channels
   uint64_t channels;
+  ChannelMapping channel_mapping;
+  /// the cycles a channel is busy for each access, the other accesses to it stall, 0 means no conflict
+  uint64_t channel_busy_cycles;
   bool alway_hit;
+  /// the number of mshr entries, that is, the max number of missed lines in flight
+  uint64_t mshr_entries;
+  /// the max number of requests that can be merged into one mshr entry
+  uint64_t mshr_max_targets;
+  PrefetcherType prefetcher;
+  /// the number of lines to prefetch each time
+  uint64_t prefetch_degree;
+  /// the bytes per cycle of the next level channel of each partition, the missed lines of the partition queue up on it, 0 means unlimited
+  uint64_t miss_bytes_per_cycle;
+  /// the way partition and the bypass of each kind of request
+  ReqKindPolicies req_kind_policies;
+};
+
+/// derive the processing time of the clauses instead of the `processing_time` passed by the builder
+/// - a clause costs `setup_cycles`, then the literals are evaluated `lanes` at a time
+/// - the literals are the ones carried by the clause, see `add_single_watcher_clause_lit`,
+///   a clause without them evaluates the values it reads
+/// - with `early_exit`, the evaluation stops at the first true literal, the values come from the
+///   functional state in the closed-loop mode and from the trace otherwise
+/// - the clauses built without a processing time, see `add_single_watcher_task_untimed`, are always derived
+struct ClauseTimingConfig {
+  bool enable;
+  /// the cycles before the first literal is evaluated
+  size_t setup_cycles;
+  /// the literals evaluated each cycle, at least 1
+  size_t lanes;
+  bool early_exit;
+};
+
+/// the config of the value reads through the private caches
+/// - when `private_value_cache` is set, the clause units read the values out of the scratchpad range in the private cache of its tile
+/// - the trail writes the value of each assignment, the `protocol` sends the writes to the private caches
+/// - the messages share one network from the trail to the tiles, each message takes
+///   `message_bytes / bytes_per_cycle` cycles on it and arrives after `latency`, a broadcast is sent once
+struct CoherenceConfig {
+  bool private_value_cache;
+  CoherenceProtocol protocol;
+  /// the size of an invalidation or an update message
+  uint64_t message_bytes;
+  /// the bandwidth of the coherence network, 0 means unlimited
+  uint64_t bytes_per_cycle;
+  size_t latency;
+};
+
+/// stop the round at the first conflict like minisat
+/// - a clause is conflicting if the trace marks it, or if the functional evaluation finds it, see [`super::FunctionalConfig`]
+/// - `signal_latency` cycles after the conflict, the trail, the watchers and the clause units cancel the work that is not issued yet,
+///   the memory requests in flight are drained and their results are dropped
+/// - the next round starts `flush_latency` cycles after the signal and after all the work of the round is drained
+struct ConflictConfig {
+  bool enable;
+  /// the cycles for the conflict signal to reach the trail and the pes
+  size_t signal_latency;
+  /// the cycles to flush the pipelines after the signal
+  size_t flush_latency;
+};
+
+/// the fast-forward mode, the fast-forwarded rounds only warm up the caches without timing
+/// - the first `warm_up_rounds` are fast-forwarded, then `detailed_rounds` are simulated in detail,
+///   then `fast_forward_rounds` are fast-forwarded again, and so on
+/// - the statistics and the cycles only count the detailed windows
+struct FastForwardConfig {
+  bool enable;
+  /// the rounds fast-forwarded before the first detailed window
+  size_t warm_up_rounds;
+  /// the rounds of each detailed window, 0 means the first window runs to the end
+  size_t detailed_rounds;
+  /// the rounds fast-forwarded after each detailed window
+  size_t fast_forward_rounds;
+};
+
+/// the closed-loop mode, the clause units evaluate the clauses and the trail sends an assignment when it's implied
+/// - the clauses carry their literals, see `add_single_watcher_clause_lit`
+/// - the first level of a round is sent at once, the other assignments wait until a clause unit implies them
+/// - an assignment that is never implied is sent in the software order when all the sent work is finished
+struct FunctionalConfig {
+  bool enable;
+  /// the cycles from a clause unit finding the implied literal to the trail receiving it
+  size_t feedback_latency;
+};
+
+/// the config of the layout model, it places the solver data by the logical ids
+/// - the value array is shared by all the pes, the `value_encoding` decides the bytes of a variable
+/// - the watch list meta data has `watch_meta_bytes` for each literal, the watchers of a literal are a growing vector
+/// - the clauses are in the arena, each has a `clause_header_bytes` header and `lit_bytes` for each literal
+struct LayoutConfig {
+  uint64_t value_base;
+  ValueEncoding value_encoding;
+  uint64_t watch_meta_base;
+  uint64_t watch_meta_bytes;
+  uint64_t watch_data_base;
+  /// a watcher is the clause ref and the blocker
+  uint64_t watcher_bytes;
+  uint64_t clause_base;
+  uint64_t clause_header_bytes;
+  uint64_t lit_bytes;
+  LayoutPlacement placement;
+  /// the bytes of each partition of a region, used by [`LayoutPlacement::PerPePartitioned`]
+  uint64_t partition_bytes;
+};
+
+/// the config of the distributed l3 cache
+/// - when enabled, each watcher tile owns a slice of the l3 cache at its mesh node,
+///   the lines are interleaved across the slices, so the hops depend on where the line lives
+/// - each slice is a fixed latency cache with `1 / n_watchers` of the sets of `l3_cache_config`
+struct NucaConfig {
+  bool enable;
+  /// put the watcher meta data, the watcher list and the blockers in the slice of the watcher that reads them
+  bool local_watch_lists;
+};
+
+/// the processing pipeline of the watcher and the clause unit
+/// - `issue_width` tasks can enter the pipeline each cycle, at most `depth` tasks are in flight
+/// - a task leaves the pipeline `latency` cycles after it enters, the clause unit adds the processing time of the clause
+/// - the width and the depth are at least 1
+struct PipelineConfig {
+  /// the cycles of a task in the pipeline
+  size_t latency;
+  /// the max number of tasks that enter the pipeline each cycle
+  size_t issue_width;
+  /// the max number of tasks in flight
+  size_t depth;
+};
+
+/// the config of the sampled simulation, see [`SamplingPlan`]
+struct SamplingConfig {
+  /// the max number of clusters of the rounds
+  size_t clusters;
+  /// the number of rounds simulated in detail in each cluster, at least 2 to get the variance
+  size_t samples_per_cluster;
+  /// the max iterations of the k-means
+  size_t max_iterations;
+  uint64_t seed;
+};
+
+/// the config of the on-chip scratchpad that holds the variable values
+/// - the value requests in `[base_addr, base_addr + capacity)` are served by the scratchpad
+/// - the value requests out of the range still go to the l3 cache
+struct ScratchpadConfig {
+  bool enable;
+  uint64_t base_addr;
+  /// the capacity in bytes
+  uint64_t capacity;
+  uint64_t banks;
+  /// the bytes of each bank line, continuous lines are interleaved across the banks
+  uint64_t bank_width;
+  size_t latency;
 };
 
 /// the config for satacc
@@ -113,8 +354,34 @@ struct Config {
   size_t l3_hit_latency;
   size_t miss_latency;
   RunMode init_running_mode;
+  /// profile the reuse distance and the working set of the requests to the l3 cache
+  bool mem_profiler;
+  /// the cycles of each window of the working set
+  size_t mem_profiler_window;
   CacheConfig private_cache_config;
   CacheConfig l3_cache_config;
+  /// the scratchpad for the variable values, the value requests in its range will not go to the l3 cache
+  ScratchpadConfig scratchpad_config;
+  /// the distributed l3 cache, it replaces the partitions of the l3 cache when enabled
+  NucaConfig nuca_config;
+  /// read the values through the private caches and keep them fresh
+  CoherenceConfig coherence_config;
+  /// the layout model of the tasks created by `create_task_with_layout`
+  LayoutConfig layout_config;
+  /// the clusters and the samples of the sampled simulation
+  SamplingConfig sampling_config;
+  /// fast-forward the rounds out of the detailed windows
+  FastForwardConfig fast_forward_config;
+  /// the closed-loop mode, the clause units evaluate the clauses and imply the assignments
+  FunctionalConfig functional_config;
+  /// stop the round at the first conflict
+  ConflictConfig conflict_config;
+  /// the blocker check of the watchers
+  PipelineConfig watcher_pipeline;
+  /// the clause evaluation of the clause units, the latency is added to the processing time of the clause
+  PipelineConfig clause_pipeline;
+  /// derive the processing time of the clauses from the literals
+  ClauseTimingConfig clause_timing_config;
 };
 
 struct Point {
@@ -130,32 +397,79 @@ struct Rec {
 
 extern "C" {
 
-void add_single_watcher_clause_value_addr(SataccMinisatTask *self,
-                                          uint64_t value_addr,
-                                          size_t clause_id);
-
-void add_single_watcher_task(SataccMinisatTask *self,
-                             uint64_t blocker_addr,
-                             uint64_t clause_addr,
-                             size_t clause_id,
-                             size_t processing_time,
-                             size_t watcher_id);
-
-void add_single_watcher_task_no_clause(SataccMinisatTask *self,
-                                       uint64_t blocker_addr,
-                                       size_t watcher_id);
-
-void add_watcher_task(SataccMinisatTask *self,
-                      size_t level,
-                      uint64_t meta_data_addr,
-                      uint64_t watcher_addr,
-                      size_t watcher_id);
+/// the clause of the last watcher has the literal `lit` with the `value` seen by the software
+BuilderStatus add_single_watcher_clause_lit(SataccMinisatTask *self, size_t lit, LitValue value);
+
+BuilderStatus add_single_watcher_clause_value_addr(SataccMinisatTask *self,
+                                                   uint64_t value_addr,
+                                                   size_t clause_id);
+
+/// the clause of the last watcher reads the value of variable `var`
+BuilderStatus add_single_watcher_clause_value_by_id(SataccMinisatTask *self, size_t var);
+
+/// add a watcher that reads the clause, it takes `processing_time` cycles unless
+/// [`super::ClauseTimingConfig`] is enabled
+BuilderStatus add_single_watcher_task(SataccMinisatTask *self,
+                                      uint64_t blocker_addr,
+                                      uint64_t clause_addr,
+                                      size_t clause_id,
+                                      size_t processing_time,
+                                      size_t watcher_id);
+
+/// add a watcher that reads clause `clause_id` of `clause_len` literals,
+/// the processing time is derived by [`super::ClauseTimingConfig`]
+BuilderStatus add_single_watcher_task_by_id(SataccMinisatTask *self,
+                                            size_t blocker_var,
+                                            size_t clause_id,
+                                            size_t clause_len,
+                                            size_t watcher_id);
+
+BuilderStatus add_single_watcher_task_no_clause(SataccMinisatTask *self,
+                                                uint64_t blocker_addr,
+                                                size_t watcher_id);
+
+/// add a watcher whose blocker is variable `blocker_var`
+BuilderStatus add_single_watcher_task_no_clause_by_id(SataccMinisatTask *self,
+                                                      size_t blocker_var,
+                                                      size_t watcher_id);
+
+/// add a watcher that reads the clause, the processing time is always derived by [`super::ClauseTimingConfig`]
+BuilderStatus add_single_watcher_task_untimed(SataccMinisatTask *self,
+                                              uint64_t blocker_addr,
+                                              uint64_t clause_addr,
+                                              size_t clause_id,
+                                              size_t watcher_id);
+
+BuilderStatus add_watcher_task(SataccMinisatTask *self,
+                               size_t level,
+                               uint64_t meta_data_addr,
+                               uint64_t watcher_addr,
+                               size_t watcher_id);
+
+/// add the assignment of literal `lit`, its watch list has `watch_list_len` watchers
+BuilderStatus add_watcher_task_by_id(SataccMinisatTask *self,
+                                     size_t level,
+                                     size_t lit,
+                                     size_t watch_list_len);
+
+/// analyze the trace file with the watchers and the cache line of `satacc_config.toml`, the report is `trace_report.json`
+bool analyze_trace_expr(const char *path);
+
+/// check the task before simulating it, log each problem and return the number of problems
+size_t check_task(const SataccMinisatTask *self);
+
+/// check the trace file, log each problem and return the number of problems, -1 if the trace cannot be read
+intptr_t check_trace(const char *path);
 
 Config config_from_file(const char *path);
 
 /// this will create a simulator task object, do not free it, it will be freed by calling `run_full_expr`
 SataccMinisatTask *create_empty_task();
 
+/// create a task that places the data by the `layout_config` and `n_watchers` of `satacc_config.toml`,
+/// the `_by_id` builders can be used on it, free it by `release_task`, return null if the config is invalid
+SataccMinisatTask *create_task_with_layout();
+
 void delete_satstat_pointer(Satstat *satstat);
 
 void end_decision(Satstat *self, bool conflict);
@@ -164,6 +478,9 @@ void end_decision(Satstat *self, bool conflict);
 /// return still ok?
 bool finish_simulator(SimulatorWapper *sim);
 
+/// flush and close the trace, return false if the trace is not complete
+bool finish_trace(SataccMinisatTask *self);
+
 /// get the simulator
 SimulatorWapper *get_simulator();
 
@@ -171,12 +488,19 @@ int32_t get_x(const Point *self);
 
 int32_t get_y(const Point *self);
 
+/// read the task from the trace file, return null if the trace is invalid, free it by `release_task`
+SataccMinisatTask *load_task_from_trace(const char *path);
+
 Satstat *new_satstat_pointer();
 
 void release_simulator(SimulatorWapper *sim);
 
 void release_task(SataccMinisatTask *task);
 
+/// solve the cnf file with the built-in solver and simulate it with `satacc_config.toml`, no trace is needed
+/// - the search stops after `max_rounds` propagation rounds, 0 means no limit
+bool run_cnf_expr(const char *path, size_t max_rounds);
+
 /// run full simulation and will  not delete the task
 bool run_full_expr(SataccMinisatTask *task);
 
@@ -185,13 +509,44 @@ bool run_full_expr(SataccMinisatTask *task);
 /// return still ok?
 bool run_single_task(SataccMinisatTask *task, SimulatorWapper *sim);
 
+/// replay the trace file with `satacc_config.toml`, the rounds are read lazily so the memory is bounded
+bool run_trace_expr(const char *path);
+
+/// the sampled simulation of the trace file with `satacc_config.toml`, the estimate is saved to `sampled_cycle.json`
+bool run_trace_sampled_expr(const char *path);
+
+/// called for each clause read by a watcher, with its number of literals
+void satstat_add_clause_length(Satstat *self, size_t clause_len);
+
 /// called every time a propagation occurs, that is, one watcher
 void satstat_add_watcher(Satstat *self, size_t num_clause_total, size_t num_clause_read);
 
+/// called for each watcher with its implication level in the decision, the decision is level 0
+void satstat_add_watcher_level(Satstat *self, size_t level);
+
 void save_data(const Satstat *self);
 
 void say_hello(const Point *point, const Rec *rect);
 
+/// set the addr of the value written by the last assignment
+BuilderStatus set_assignment_value_addr(SataccMinisatTask *self, uint64_t value_addr);
+
+/// the clause of the last watcher is conflicting
+BuilderStatus set_clause_conflict(SataccMinisatTask *self);
+
+/// the clause of the last watcher moves the watcher to the watch list of literal `lit`
+BuilderStatus set_clause_new_watch(SataccMinisatTask *self,
+                                   size_t lit,
+                                   uint64_t append_addr,
+                                   uint64_t compact_addr);
+
+/// the clause of the last watcher moves the watcher to the watch list of literal `lit` as its `append_index`th watcher,
+/// the source watch list is compacted at `compact_index`
+BuilderStatus set_clause_new_watch_by_id(SataccMinisatTask *self,
+                                         size_t lit,
+                                         size_t append_index,
+                                         size_t compact_index);
+
 void set_x(Point *self, int32_t x);
 
 void set_y(Point *self, int32_t y);
@@ -202,6 +557,9 @@ void show_data(const Satstat *self);
 
 void start_new_assgin(SataccMinisatTask *self);
 
+/// write the following builder calls to the trace file at `path`, return false if it cannot be created
+bool start_trace(SataccMinisatTask *self, const char *path);
+
 } // extern "C"
 
 } // namespace sjqrusttools
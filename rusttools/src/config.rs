@@ -1,11 +1,16 @@
-use eyre::{Context, Result};
+use eyre::{bail, Context, Result};
 use libc::c_char;
+#[cfg(feature = "ramulator")]
 use ramulator_wrapper::PresetConfigs;
 use std::{ffi::CStr, fs};
 
 use serde::{Deserialize, Serialize};
 
-use crate::satacc::{simulator::RunMode, CacheConfig};
+use crate::satacc::{
+    simulator::RunMode, CacheConfig, ChannelMapping, ClauseTimingConfig, CoherenceConfig,
+    CoherenceProtocol, ConflictConfig, FastForwardConfig, FunctionalConfig, LayoutConfig,
+    NucaConfig, PipelineConfig, PrefetcherType, SamplingConfig, ScratchpadConfig,
+};
 
 /// The type for the watcher sending to the clase
 #[repr(C)]
@@ -33,11 +38,35 @@ pub enum IcntType {
     Ring,
     Ideal,
 }
+/// the presets of the ramulator, the same as `ramulator_wrapper::PresetConfigs`,
+/// so the config files are still valid without the `ramulator` feature
+#[cfg(not(feature = "ramulator"))]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum PresetConfigs {
+    ALDRAM,
+    DDR4,
+    GDDR5,
+    LPDDR3,
+    PCM,
+    STTMRAM,
+    WideIO2,
+    DDR3,
+    DSARP,
+    HBM,
+    LPDDR4,
+    SALP,
+    TLDRAM,
+    WideIO,
+}
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[repr(C)]
 pub enum CacheType {
     Simple,
+    /// the cache with the ramulator of `ramu_cache_config`, it needs the `ramulator` feature
     Ramu,
+    /// the cache with the built-in dram model of `dram_config`
+    Dram,
 }
 /// the config for satacc
 ///
@@ -73,8 +102,34 @@ pub struct Config {
     pub l3_hit_latency: usize,
     pub miss_latency: usize,
     pub init_running_mode: RunMode,
+    /// profile the reuse distance and the working set of the requests to the l3 cache
+    pub mem_profiler: bool,
+    /// the cycles of each window of the working set
+    pub mem_profiler_window: usize,
     pub private_cache_config: CacheConfig,
     pub l3_cache_config: CacheConfig,
+    /// the scratchpad for the variable values, the value requests in its range will not go to the l3 cache
+    pub scratchpad_config: ScratchpadConfig,
+    /// the distributed l3 cache, it replaces the partitions of the l3 cache when enabled
+    pub nuca_config: NucaConfig,
+    /// read the values through the private caches and keep them fresh
+    pub coherence_config: CoherenceConfig,
+    /// the layout model of the tasks created by `create_task_with_layout`
+    pub layout_config: LayoutConfig,
+    /// the clusters and the samples of the sampled simulation
+    pub sampling_config: SamplingConfig,
+    /// fast-forward the rounds out of the detailed windows
+    pub fast_forward_config: FastForwardConfig,
+    /// the closed-loop mode, the clause units evaluate the clauses and imply the assignments
+    pub functional_config: FunctionalConfig,
+    /// stop the round at the first conflict
+    pub conflict_config: ConflictConfig,
+    /// the blocker check of the watchers
+    pub watcher_pipeline: PipelineConfig,
+    /// the clause evaluation of the clause units, the latency is added to the processing time of the clause
+    pub clause_pipeline: PipelineConfig,
+    /// derive the processing time of the clauses from the literals
+    pub clause_timing_config: ClauseTimingConfig,
 }
 impl Default for Config {
     /// build a default config with 16x16 pes, 1k private cache ,16M l3 cache
@@ -105,20 +160,70 @@ impl Default for Config {
             channel_size: 16,
             l3_cache_type: CacheType::Simple,
             init_running_mode: RunMode::RealRoundGap,
-            ramu_cache_config: ramulator_wrapper::PresetConfigs::HBM,
+            mem_profiler: false,
+            mem_profiler_window: 10000,
+            ramu_cache_config: PresetConfigs::HBM,
             private_cache_config: CacheConfig {
                 sets: 4,
                 associativity: 4,
                 block_size: 64,
                 channels: 1,
+                channel_mapping: ChannelMapping::BitSlice,
+                channel_busy_cycles: 0,
                 alway_hit: false,
+                mshr_entries: 2048,
+                mshr_max_targets: 64,
+                prefetcher: PrefetcherType::NoPrefetch,
+                prefetch_degree: 1,
+                miss_bytes_per_cycle: 0,
+                req_kind_policies: Default::default(),
             },
             l3_cache_config: CacheConfig {
                 sets: 65536,
                 associativity: 4,
                 block_size: 64,
                 channels: 8,
+                channel_mapping: ChannelMapping::BitSlice,
+                channel_busy_cycles: 0,
                 alway_hit: false,
+                mshr_entries: 2048,
+                mshr_max_targets: 64,
+                prefetcher: PrefetcherType::NoPrefetch,
+                prefetch_degree: 1,
+                miss_bytes_per_cycle: 0,
+                req_kind_policies: Default::default(),
+            },
+            scratchpad_config: ScratchpadConfig {
+                enable: false,
+                base_addr: 0,
+                capacity: 1 << 20,
+                banks: 8,
+                bank_width: 64,
+                latency: 2,
+            },
+            nuca_config: NucaConfig {
+                enable: false,
+                local_watch_lists: false,
+            },
+            coherence_config: CoherenceConfig {
+                private_value_cache: false,
+                protocol: CoherenceProtocol::DirectoryInvalidate,
+                message_bytes: 8,
+                bytes_per_cycle: 0,
+                latency: 4,
+            },
+            layout_config: LayoutConfig::default(),
+            sampling_config: SamplingConfig::default(),
+            fast_forward_config: FastForwardConfig::default(),
+            functional_config: FunctionalConfig::default(),
+            conflict_config: ConflictConfig::default(),
+            watcher_pipeline: PipelineConfig::single(2),
+            clause_pipeline: PipelineConfig::single(1),
+            clause_timing_config: ClauseTimingConfig {
+                enable: false,
+                setup_cycles: 2,
+                lanes: 4,
+                early_exit: true,
             },
             l1_hit_latency: 1,
             l3_hit_latency: 15,
@@ -132,8 +237,64 @@ impl Config {
         let config_file = fs::read_to_string(config_file).wrap_err("cannot read config file")?;
         let config: Config =
             toml::from_str(&config_file).wrap_err("cannot deserialize to Config")?;
+        config.validate()?;
         Ok(config)
     }
+    /// reject the configs that would panic in the simulation
+    pub fn validate(&self) -> Result<()> {
+        if self.scratchpad_config.banks == 0 || self.scratchpad_config.bank_width == 0 {
+            bail!(
+                "the scratchpad should have at least one bank and the bank width should not be 0"
+            );
+        }
+        if self.mem_profiler_window == 0 {
+            bail!("the window of the mem profiler should not be 0");
+        }
+        if self.nuca_config.enable && !matches!(self.l3_cache_type, CacheType::Simple) {
+            bail!(
+                "the nuca l3 cache only supports the Simple cache type, got {:?}",
+                self.l3_cache_type
+            );
+        }
+        // each watcher has one l3 slice, the sets are evenly split into the slices
+        let l3_sets = self.l3_cache_config.sets;
+        let n_watchers = self.n_watchers as u64;
+        if self.nuca_config.enable
+            && (n_watchers == 0 || l3_sets < n_watchers || !l3_sets.is_multiple_of(n_watchers))
+        {
+            bail!(
+                "the nuca l3 cache should split its {l3_sets} sets evenly into the {n_watchers} slices"
+            );
+        }
+        if cfg!(not(feature = "ramulator")) && matches!(self.l3_cache_type, CacheType::Ramu) {
+            bail!(
+                "the Ramu cache type needs the ramulator feature, use the Dram cache type instead"
+            );
+        }
+        if self.sampling_config.samples_per_cluster < 2 {
+            bail!("the sampling needs at least 2 samples per cluster to estimate the variance");
+        }
+        for (name, cache_config) in [
+            ("private", &self.private_cache_config),
+            ("l3", &self.l3_cache_config),
+        ] {
+            if cache_config.channels == 0
+                || !cache_config.sets.is_multiple_of(cache_config.channels)
+            {
+                bail!(
+                    "the sets of the {name} cache should be evenly split into the channels, got {} sets and {} channels",
+                    cache_config.sets,
+                    cache_config.channels
+                );
+            }
+            if cache_config.mshr_entries == 0 || cache_config.mshr_max_targets == 0 {
+                bail!(
+                    "the mshr of the {name} cache should have at least one entry and one target per entry"
+                );
+            }
+        }
+        Ok(())
+    }
 
     #[no_mangle]
     pub extern "C" fn show_config(&self) {
@@ -156,7 +317,54 @@ impl Config {
 mod test {
     use std::fs;
 
-    use super::Config;
+    use super::{CacheType, Config};
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+        let mut config = Config::default();
+        config.scratchpad_config.banks = 0;
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.nuca_config.enable = true;
+        assert!(config.validate().is_ok());
+        config.l3_cache_type = CacheType::Dram;
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.nuca_config.enable = true;
+        config.l3_cache_config.sets = config.n_watchers as u64 / 2;
+        assert!(config.validate().is_err());
+        config.l3_cache_config.sets = config.n_watchers as u64 * 3 / 2;
+        assert!(config.validate().is_err());
+        let config = Config {
+            mem_profiler_window: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.sampling_config.samples_per_cluster = 1;
+        assert!(config.validate().is_err());
+        let config = Config {
+            l3_cache_type: CacheType::Ramu,
+            ..Default::default()
+        };
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "ramulator"));
+        let mut config = Config::default();
+        config.private_cache_config.channels = 0;
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.l3_cache_config.channels = 0;
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.l3_cache_config.sets = config.l3_cache_config.channels * 3 + 1;
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.private_cache_config.mshr_entries = 0;
+        assert!(config.validate().is_err());
+        let mut config = Config::default();
+        config.l3_cache_config.mshr_max_targets = 0;
+        assert!(config.validate().is_err());
+    }
 
     #[test]
     #[ignore]
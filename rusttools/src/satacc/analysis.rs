@@ -0,0 +1,320 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+};
+
+use eyre::{bail, Context, Result};
+use libc::c_char;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+use super::{
+    satacc_minisat_task::{SataccMinisatTask, SingleRoundTask},
+    statistics::AverageStat,
+    task_source::TaskSource,
+    trace::{path_from_c, TraceReader},
+};
+
+/// the count of each value
+pub type Histogram = BTreeMap<usize, usize>;
+
+/// the load of a watcher pe
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PeLoad {
+    pub assignments: usize,
+    pub watchers: usize,
+    pub clauses: usize,
+    pub clause_values: usize,
+}
+
+/// the accesses to an address region
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Footprint {
+    pub accesses: usize,
+    pub unique_lines: usize,
+    pub min_addr: Option<u64>,
+    pub max_addr: Option<u64>,
+}
+
+/// # TaskReport
+/// the offline statistics of the rounds, saved as json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskReport {
+    pub rounds: usize,
+    pub assignments: usize,
+    pub watchers: usize,
+    pub clauses: usize,
+    /// the watchers that only read the blocker
+    pub blocker_only_fraction: f64,
+    pub average_assignments_per_round: AverageStat,
+    pub average_watchers_per_assignment: AverageStat,
+    pub average_clause_length: AverageStat,
+    pub assignments_per_round: Histogram,
+    pub watchers_per_assignment: Histogram,
+    /// the number of literals of each clause, the values read by the clause if the trace has no literals
+    pub clause_lengths: Histogram,
+    /// the assignments of each level
+    pub levels: Histogram,
+    /// the number of levels of each round
+    pub levels_per_round: Histogram,
+    /// the load of each pe under `watcher_id / 2 % total_watchers`
+    pub pe_loads: Vec<PeLoad>,
+    /// the max load of the pes over the average, 1 is balanced
+    pub pe_imbalance: f64,
+    /// the footprint of each address region
+    pub footprints: BTreeMap<String, Footprint>,
+}
+impl TaskReport {
+    pub fn save(&self, path: &str) -> Result<()> {
+        let file = File::create(path).wrap_err_with(|| format!("cannot create report {path}"))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+    /// analyze the trace file with the watchers and the cache line of `satacc_config.toml`, the report is `trace_report.json`
+    #[no_mangle]
+    pub extern "C" fn analyze_trace_expr(path: *const c_char) -> bool {
+        tracing_subscriber::fmt::try_init().unwrap_or_default();
+        match path_from_c(path)
+            .and_then(|path| {
+                let config = Config::from_config_file("satacc_config.toml")?;
+                analyze_trace(
+                    path,
+                    config.n_watchers,
+                    config.private_cache_config.block_size,
+                )
+            })
+            .and_then(|report| report.save("trace_report.json"))
+        {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::error!("analysis error: {e:?}");
+                false
+            }
+        }
+    }
+}
+
+/// the unique lines of a region
+#[derive(Default)]
+struct RegionLines {
+    footprint: Footprint,
+    lines: HashSet<u64>,
+}
+impl RegionLines {
+    fn access(&mut self, addr: u64, block_size: u64) {
+        let footprint = &mut self.footprint;
+        footprint.accesses += 1;
+        footprint.min_addr = Some(footprint.min_addr.map_or(addr, |min| min.min(addr)));
+        footprint.max_addr = Some(footprint.max_addr.map_or(addr, |max| max.max(addr)));
+        self.lines.insert(addr / block_size);
+    }
+}
+
+/// # TaskAnalyzer
+/// go through the rounds without simulating them, the rounds can come from a task, a trace or any [`TaskSource`]
+pub struct TaskAnalyzer {
+    total_watchers: usize,
+    block_size: u64,
+    report: TaskReport,
+    regions: BTreeMap<&'static str, RegionLines>,
+}
+impl TaskAnalyzer {
+    /// the watchers and the block size should not be 0
+    pub fn new(total_watchers: usize, block_size: u64) -> Result<Self> {
+        if total_watchers == 0 || block_size == 0 {
+            bail!("invalid analyzer: {total_watchers} watchers, block size {block_size}");
+        }
+        Ok(Self {
+            total_watchers,
+            block_size,
+            report: TaskReport {
+                pe_loads: vec![PeLoad::default(); total_watchers],
+                ..Default::default()
+            },
+            regions: BTreeMap::new(),
+        })
+    }
+
+    fn access(&mut self, region: &'static str, addr: u64) {
+        self.regions
+            .entry(region)
+            .or_default()
+            .access(addr, self.block_size);
+    }
+
+    pub fn add_round(&mut self, round: &SingleRoundTask) {
+        let report = &mut self.report;
+        report.rounds += 1;
+        report
+            .average_assignments_per_round
+            .add(round.assignments.len());
+        *report
+            .assignments_per_round
+            .entry(round.assignments.len())
+            .or_default() += 1;
+        let mut round_levels = HashSet::new();
+        let mut accesses = vec![];
+        for assignment in &round.assignments {
+            let report = &mut self.report;
+            let watchers = assignment.single_watcher_tasks.len();
+            report.assignments += 1;
+            report.watchers += watchers;
+            report.average_watchers_per_assignment.add(watchers);
+            *report.watchers_per_assignment.entry(watchers).or_default() += 1;
+            *report.levels.entry(assignment.level).or_default() += 1;
+            round_levels.insert(assignment.level);
+            let pe_load = &mut report.pe_loads[assignment.get_watcher_pe_id(self.total_watchers)];
+            pe_load.assignments += 1;
+            pe_load.watchers += watchers;
+            accesses.push(("watch_meta", assignment.meta_data_addr));
+            accesses.push(("watch_data", assignment.watcher_addr));
+            if let Some(value_addr) = assignment.value_addr {
+                accesses.push(("assignment_value", value_addr));
+            }
+            for watcher in &assignment.single_watcher_tasks {
+                accesses.push(("blocker", watcher.blocker_addr));
+                let Some(clause_data) = &watcher.clause_data else {
+                    continue;
+                };
+                let values = clause_data.clause_value_addr.len();
+                let length = match clause_data.clause_lits.is_empty() {
+                    true => values,
+                    false => clause_data.clause_lits.len(),
+                };
+                report.clauses += 1;
+                report.average_clause_length.add(length);
+                *report.clause_lengths.entry(length).or_default() += 1;
+                pe_load.clauses += 1;
+                pe_load.clause_values += values;
+                accesses.push(("clause", clause_data.clause_addr));
+                for &value_addr in &clause_data.clause_value_addr {
+                    accesses.push(("clause_value", value_addr));
+                }
+            }
+        }
+        *self
+            .report
+            .levels_per_round
+            .entry(round_levels.len())
+            .or_default() += 1;
+        for (region, addr) in accesses {
+            self.access(region, addr);
+        }
+    }
+
+    /// analyze all the rounds of the source
+    pub fn add_source(&mut self, source: &mut impl TaskSource) -> Result<()> {
+        while let Some(round) = source.next_round()? {
+            self.add_round(&round);
+        }
+        Ok(())
+    }
+
+    /// analyze the rounds of the task without removing them
+    pub fn add_task(&mut self, task: &SataccMinisatTask) {
+        for round in &task.decisions {
+            self.add_round(round);
+        }
+    }
+
+    pub fn report(mut self) -> TaskReport {
+        let report = &mut self.report;
+        if report.watchers > 0 {
+            report.blocker_only_fraction =
+                (report.watchers - report.clauses) as f64 / report.watchers as f64;
+        }
+        let work = |load: &PeLoad| load.watchers + load.clause_values;
+        let max_work = report.pe_loads.iter().map(work).max().unwrap_or(0);
+        let total_work: usize = report.pe_loads.iter().map(work).sum();
+        if total_work > 0 {
+            report.pe_imbalance =
+                max_work as f64 * report.pe_loads.len() as f64 / total_work as f64;
+        }
+        for (region, lines) in self.regions {
+            self.report.footprints.insert(
+                region.to_string(),
+                Footprint {
+                    unique_lines: lines.lines.len(),
+                    ..lines.footprint
+                },
+            );
+        }
+        self.report
+    }
+}
+
+/// analyze the trace file lazily, only one round is in memory
+pub fn analyze_trace(path: &str, total_watchers: usize, block_size: u64) -> Result<TaskReport> {
+    let mut analyzer = TaskAnalyzer::new(total_watchers, block_size)?;
+    analyzer.add_source(&mut TraceReader::open(path)?)?;
+    Ok(analyzer.report())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::satacc::{functional::LitValue, trace::TraceWriter};
+
+    #[test]
+    fn test_analyze() {
+        let mut task = SataccMinisatTask::new();
+        task.start_new_assgin();
+        task.add_watcher_task(0, 64, 128, 3);
+        task.set_assignment_value_addr(4096);
+        task.add_single_watcher_task_no_clause(256, 3);
+        task.add_single_watcher_task(320, 1024, 7, 20, 3);
+        task.add_single_watcher_clause_value_addr(4100, 5);
+        task.add_single_watcher_clause_value_addr(4200, 6);
+        for lit in [10, 12, 14] {
+            task.add_single_watcher_clause_lit(lit, LitValue::False);
+        }
+        task.add_watcher_task(1, 512, 640, 4);
+        task.add_single_watcher_task_no_clause(260, 4);
+        task.start_new_assgin();
+        task.add_watcher_task(0, 512, 640, 4);
+
+        assert!(TaskAnalyzer::new(0, 64).is_err());
+        assert!(TaskAnalyzer::new(2, 0).is_err());
+        let mut analyzer = TaskAnalyzer::new(2, 64).unwrap();
+        analyzer.add_task(&task);
+        let report = analyzer.report();
+        assert_eq!(report.rounds, 2);
+        assert_eq!(report.assignments, 3);
+        assert_eq!(report.watchers, 3);
+        assert_eq!(report.clauses, 1);
+        assert!((report.blocker_only_fraction - 2. / 3.).abs() < 1e-9);
+        assert_eq!(
+            report.watchers_per_assignment,
+            [(0, 1), (1, 1), (2, 1)].into()
+        );
+        // the clause has 3 literals but reads 2 values
+        assert_eq!(report.clause_lengths, [(3, 1)].into());
+        assert_eq!(report.levels, [(0, 2), (1, 1)].into());
+        assert_eq!(report.levels_per_round, [(1, 1), (2, 1)].into());
+        // watcher 3 is on pe 1, watcher 4 is on pe 0
+        assert_eq!(report.pe_loads[1].watchers, 2);
+        assert_eq!(report.pe_loads[1].clause_values, 2);
+        assert_eq!(report.pe_loads[0].assignments, 2);
+        let blocker = &report.footprints["blocker"];
+        assert_eq!(blocker.accesses, 3);
+        // 256 and 260 are in the same line
+        assert_eq!(blocker.unique_lines, 2);
+        assert_eq!((blocker.min_addr, blocker.max_addr), (Some(256), Some(320)));
+        assert_eq!(report.footprints["watch_meta"].unique_lines, 2);
+
+        // the trace gives the same report
+        let mut writer = TraceWriter::new(Vec::new()).unwrap();
+        writer.write_task(&task).unwrap();
+        let bytes = writer.finish().unwrap();
+        let mut analyzer = TaskAnalyzer::new(2, 64).unwrap();
+        analyzer
+            .add_source(&mut TraceReader::new(bytes.as_slice()).unwrap())
+            .unwrap();
+        let trace_report = analyzer.report();
+        assert_eq!(
+            serde_json::to_string(&trace_report).unwrap(),
+            serde_json::to_string(&report).unwrap()
+        );
+    }
+}
@@ -1,42 +1,139 @@
 use std::collections::{BTreeMap, VecDeque};
 
 use crate::{
-    satacc::{icnt::IcntMsgWrapper, wating_task::WaitingTask, MemReq, SataccStatus},
+    satacc::{
+        icnt::IcntMsgWrapper, statistics::CacheStatistics, wating_task::WaitingTask,
+        CoherenceProtocol, MemReq, MemReqKind, SataccStatus,
+    },
     sim::{InOutPort, SimComponent},
 };
 
-use super::{AccessResult, CacheConfig, CacheId, FastCache};
+use super::{
+    mshr::{Mshr, MshrAddResult},
+    prefetcher::PrefetchUnit,
+    AccessResult, CacheConfig, CacheId, FastCache,
+};
 
 /// the cache with fix time simulator
 /// the hit latency is fixed
 /// when miss, the latency will be `miss_latency`
+/// - the missed lines are tracked by the [`Mshr`], when it's full, the request will stall in the in port
+/// - when `miss_bytes_per_cycle` is set, the missed lines queue up on the channels of the next level,
+///   the queueing and transfer time is added to `miss_latency`
+/// - the evicted dirty lines are written back, they take the bandwidth of the next level but no one waits for them
+/// - the response leaves from the port of the request's partition, a single port cache (like a nuca slice) uses its only port
 pub struct CacheWithFixTime {
     pub fast_cache: FastCache,
     pub req_ports: Vec<InOutPort<IcntMsgWrapper<MemReq>>>,
-    pub on_going_reqs: WaitingTask<u64>,
-    pub tag_to_reqs: BTreeMap<u64, Vec<MemReq>>,
+    /// the hit requests that waiting for `hit_latency`
+    pub on_going_reqs: WaitingTask<MemReq>,
+    /// the missed tags that waiting for `miss_latency`
+    pub on_going_misses: WaitingTask<u64>,
+    pub mshr: Mshr,
+    pub prefetch_unit: PrefetchUnit,
     pub hit_latency: usize,
     pub miss_latency: usize,
     pub ready_reqs: VecDeque<MemReq>,
     pub cache_id: CacheId,
+    /// the arriving cycle of each request, used to get the latency of each kind of request
+    pub req_arrival_cycle: BTreeMap<usize, usize>,
+    pub miss_queue: MissQueue,
+}
+
+/// the channels of the next level that the missed lines queue up on, each partition of the cache has its channel
+pub struct MissQueue {
+    bytes_per_cycle: u64,
+    block_size: u64,
+    /// the time each channel finishes its queued lines, in bytes(cycle * bytes_per_cycle)
+    channel_busy_until: Vec<u64>,
+}
+impl MissQueue {
+    /// the queue of the cache that serves `num_partitions` memory partitions
+    pub fn new(config: &CacheConfig, num_partitions: usize) -> Self {
+        assert!(
+            num_partitions > 0,
+            "the cache should serve at least one partition"
+        );
+        Self {
+            bytes_per_cycle: config.miss_bytes_per_cycle,
+            block_size: config.block_size,
+            channel_busy_until: vec![0; num_partitions],
+        }
+    }
+    /// queue the missed line of the partition `mem_id`, return the cycles to wait and transfer it, 0 if the bandwidth is unlimited
+    pub fn push(
+        &mut self,
+        mem_id: usize,
+        current_cycle: usize,
+        stat: &mut CacheStatistics,
+    ) -> usize {
+        if self.bytes_per_cycle == 0 {
+            return 0;
+        }
+        let queue_delay = self.occupy(mem_id, current_cycle);
+        stat.miss_queue_delay.add(queue_delay);
+        queue_delay
+    }
+    /// queue the written back line of the partition `mem_id`, it only delays the later lines
+    pub fn push_writeback(
+        &mut self,
+        mem_id: usize,
+        current_cycle: usize,
+        stat: &mut CacheStatistics,
+    ) {
+        stat.writebacks += 1;
+        if self.bytes_per_cycle != 0 {
+            self.occupy(mem_id, current_cycle);
+        }
+    }
+    /// occupy the channel of the partition for a line, return the cycles until the line is transferred
+    fn occupy(&mut self, mem_id: usize, current_cycle: usize) -> usize {
+        let channel = mem_id % self.channel_busy_until.len();
+        let start =
+            (current_cycle as u64 * self.bytes_per_cycle).max(self.channel_busy_until[channel]);
+        let finish = start + self.block_size;
+        self.channel_busy_until[channel] = finish;
+        finish.div_ceil(self.bytes_per_cycle) as usize - current_cycle
+    }
 }
 impl CacheWithFixTime {
+    /// - `num_partitions`: the number of the memory partitions that the cache serves, each one has its miss queue
     pub fn new(
         config: &CacheConfig,
         req_ports: Vec<InOutPort<IcntMsgWrapper<MemReq>>>,
         hit_latency: usize,
         miss_latency: usize,
         cache_id: CacheId,
+        num_partitions: usize,
     ) -> Self {
         Self {
             fast_cache: FastCache::new(config),
             req_ports,
             on_going_reqs: WaitingTask::new(),
-            tag_to_reqs: BTreeMap::new(),
+            on_going_misses: WaitingTask::new(),
+            mshr: Mshr::new(
+                config.mshr_entries as usize,
+                config.mshr_max_targets as usize,
+            ),
+            prefetch_unit: PrefetchUnit::new(config),
             hit_latency,
             miss_latency,
             ready_reqs: VecDeque::new(),
             cache_id,
+            req_arrival_cycle: BTreeMap::new(),
+            miss_queue: MissQueue::new(config, num_partitions),
+        }
+    }
+    /// the private cache tracks its value lines in the directory
+    fn directory_pe_id(&self, shared_status: &SataccStatus) -> Option<usize> {
+        match self.cache_id {
+            CacheId::PrivateCache(watcher_pe_id)
+                if shared_status.statistics.config.coherence_config.protocol
+                    == CoherenceProtocol::DirectoryInvalidate =>
+            {
+                Some(watcher_pe_id)
+            }
+            _ => None,
         }
     }
 }
@@ -48,70 +145,189 @@ impl SimComponent for CacheWithFixTime {
         shared_status: &mut Self::SharedStatus,
         current_cycle: usize,
     ) -> (bool, bool) {
-        let mut busy = !self.on_going_reqs.is_empty();
+        let mut busy = !self.on_going_reqs.is_empty() || !self.mshr.is_empty();
         let mut updated = false;
+        let directory_pe_id = self.directory_pe_id(shared_status);
         // first check if there is any request in the in_req_queues, if find, access it
         for InOutPort {
             in_port,
             out_port: _,
         } in &mut self.req_ports
         {
-            if self.on_going_reqs.len() < 2048 {
-                if let Ok(IcntMsgWrapper {
-                    msg,
-                    mem_target_port: _,
-                }) = in_port.recv()
-                {
-                    tracing::debug!("cache receive request {:?}", msg.id);
-                    match self.fast_cache.access(msg.addr) {
-                        AccessResult::Hit(tag) => {
-                            // if it's hit, if the tag is in the tag_to_reqs, means it's already in on_going_reqs, just add this req to tag_to_reqs
-                            // if the tag is not in the tag_to_reqs, means it's not in on_going_reqs, add it to on_going_reqs and tag_to_reqs
-                            shared_status.statistics.update_hit(&self.cache_id);
-                            match self.tag_to_reqs.get_mut(&tag) {
-                                Some(entry) => {
-                                    entry.push(msg);
-                                }
-                                None => {
-                                    // no tag in record, add it!
-                                    // the latency will be hit_latency
-                                    self.on_going_reqs
-                                        .push(tag, current_cycle + self.hit_latency);
-                                    self.tag_to_reqs.insert(tag, vec![msg]);
-                                }
-                            }
+            if let Ok(IcntMsgWrapper {
+                msg,
+                mem_target_port,
+            }) = in_port.recv()
+            {
+                busy = true;
+                tracing::debug!("cache receive request {:?}", msg.id);
+                let tag = self.fast_cache.get_line_addr(msg.addr);
+                let in_flight = self.mshr.contains(tag);
+                // a hit that not in flight do not need the mshr
+                if !self.mshr.can_accept(tag) && (in_flight || !self.fast_cache.probe(msg.addr)) {
+                    tracing::debug!("cache is busy with {} missed lines", self.mshr.len());
+                    if in_flight {
+                        shared_status
+                            .statistics
+                            .update_mshr_target_full(&self.cache_id);
+                    } else {
+                        shared_status
+                            .statistics
+                            .update_mshr_entry_full(&self.cache_id);
+                    }
+                    in_port.ret(IcntMsgWrapper {
+                        msg,
+                        mem_target_port,
+                    });
+                    continue;
+                }
+                if !self.fast_cache.try_reserve_channel(msg.addr, current_cycle) {
+                    tracing::debug!("cache channel conflict at cycle: {current_cycle}");
+                    shared_status
+                        .statistics
+                        .update_channel_conflict(&self.cache_id);
+                    in_port.ret(IcntMsgWrapper {
+                        msg,
+                        mem_target_port,
+                    });
+                    continue;
+                }
+                let req_kind = msg.req_type.get_kind();
+                let (result, evicted) = self.fast_cache.access_with_evict(msg.addr, req_kind);
+                let mem_id = msg.mem_id;
+                let stat = shared_status
+                    .statistics
+                    .get_cache_statistics_mut(&self.cache_id);
+                if let Some(evicted) = evicted {
+                    self.prefetch_unit.on_evict(evicted.addr, stat);
+                    if evicted.dirty {
+                        self.miss_queue.push_writeback(mem_id, current_cycle, stat);
+                    }
+                }
+                self.prefetch_unit.on_demand_access(&msg, tag, stat);
+                if let (Some(evicted), Some(watcher_pe_id)) = (evicted, directory_pe_id) {
+                    shared_status
+                        .value_directory
+                        .remove_sharer(evicted.addr, watcher_pe_id);
+                }
+                self.req_arrival_cycle.insert(msg.id, current_cycle);
+                match result {
+                    AccessResult::Hit(tag) => {
+                        // if the tag is in the mshr, the line is still on the way, just merge it
+                        if in_flight {
+                            shared_status.statistics.update_mshr_hit(
+                                &self.cache_id,
+                                req_kind,
+                                true,
+                            );
+                            let result = self.mshr.add(tag, msg);
+                            assert!(matches!(result, Ok(MshrAddResult::Merged)));
+                        } else {
+                            shared_status
+                                .statistics
+                                .update_hit(&self.cache_id, req_kind);
+                            self.on_going_reqs
+                                .push(msg, current_cycle + self.hit_latency);
                         }
-                        AccessResult::Miss(tag) => {
-                            shared_status.statistics.update_miss(&self.cache_id);
-                            match self.tag_to_reqs.get_mut(&tag) {
-                                Some(entry) => {
-                                    entry.push(msg);
+                    }
+                    AccessResult::Miss(tag) => match self.mshr.add(tag, msg) {
+                        Ok(MshrAddResult::NewEntry) => {
+                            shared_status
+                                .statistics
+                                .update_miss(&self.cache_id, req_kind);
+                            if self.fast_cache.is_bypass(req_kind) {
+                                shared_status
+                                    .statistics
+                                    .update_bypass(&self.cache_id, req_kind);
+                                // no line holds the written data, write it through
+                                if req_kind.is_write() {
+                                    self.miss_queue.push_writeback(
+                                        mem_id,
+                                        current_cycle,
+                                        shared_status
+                                            .statistics
+                                            .get_cache_statistics_mut(&self.cache_id),
+                                    );
                                 }
-                                None => {
-                                    self.on_going_reqs
-                                        .push(tag, current_cycle + self.miss_latency);
-                                    self.tag_to_reqs.insert(tag, vec![msg]);
+                            } else if req_kind == MemReqKind::ClauseValue {
+                                if let Some(watcher_pe_id) = directory_pe_id {
+                                    shared_status.value_directory.add_sharer(tag, watcher_pe_id);
                                 }
                             }
+                            let queue_delay = self.miss_queue.push(
+                                mem_id,
+                                current_cycle,
+                                shared_status
+                                    .statistics
+                                    .get_cache_statistics_mut(&self.cache_id),
+                            );
+                            self.on_going_misses
+                                .push(tag, current_cycle + self.miss_latency + queue_delay);
                         }
-                    }
-                    busy = true;
-                    updated = true;
+                        Ok(MshrAddResult::Merged) => {
+                            shared_status.statistics.update_mshr_hit(
+                                &self.cache_id,
+                                req_kind,
+                                false,
+                            );
+                        }
+                        Err(_) => unreachable!("the mshr is checked before access"),
+                    },
+                }
+                updated = true;
+            }
+        }
+        // issue one prefetch each cycle
+        let stat = shared_status
+            .statistics
+            .get_cache_statistics_mut(&self.cache_id);
+        if let Some(prefetch) =
+            self.prefetch_unit
+                .issue_prefetch(&mut self.fast_cache, &mut self.mshr, stat)
+        {
+            busy = true;
+            updated = true;
+            let mem_id = prefetch.mem_id;
+            if prefetch.evicted.is_some_and(|evicted| evicted.dirty) {
+                self.miss_queue.push_writeback(mem_id, current_cycle, stat);
+            }
+            let queue_delay = self.miss_queue.push(mem_id, current_cycle, stat);
+            self.on_going_misses.push(
+                prefetch.line_addr,
+                current_cycle + self.miss_latency + queue_delay,
+            );
+            // the prefetched value line is tracked like a demand miss
+            if let Some(watcher_pe_id) = directory_pe_id {
+                let directory = &mut shared_status.value_directory;
+                if let Some(evicted) = prefetch.evicted {
+                    directory.remove_sharer(evicted.addr, watcher_pe_id);
+                }
+                if prefetch.kind == MemReqKind::ClauseValue {
+                    directory.add_sharer(prefetch.line_addr, watcher_pe_id);
                 }
-            } else {
-                tracing::debug!("cache is busy with {} requests", self.on_going_reqs.len());
             }
         }
         // then check if there is any request in the on_going_reqs
-        while let Some((leaving_cycle, tag)) = self.on_going_reqs.pop() {
+        while let Some((leaving_cycle, req)) = self.on_going_reqs.pop() {
             busy = true;
             updated = true;
             if leaving_cycle > current_cycle {
-                self.on_going_reqs.push(tag, leaving_cycle);
+                self.on_going_reqs.push(req, leaving_cycle);
                 break;
             } else {
-                self.ready_reqs
-                    .extend(self.tag_to_reqs.remove(&tag).unwrap());
+                self.ready_reqs.push_back(req);
+            }
+        }
+        // then check if any missed line is filled
+        while let Some((leaving_cycle, tag)) = self.on_going_misses.pop() {
+            busy = true;
+            updated = true;
+            if leaving_cycle > current_cycle {
+                self.on_going_misses.push(tag, leaving_cycle);
+                break;
+            } else {
+                self.prefetch_unit.on_fill(tag);
+                self.ready_reqs.extend(self.mshr.fill(tag).unwrap());
             }
         }
         // then push ready queue to out
@@ -119,8 +335,10 @@ impl SimComponent for CacheWithFixTime {
         while let Some(req) = self.ready_reqs.pop_front() {
             busy = true;
             tracing::debug!("send req: {:?} at cycle: {current_cycle}", req.id);
-            let out_id = req.mem_id;
+            let out_id = req.mem_id % self.req_ports.len();
             let wathcer_id = req.watcher_pe_id;
+            let req_id = req.id;
+            let req_kind = req.req_type.get_kind();
             let req = IcntMsgWrapper {
                 msg: req,
                 mem_target_port: wathcer_id,
@@ -128,6 +346,12 @@ impl SimComponent for CacheWithFixTime {
             match self.req_ports[out_id].out_port.send(req) {
                 Ok(_) => {
                     updated = true;
+                    let arrival_cycle = self.req_arrival_cycle.remove(&req_id).unwrap();
+                    shared_status.statistics.update_cache_latency(
+                        &self.cache_id,
+                        req_kind,
+                        current_cycle - arrival_cycle,
+                    );
                 }
                 Err(e) => {
                     // cannot send to cache now
@@ -151,7 +375,10 @@ impl SimComponent for CacheWithFixTime {
 mod test {
     use crate::{
         config::Config,
-        satacc::{cache::fast_cache::CacheConfig, MemReqType},
+        satacc::{
+            cache::fast_cache::{CacheConfig, ReqKindPolicies, ReqKindPolicy},
+            MemReqKind, MemReqType, PrefetcherType,
+        },
         sim::{ChannelBuilder, SimRunner},
         test_utils,
     };
@@ -162,23 +389,14 @@ mod test {
         test_utils::init();
         let channel_builder = ChannelBuilder::new();
         let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 2);
-        let cache = CacheWithFixTime {
-            fast_cache: FastCache::new(&CacheConfig {
-                sets: 2,
-                associativity: 2,
-                block_size: 4,
-                channels: 1,
-                alway_hit: false,
-            }),
-
-            on_going_reqs: WaitingTask::new(),
-            hit_latency: 14,
-            miss_latency: 120,
-            tag_to_reqs: BTreeMap::new(),
-            ready_reqs: VecDeque::new(),
-            req_ports: inout_cache,
-            cache_id: CacheId::L3Cache,
-        };
+        let cache = CacheWithFixTime::new(
+            &test_utils::cache_config(),
+            inout_cache,
+            14,
+            120,
+            CacheId::L3Cache,
+            1,
+        );
         let config = Config::default();
         let mut status = SataccStatus::new(config);
         for i in 0..10 {
@@ -191,6 +409,7 @@ mod test {
                         mem_id: 0,
                         id: status.next_mem_id(),
                         req_type: MemReqType::WatcherReadData,
+                        next_addr: None,
                         watcher_pe_id: 0,
                     },
                     mem_target_port: 1,
@@ -201,4 +420,473 @@ mod test {
         let mut sim_runner = SimRunner::new(cache, status);
         sim_runner.run().unwrap();
     }
+
+    #[test]
+    fn test_mshr_stall() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 1);
+        let cache = CacheWithFixTime::new(
+            &CacheConfig {
+                mshr_entries: 1,
+                mshr_max_targets: 1,
+                ..test_utils::cache_config()
+            },
+            inout_cache,
+            14,
+            120,
+            CacheId::L3Cache,
+            1,
+        );
+        let mut status = SataccStatus::new(Config::default());
+        // two requests to the same line and one to another line
+        for addr in [0, 1, 4] {
+            inout_base[0]
+                .out_port
+                .send(IcntMsgWrapper {
+                    msg: MemReq {
+                        addr,
+                        is_write: false,
+                        mem_id: 0,
+                        id: status.next_mem_id(),
+                        req_type: MemReqType::WatcherReadData,
+                        next_addr: None,
+                        watcher_pe_id: 0,
+                    },
+                    mem_target_port: 1,
+                })
+                .unwrap();
+        }
+        let mut sim_runner = SimRunner::new(cache, status);
+        sim_runner.run().unwrap();
+        // every request is served one by one
+        for _ in 0..3 {
+            inout_base[0].in_port.recv().unwrap();
+        }
+        let stat = &sim_runner
+            .get_shared_status()
+            .statistics
+            .l3_cache_statistics;
+        assert!(stat.mshr_target_full_stalls > 0);
+        assert!(sim_runner.get_current_cycle() > 240);
+    }
+
+    #[test]
+    fn test_miss_bandwidth() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 1);
+        let cache = CacheWithFixTime::new(
+            &CacheConfig {
+                miss_bytes_per_cycle: 1,
+                ..test_utils::cache_config()
+            },
+            inout_cache,
+            14,
+            120,
+            CacheId::L3Cache,
+            1,
+        );
+        let mut status = SataccStatus::new(Config::default());
+        // four missed lines, each takes 4 cycles on the channel
+        for addr in [0, 4, 8, 12] {
+            inout_base[0]
+                .out_port
+                .send(IcntMsgWrapper {
+                    msg: MemReq {
+                        addr,
+                        is_write: false,
+                        mem_id: 0,
+                        id: status.next_mem_id(),
+                        req_type: MemReqType::WatcherReadData,
+                        next_addr: None,
+                        watcher_pe_id: 0,
+                    },
+                    mem_target_port: 1,
+                })
+                .unwrap();
+        }
+        let mut sim_runner = SimRunner::new(cache, status);
+        sim_runner.run().unwrap();
+        let stat = &sim_runner
+            .get_shared_status()
+            .statistics
+            .l3_cache_statistics;
+        // the runner steps two cycles each update, so the misses arrive at cycle 0,2,4,6 and finish at cycle 4,8,12,16
+        assert_eq!(
+            stat.miss_queue_delay.get_average(),
+            (4 + 6 + 8 + 10) as f64 / 4.0
+        );
+        assert!(sim_runner.get_current_cycle() > 120 + 16);
+    }
+    #[test]
+    fn test_miss_queue_partition() {
+        let mut miss_queue = MissQueue::new(
+            &CacheConfig {
+                block_size: 4,
+                miss_bytes_per_cycle: 1,
+                ..Config::default().l3_cache_config
+            },
+            2,
+        );
+        let mut stat = CacheStatistics::default();
+        // the two partitions have their own channels
+        assert_eq!(miss_queue.push(0, 0, &mut stat), 4);
+        assert_eq!(miss_queue.push(1, 0, &mut stat), 4);
+        // the partition 2 shares the channel with the partition 0
+        assert_eq!(miss_queue.push(2, 0, &mut stat), 8);
+    }
+    #[test]
+    fn test_per_kind_statistics() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 1);
+        let cache = CacheWithFixTime::new(
+            &test_utils::cache_config(),
+            inout_cache,
+            14,
+            120,
+            CacheId::L3Cache,
+            1,
+        );
+        let mut status = SataccStatus::new(Config::default());
+        // the clause data misses, the value merges into it, then the value hits after the fill
+        let reqs = [
+            (0, MemReqType::ClauseReadData(0)),
+            (1, MemReqType::ClauseReadValue(0)),
+        ];
+        for (addr, req_type) in reqs {
+            inout_base[0]
+                .out_port
+                .send(IcntMsgWrapper {
+                    msg: MemReq {
+                        addr,
+                        is_write: false,
+                        mem_id: 0,
+                        id: status.next_mem_id(),
+                        req_type,
+                        next_addr: None,
+                        watcher_pe_id: 0,
+                    },
+                    mem_target_port: 1,
+                })
+                .unwrap();
+        }
+        let mut sim_runner = SimRunner::new(cache, status);
+        sim_runner.run().unwrap();
+        let id = sim_runner.get_shared_status_mut().next_mem_id();
+        inout_base[0]
+            .out_port
+            .send(IcntMsgWrapper {
+                msg: MemReq {
+                    addr: 2,
+                    is_write: false,
+                    mem_id: 0,
+                    id,
+                    req_type: MemReqType::ClauseReadValue(0),
+                    next_addr: None,
+                    watcher_pe_id: 0,
+                },
+                mem_target_port: 1,
+            })
+            .unwrap();
+        sim_runner.run().unwrap();
+        let stat = &sim_runner
+            .get_shared_status()
+            .statistics
+            .l3_cache_statistics;
+        let clause_data = &stat.per_kind[&MemReqKind::ClauseData];
+        assert_eq!(clause_data.misses, 1);
+        assert_eq!(clause_data.latency.get_average(), 120.0);
+        let clause_value = &stat.per_kind[&MemReqKind::ClauseValue];
+        assert_eq!(clause_value.mshr_hits, 1);
+        assert_eq!(clause_value.hits, 2);
+        assert_eq!(clause_value.latency.count, 2);
+        // the merged value found the tag, the kinds and the totals count it as a hit
+        assert_eq!(stat.cache_misses, 1);
+        assert_eq!(stat.cache_hits, 2);
+        let (hits, misses) = stat.per_kind.values().fold((0, 0), |(hits, misses), kind| {
+            (hits + kind.hits, misses + kind.misses)
+        });
+        assert_eq!((hits, misses), (stat.cache_hits, stat.cache_misses));
+    }
+
+    #[test]
+    fn test_writeback() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 1);
+        let cache = CacheWithFixTime::new(
+            &CacheConfig {
+                sets: 1,
+                associativity: 1,
+                miss_bytes_per_cycle: 1,
+                ..test_utils::cache_config()
+            },
+            inout_cache,
+            14,
+            120,
+            CacheId::L3Cache,
+            1,
+        );
+        let status = SataccStatus::new(Config::default());
+        let mut sim_runner = SimRunner::new(cache, status);
+        // the written line is evicted by the read, then the read line is evicted clean
+        let reqs = [
+            (0, MemReqType::ClauseWriteWatchList(0)),
+            (4, MemReqType::WatcherReadData),
+            (8, MemReqType::WatcherReadData),
+        ];
+        for (id, (addr, req_type)) in reqs.into_iter().enumerate() {
+            inout_base[0]
+                .out_port
+                .send(IcntMsgWrapper {
+                    msg: MemReq {
+                        addr,
+                        is_write: req_type.get_kind().is_write(),
+                        mem_id: 0,
+                        id,
+                        req_type,
+                        watcher_pe_id: 0,
+                        next_addr: None,
+                    },
+                    mem_target_port: 1,
+                })
+                .unwrap();
+            sim_runner.run().unwrap();
+            inout_base[0].in_port.recv().unwrap();
+        }
+        let stat = &sim_runner
+            .get_shared_status()
+            .statistics
+            .l3_cache_statistics;
+        assert_eq!(stat.cache_misses, 3);
+        assert_eq!(stat.writebacks, 1);
+        // the write back is not a miss, it only takes the bandwidth
+        assert_eq!(stat.miss_queue_delay.count, 3);
+    }
+
+    #[test]
+    fn test_next_line_prefetch() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 1);
+        let cache = CacheWithFixTime::new(
+            &CacheConfig {
+                sets: 16,
+                prefetcher: PrefetcherType::NextLine,
+                ..test_utils::cache_config()
+            },
+            inout_cache,
+            14,
+            120,
+            CacheId::L3Cache,
+            1,
+        );
+        let status = SataccStatus::new(Config::default());
+        let mut sim_runner = SimRunner::new(cache, status);
+        // read the lines one by one, each read should find the line prefetched
+        for line in 0..4 {
+            inout_base[0]
+                .out_port
+                .send(IcntMsgWrapper {
+                    msg: MemReq {
+                        addr: line * 4,
+                        is_write: false,
+                        mem_id: 0,
+                        id: line as usize,
+                        req_type: MemReqType::WatcherReadData,
+                        watcher_pe_id: 0,
+                        next_addr: None,
+                    },
+                    mem_target_port: 1,
+                })
+                .unwrap();
+            sim_runner.run().unwrap();
+            inout_base[0].in_port.recv().unwrap();
+        }
+        let stat = &sim_runner
+            .get_shared_status()
+            .statistics
+            .l3_cache_statistics;
+        assert_eq!(stat.prefetch_issued, 4);
+        assert_eq!(stat.prefetch_useful, 3);
+        assert_eq!(stat.cache_misses, 1);
+    }
+
+    #[test]
+    fn test_prefetch_directory() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 1);
+        let cache = CacheWithFixTime::new(
+            &CacheConfig {
+                sets: 1,
+                prefetcher: PrefetcherType::NextLine,
+                ..test_utils::cache_config()
+            },
+            inout_cache,
+            14,
+            120,
+            CacheId::PrivateCache(0),
+            1,
+        );
+        let mut config = Config::default();
+        config.coherence_config.protocol = CoherenceProtocol::DirectoryInvalidate;
+        let status = SataccStatus::new(config);
+        let mut sim_runner = SimRunner::new(cache, status);
+        // each demand miss prefetches the next line, the second pair evicts the first one
+        for line in [0, 2] {
+            inout_base[0]
+                .out_port
+                .send(IcntMsgWrapper {
+                    msg: MemReq {
+                        addr: line * 4,
+                        is_write: false,
+                        mem_id: 0,
+                        id: line as usize,
+                        req_type: MemReqType::ClauseReadValue(0),
+                        watcher_pe_id: 0,
+                        next_addr: None,
+                    },
+                    mem_target_port: 1,
+                })
+                .unwrap();
+            sim_runner.run().unwrap();
+            inout_base[0].in_port.recv().unwrap();
+        }
+        let status = sim_runner.get_shared_status_mut();
+        let stat = status
+            .statistics
+            .get_cache_statistics_mut(&CacheId::PrivateCache(0));
+        assert_eq!(stat.prefetch_issued, 2);
+        // the directory holds exactly the resident lines, including the prefetched one
+        let directory = &mut status.value_directory;
+        for line_addr in [0, 4] {
+            assert!(directory.take_sharers(line_addr).is_empty());
+        }
+        for line_addr in [8, 12] {
+            assert_eq!(
+                directory
+                    .take_sharers(line_addr)
+                    .into_iter()
+                    .collect::<Vec<_>>(),
+                [0]
+            );
+        }
+    }
+
+    #[test]
+    fn test_bypass() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 1);
+        let cache = CacheWithFixTime::new(
+            &CacheConfig {
+                req_kind_policies: ReqKindPolicies {
+                    clause_data: ReqKindPolicy {
+                        way_mask: 0,
+                        bypass: true,
+                    },
+                    ..Default::default()
+                },
+                ..test_utils::cache_config()
+            },
+            inout_cache,
+            14,
+            120,
+            CacheId::L3Cache,
+            1,
+        );
+        let status = SataccStatus::new(Config::default());
+        let mut sim_runner = SimRunner::new(cache, status);
+        // the clause data never allocate the line, the value allocates it and then the clause data hit it
+        let reqs = [
+            (0, MemReqType::ClauseReadData(0)),
+            (0, MemReqType::ClauseReadData(0)),
+            (0, MemReqType::ClauseReadValue(0)),
+            (0, MemReqType::ClauseReadData(0)),
+        ];
+        for (id, (addr, req_type)) in reqs.into_iter().enumerate() {
+            inout_base[0]
+                .out_port
+                .send(IcntMsgWrapper {
+                    msg: MemReq {
+                        addr,
+                        is_write: false,
+                        mem_id: 0,
+                        id,
+                        req_type,
+                        watcher_pe_id: 0,
+                        next_addr: None,
+                    },
+                    mem_target_port: 1,
+                })
+                .unwrap();
+            sim_runner.run().unwrap();
+            inout_base[0].in_port.recv().unwrap();
+        }
+        let stat = &sim_runner
+            .get_shared_status()
+            .statistics
+            .l3_cache_statistics;
+        let clause_data = &stat.per_kind[&MemReqKind::ClauseData];
+        assert_eq!(clause_data.misses, 2);
+        assert_eq!(clause_data.bypasses, 2);
+        assert_eq!(clause_data.hits, 1);
+        let clause_value = &stat.per_kind[&MemReqKind::ClauseValue];
+        assert_eq!(clause_value.misses, 1);
+        assert_eq!(clause_value.bypasses, 0);
+    }
+
+    #[test]
+    fn test_channel_conflict() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 3);
+        let cache = CacheWithFixTime::new(
+            &CacheConfig {
+                sets: 4,
+                channels: 2,
+                channel_busy_cycles: 4,
+                ..test_utils::cache_config()
+            },
+            inout_cache,
+            14,
+            120,
+            CacheId::L3Cache,
+            1,
+        );
+        let status = SataccStatus::new(Config::default());
+        let mut sim_runner = SimRunner::new(cache, status);
+        // the line 0 and 2 are in channel 0, the line 1 is in channel 1
+        for (port, addr) in [(0, 0), (1, 8), (2, 4)] {
+            inout_base[port]
+                .out_port
+                .send(IcntMsgWrapper {
+                    msg: MemReq {
+                        addr,
+                        is_write: false,
+                        mem_id: port,
+                        id: port,
+                        req_type: MemReqType::WatcherReadData,
+                        watcher_pe_id: 0,
+                        next_addr: None,
+                    },
+                    mem_target_port: 1,
+                })
+                .unwrap();
+        }
+        sim_runner.run().unwrap();
+        for port in &inout_base {
+            port.in_port.recv().unwrap();
+        }
+        let stat = &sim_runner
+            .get_shared_status()
+            .statistics
+            .l3_cache_statistics;
+        // the line 2 waits for 4 cycles, it's checked every 2 cycles
+        assert_eq!(stat.channel_conflicts, 2);
+        assert_eq!(stat.cache_misses, 3);
+    }
 }
@@ -1,5 +1,6 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 
+#[cfg(feature = "ramulator")]
 use ramulator_wrapper::{PresetConfigs, RamulatorWrapper};
 
 use crate::{
@@ -7,132 +8,236 @@ use crate::{
     sim::{InOutPort, SimComponent},
 };
 
-use super::{get_set_number_from_addr, AccessResult, CacheConfig, CacheId, FastCache};
+use super::{
+    dram::DramBackend,
+    mshr::{Mshr, MshrAddResult},
+    prefetcher::PrefetchUnit,
+    AccessResult, CacheConfig, CacheId, FastCache,
+};
 
 /// # CacheWithRamulator
-/// the cache with ramulator simulator
-/// - when miss, it will send a request to the ramulator
-/// - when the ramulator returns the result, it will resonse the request to sender with a `lit_latency`
-pub struct CacheWithRamulator {
+/// the cache with a dram simulator, the ramulator or the built-in [`super::DramModel`]
+/// - the ramulator needs the `ramulator` feature, see [`CacheWithRamulator::new`]
+/// - when miss, it will allocate a [`Mshr`] entry and send a request to the dram
+/// - when the mshr is full, the request will stall in the in port
+/// - when the dram returns the result, it will resonse the request to sender with a `lit_latency`
+/// - the missed line is always read from the dram, the evicted dirty line is written to the dram, the dram does not return the writes
+pub struct CacheWithRamulator<D: DramBackend> {
     pub fast_cache: FastCache,
-    pub ramulator: RamulatorWrapper,
+    pub ramulator: D,
     pub req_ports: Vec<InOutPort<IcntMsgWrapper<MemReq>>>,
     pub on_going_reqs: WaitingTask<MemReq>,
-    /// the tags the hold a vec of requests that are waiting for the ramulator
-    pub on_dram_reqs: BTreeMap<u64, Vec<MemReq>>,
+    /// the missed lines, each holds the requests that are waiting for the ramulator
+    pub mshr: Mshr,
+    pub prefetch_unit: PrefetchUnit,
     pub hit_latency: usize,
-    /// the mem request received from the queue, and accessed the cache, but not yet able to send to the dram.
-    /// process it!
-    pub temp_send_blocked_req: Option<MemReq>,
+    /// the missed lines(tag,false) that allocated a mshr entry and the written back lines(tag,true), but not yet able to send to the dram.
+    pub dram_send_queue: VecDeque<(u64, bool)>,
     pub cache_id: CacheId,
+    /// the arriving cycle of each request, used to get the latency of each kind of request
+    pub req_arrival_cycle: BTreeMap<usize, usize>,
 }
 
-impl CacheWithRamulator {
+#[cfg(feature = "ramulator")]
+impl CacheWithRamulator<RamulatorWrapper> {
     pub fn new(
         config: &CacheConfig,
         req_ports: Vec<InOutPort<IcntMsgWrapper<MemReq>>>,
         ramulator_preset: PresetConfigs,
         hit_latency: usize,
         cache_id: CacheId,
+    ) -> Self {
+        Self::new_with_dram(
+            config,
+            req_ports,
+            RamulatorWrapper::new_with_preset(ramulator_preset, "ramu_stat.txt"),
+            hit_latency,
+            cache_id,
+        )
+    }
+}
+impl<D: DramBackend> CacheWithRamulator<D> {
+    /// build the cache with another dram backend
+    pub fn new_with_dram(
+        config: &CacheConfig,
+        req_ports: Vec<InOutPort<IcntMsgWrapper<MemReq>>>,
+        dram: D,
+        hit_latency: usize,
+        cache_id: CacheId,
     ) -> Self {
         Self {
             fast_cache: FastCache::new(config),
-            ramulator: RamulatorWrapper::new_with_preset(ramulator_preset, "ramu_stat.txt"),
+            ramulator: dram,
             req_ports,
             on_going_reqs: WaitingTask::new(),
-            on_dram_reqs: BTreeMap::new(),
+            mshr: Mshr::new(
+                config.mshr_entries as usize,
+                config.mshr_max_targets as usize,
+            ),
+            prefetch_unit: PrefetchUnit::new(config),
             hit_latency,
-            temp_send_blocked_req: None,
+            dram_send_queue: VecDeque::new(),
             cache_id,
+            req_arrival_cycle: BTreeMap::new(),
         }
     }
 }
 
-impl SimComponent for CacheWithRamulator {
+impl<D: DramBackend> SimComponent for CacheWithRamulator<D> {
     type SharedStatus = SataccStatus;
     fn update(
         &mut self,
         shared_status: &mut Self::SharedStatus,
         current_cycle: usize,
     ) -> (bool, bool) {
-        let mut busy = !self.on_going_reqs.is_empty() || !self.on_dram_reqs.is_empty();
-        let mut updated = !self.on_dram_reqs.is_empty();
-        // first check if there is any request in the in_req_queues
-        if let Some(req) = self.temp_send_blocked_req.take() {
-            busy = true;
-            // if temp_send_blocked_req have value, first process it!
-            if self.ramulator.available(req.addr, req.is_write) {
-                updated = true;
-                tracing::debug!("send blocked req to dram");
-                let tag = get_set_number_from_addr(
-                    req.addr,
-                    self.fast_cache.get_set_bit_len(),
-                    self.fast_cache.get_block_bit_len(),
-                    self.fast_cache.get_channel_bit_len(),
-                )
-                .1;
-                self.ramulator.send(tag, req.is_write);
-                self.on_dram_reqs.insert(tag, vec![req]);
-            } else {
-                self.temp_send_blocked_req = Some(req);
-            }
-        } else {
-            // for each inport, check if there is any request in the in_req_queues,
-            // try to send it to dram, if cannot send it, put it in temp_send_blocked_req and send it next cycle
-            for InOutPort {
-                in_port,
-                out_port: _,
-            } in &mut self.req_ports
+        let mut busy = !self.on_going_reqs.is_empty() || !self.mshr.is_empty();
+        let mut updated = !self.mshr.is_empty();
+        // for each inport, check if there is any request in the in_req_queues,
+        for InOutPort {
+            in_port,
+            out_port: _,
+        } in &mut self.req_ports
+        {
+            if let Ok(IcntMsgWrapper {
+                mem_target_port,
+                msg,
+            }) = in_port.recv()
             {
-                if let Ok(IcntMsgWrapper {
-                    mem_target_port: _,
-                    msg,
-                }) = in_port.recv()
-                {
-                    busy = true;
-                    updated = true;
-                    tracing::debug!("recv req: {:?} at cycle: {current_cycle}", msg);
-                    match self.fast_cache.access(msg.addr) {
-                        AccessResult::Hit(tag) => {
-                            match self.on_dram_reqs.get_mut(&tag) {
-                                Some(entry) => {
-                                    entry.push(msg);
-                                }
-                                None => {
-                                    tracing::debug!("hit");
-                                    self.on_going_reqs
-                                        .push(msg, current_cycle + self.hit_latency);
-                                    shared_status.statistics.update_hit(&self.cache_id);
-                                }
-                            };
+                busy = true;
+                tracing::debug!("recv req: {:?} at cycle: {current_cycle}", msg);
+                let tag = self.fast_cache.get_line_addr(msg.addr);
+                let in_flight = self.mshr.contains(tag);
+                // a hit that not in flight do not need the mshr
+                if !self.mshr.can_accept(tag) && (in_flight || !self.fast_cache.probe(msg.addr)) {
+                    tracing::debug!(
+                        "mshr is full, stall the req: {:?} at cycle: {current_cycle}",
+                        msg
+                    );
+                    if in_flight {
+                        shared_status
+                            .statistics
+                            .update_mshr_target_full(&self.cache_id);
+                    } else {
+                        shared_status
+                            .statistics
+                            .update_mshr_entry_full(&self.cache_id);
+                    }
+                    in_port.ret(IcntMsgWrapper {
+                        mem_target_port,
+                        msg,
+                    });
+                    continue;
+                }
+                if !self.fast_cache.try_reserve_channel(msg.addr, current_cycle) {
+                    tracing::debug!("cache channel conflict at cycle: {current_cycle}");
+                    shared_status
+                        .statistics
+                        .update_channel_conflict(&self.cache_id);
+                    in_port.ret(IcntMsgWrapper {
+                        mem_target_port,
+                        msg,
+                    });
+                    continue;
+                }
+                updated = true;
+                let req_kind = msg.req_type.get_kind();
+                let (result, evicted) = self.fast_cache.access_with_evict(msg.addr, req_kind);
+                let stat = shared_status
+                    .statistics
+                    .get_cache_statistics_mut(&self.cache_id);
+                if let Some(evicted) = evicted {
+                    self.prefetch_unit.on_evict(evicted.addr, stat);
+                    if evicted.dirty {
+                        stat.writebacks += 1;
+                        self.dram_send_queue.push_back((evicted.addr, true));
+                    }
+                }
+                self.prefetch_unit.on_demand_access(&msg, tag, stat);
+                self.req_arrival_cycle.insert(msg.id, current_cycle);
+                match result {
+                    AccessResult::Hit(tag) => {
+                        if in_flight {
+                            shared_status.statistics.update_mshr_hit(
+                                &self.cache_id,
+                                req_kind,
+                                true,
+                            );
+                            let result = self.mshr.add(tag, msg);
+                            assert!(matches!(result, Ok(MshrAddResult::Merged)));
+                        } else {
+                            tracing::debug!("hit");
+                            shared_status
+                                .statistics
+                                .update_hit(&self.cache_id, req_kind);
+                            self.on_going_reqs
+                                .push(msg, current_cycle + self.hit_latency);
                         }
-                        AccessResult::Miss(tag) => {
-                            shared_status.statistics.update_miss(&self.cache_id);
-                            tracing::debug!("miss at cycle: {current_cycle}");
-
-                            match self.on_dram_reqs.get_mut(&tag) {
-                                Some(entry) => {
-                                    entry.push(msg);
-                                }
-                                None => {
-                                    // send it to dram
-                                    let is_write = msg.is_write;
-                                    if self.ramulator.available(tag, is_write) {
-                                        self.on_dram_reqs.insert(tag, vec![msg]);
-                                        self.ramulator.send(tag, is_write);
-                                    } else {
-                                        // cannot send to dram now
-                                        // temporarily put it in the on_going_reqs
-                                        tracing::debug!("cannot send to dram now, store it in temp slot : {:?} at cycle: {current_cycle}", msg);
-                                        self.temp_send_blocked_req = Some(msg);
+                    }
+                    AccessResult::Miss(tag) => {
+                        tracing::debug!("miss at cycle: {current_cycle}");
+                        match self.mshr.add(tag, msg) {
+                            Ok(MshrAddResult::NewEntry) => {
+                                shared_status
+                                    .statistics
+                                    .update_miss(&self.cache_id, req_kind);
+                                if self.fast_cache.is_bypass(req_kind) {
+                                    shared_status
+                                        .statistics
+                                        .update_bypass(&self.cache_id, req_kind);
+                                    // no line holds the written data, write it through
+                                    if req_kind.is_write() {
+                                        shared_status
+                                            .statistics
+                                            .get_cache_statistics_mut(&self.cache_id)
+                                            .writebacks += 1;
+                                        self.dram_send_queue.push_back((tag, true));
                                     }
                                 }
+                                // the written line is allocated, so it's read like the others
+                                self.dram_send_queue.push_back((tag, false));
                             }
+                            Ok(MshrAddResult::Merged) => {
+                                shared_status.statistics.update_mshr_hit(
+                                    &self.cache_id,
+                                    req_kind,
+                                    false,
+                                );
+                            }
+                            Err(_) => unreachable!("the mshr is checked before access"),
                         }
                     }
                 }
             }
         }
+        // issue one prefetch each cycle
+        let stat = shared_status
+            .statistics
+            .get_cache_statistics_mut(&self.cache_id);
+        if let Some(prefetch) =
+            self.prefetch_unit
+                .issue_prefetch(&mut self.fast_cache, &mut self.mshr, stat)
+        {
+            busy = true;
+            updated = true;
+            if let Some(evicted) = prefetch.evicted.filter(|evicted| evicted.dirty) {
+                stat.writebacks += 1;
+                self.dram_send_queue.push_back((evicted.addr, true));
+            }
+            self.dram_send_queue.push_back((prefetch.line_addr, false));
+        }
+        // send the missed lines to dram
+        while let Some((tag, is_write)) = self.dram_send_queue.pop_front() {
+            busy = true;
+            if self.ramulator.available(tag, is_write) {
+                updated = true;
+                self.ramulator.send(tag, is_write, current_cycle);
+            } else {
+                // cannot send to dram now
+                tracing::debug!("cannot send to dram now: {:?} at cycle: {current_cycle}", tag);
+                self.dram_send_queue.push_front((tag, is_write));
+                break;
+            }
+        }
         // then check if there is any request in the on_going_reqs
         while let Some((leaving_cycle, req)) = self.on_going_reqs.pop() {
             busy = true;
@@ -144,6 +249,8 @@ impl SimComponent for CacheWithRamulator {
             } else {
                 let req_addr = req.addr;
                 let watcher_id = req.watcher_pe_id;
+                let req_id = req.id;
+                let req_kind = req.req_type.get_kind();
                 match self.req_ports[req.mem_id].out_port.send(IcntMsgWrapper {
                     msg: req,
                     mem_target_port: watcher_id,
@@ -151,6 +258,12 @@ impl SimComponent for CacheWithRamulator {
                     Ok(_) => {
                         tracing::debug!("send req: {:?} at cycle: {current_cycle}", req_addr);
                         busy = true;
+                        let arrival_cycle = self.req_arrival_cycle.remove(&req_id).unwrap();
+                        shared_status.statistics.update_cache_latency(
+                            &self.cache_id,
+                            req_kind,
+                            current_cycle - arrival_cycle,
+                        );
                     }
                     Err(req) => {
                         tracing::debug!(
@@ -164,13 +277,14 @@ impl SimComponent for CacheWithRamulator {
             }
         }
 
-        // no temp blocked, check the dram reqs
+        // check the dram reqs
         while self.ramulator.ret_available() {
             busy = true;
             updated = true;
             let tag = self.ramulator.pop();
             tracing::debug!("dram req: {:?} at cycle: {}", tag, current_cycle);
-            match self.on_dram_reqs.remove(&tag) {
+            self.prefetch_unit.on_fill(tag);
+            match self.mshr.fill(tag) {
                 Some(mut entrys) => {
                     while let Some(req) = entrys.pop() {
                         tracing::debug!(
@@ -187,7 +301,8 @@ impl SimComponent for CacheWithRamulator {
                 }
             }
         }
-        self.ramulator.cycle();
+        self.ramulator
+            .cycle(current_cycle, &mut shared_status.statistics.dram_statistics);
         if busy && !updated {
             tracing::debug!("no update at cycle: {current_cycle}");
         }
@@ -197,34 +312,39 @@ impl SimComponent for CacheWithRamulator {
 
 #[cfg(test)]
 mod test {
+    #[cfg(feature = "ramulator")]
+    use crate::satacc::PrefetcherType;
     use crate::{
-        satacc::{CacheConfig, MemReqType},
+        config::Config,
+        satacc::{
+            cache::{DramModel, DramTiming},
+            CacheConfig, MemReqType,
+        },
         sim::{ChannelBuilder, SimRunner},
         test_utils,
     };
 
     use super::*;
-    use ramulator_wrapper::PresetConfigs;
+    #[cfg(feature = "ramulator")]
     #[test]
     fn test_cache_with_ramu() {
         test_utils::init();
         let channel_builder = ChannelBuilder::new();
         let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 2);
         let cache = CacheWithRamulator {
-            fast_cache: FastCache::new(&CacheConfig {
-                sets: 2,
-                associativity: 2,
-                block_size: 4,
-                channels: 1,
-                alway_hit: false,
-            }),
+            fast_cache: FastCache::new(&test_utils::cache_config()),
             ramulator: RamulatorWrapper::new_with_preset(PresetConfigs::HBM, "STAT.txt"),
             on_going_reqs: WaitingTask::new(),
-            on_dram_reqs: BTreeMap::new(),
+            mshr: Mshr::new(16, 4),
+            prefetch_unit: PrefetchUnit::new(&CacheConfig {
+                prefetcher: PrefetcherType::NextLine,
+                ..test_utils::cache_config()
+            }),
             hit_latency: 14,
-            temp_send_blocked_req: None,
+            dram_send_queue: VecDeque::new(),
             req_ports: inout_cache,
             cache_id: CacheId::L3Cache,
+            req_arrival_cycle: BTreeMap::new(),
         };
         let mut status = SataccStatus::default();
         for i in 0..1000 {
@@ -237,14 +357,117 @@ mod test {
                         mem_id: 0,
                         id: status.next_mem_id(),
                         req_type: MemReqType::WatcherReadData,
+                        next_addr: None,
+                        watcher_pe_id: 0,
+                    },
+                    mem_target_port: 1,
+                })
+                .unwrap();
+        }
+
+        let mut sim_runner = SimRunner::new(cache, status);
+        sim_runner.run().unwrap();
+    }
+
+    #[test]
+    fn test_dirty_writeback() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 1);
+        let cache = CacheWithRamulator::new_with_dram(
+            &CacheConfig {
+                sets: 1,
+                associativity: 1,
+                block_size: 64,
+                ..test_utils::cache_config()
+            },
+            inout_cache,
+            DramModel::new(DramTiming::ddr4()),
+            14,
+            CacheId::L3Cache,
+        );
+        let status = SataccStatus::new(Config::default());
+        let mut sim_runner = SimRunner::new(cache, status);
+        // the written line is evicted by the read and written to the dram
+        let reqs = [
+            (0, MemReqType::ClauseWriteWatchList(0)),
+            (64, MemReqType::WatcherReadData),
+        ];
+        for (id, (addr, req_type)) in reqs.into_iter().enumerate() {
+            inout_base[0]
+                .out_port
+                .send(IcntMsgWrapper {
+                    msg: MemReq {
+                        addr,
+                        is_write: req_type.get_kind().is_write(),
+                        mem_id: 0,
+                        id,
+                        req_type,
                         watcher_pe_id: 0,
+                        next_addr: None,
                     },
                     mem_target_port: 1,
                 })
                 .unwrap();
+            sim_runner.run().unwrap();
+            inout_base[0].in_port.recv().unwrap();
         }
+        let stat = &sim_runner.get_shared_status().statistics;
+        assert_eq!(stat.l3_cache_statistics.writebacks, 1);
+        // the written line is read when it's allocated, then written back when it's evicted
+        let dram_stat = &stat.dram_statistics;
+        assert_eq!(dram_stat.writes, 1);
+        assert_eq!(dram_stat.latency.count, 2);
+    }
 
+    #[test]
+    fn test_dram_timing_in_runner_cycles() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_cache) = channel_builder.in_out_poat_array(1000, 1);
+        let cache = CacheWithRamulator::new_with_dram(
+            &CacheConfig {
+                sets: 1,
+                associativity: 1,
+                block_size: 64,
+                ..test_utils::cache_config()
+            },
+            inout_cache,
+            DramModel::new(DramTiming::ddr4()),
+            14,
+            CacheId::L3Cache,
+        );
+        let status = SataccStatus::new(Config::default());
         let mut sim_runner = SimRunner::new(cache, status);
+        inout_base[0]
+            .out_port
+            .send(IcntMsgWrapper {
+                msg: MemReq {
+                    addr: 0,
+                    is_write: false,
+                    mem_id: 0,
+                    id: 0,
+                    req_type: MemReqType::WatcherReadData,
+                    watcher_pe_id: 0,
+                    next_addr: None,
+                },
+                mem_target_port: 1,
+            })
+            .unwrap();
         sim_runner.run().unwrap();
+        inout_base[0].in_port.recv().unwrap();
+        // the closed bank costs t_rcd + t_cl + t_burst runner cycles, though the runner steps two cycles each update
+        let timing = DramTiming::ddr4();
+        let dram_latency = timing.t_rcd + timing.t_cl + timing.t_burst;
+        assert_eq!(
+            sim_runner
+                .get_shared_status()
+                .statistics
+                .dram_statistics
+                .latency
+                .get_average(),
+            dram_latency as f64
+        );
+        assert!(sim_runner.get_current_cycle() < 2 * dram_latency);
     }
 }
@@ -0,0 +1,291 @@
+use std::collections::VecDeque;
+
+#[cfg(feature = "ramulator")]
+use ramulator_wrapper::RamulatorWrapper;
+
+use crate::{
+    config::DramType,
+    satacc::{statistics::DramStatistics, wating_task::WaitingTask},
+};
+
+/// the dram behind the cache, the cache sends the missed lines and pops the finished ones
+/// - the writes are not returned, like the ramulator
+/// - `current_cycle` is the cycle of the [`crate::sim::SimRunner`], the cache calls [`DramBackend::cycle`] once each update
+pub trait DramBackend {
+    /// test if the dram can accept the request now
+    fn available(&mut self, addr: u64, is_write: bool) -> bool;
+    fn send(&mut self, addr: u64, is_write: bool, current_cycle: usize);
+    /// test if there is any finished request
+    fn ret_available(&mut self) -> bool;
+    /// pop the addr of a finished read
+    fn pop(&mut self) -> u64;
+    /// run the dram until `current_cycle`
+    fn cycle(&mut self, current_cycle: usize, stat: &mut DramStatistics);
+}
+
+#[cfg(feature = "ramulator")]
+impl DramBackend for RamulatorWrapper {
+    fn available(&mut self, addr: u64, is_write: bool) -> bool {
+        RamulatorWrapper::available(self, addr, is_write)
+    }
+    fn send(&mut self, addr: u64, is_write: bool, _current_cycle: usize) {
+        RamulatorWrapper::send(self, addr, is_write)
+    }
+    fn ret_available(&mut self) -> bool {
+        RamulatorWrapper::ret_available(self)
+    }
+    fn pop(&mut self) -> u64 {
+        RamulatorWrapper::pop(self)
+    }
+    /// the ramulator keeps its own clock and its own statistics in the stat file
+    fn cycle(&mut self, _current_cycle: usize, _stat: &mut DramStatistics) {
+        RamulatorWrapper::cycle(self)
+    }
+}
+
+/// the organization and the timing of the dram, the timing is in the cycles of the [`crate::sim::SimRunner`]
+#[derive(Debug, Clone, Copy)]
+pub struct DramTiming {
+    pub channels: u64,
+    pub banks: u64,
+    /// the bytes of a row in a bank
+    pub row_size: u64,
+    pub block_size: u64,
+    /// the max requests waiting in each channel
+    pub queue_size: usize,
+    /// activate to read
+    pub t_rcd: usize,
+    /// precharge
+    pub t_rp: usize,
+    /// read to the first data
+    pub t_cl: usize,
+    /// the cycles to transfer a block on the channel bus
+    pub t_burst: usize,
+}
+impl DramTiming {
+    /// ddr4-2400, 2 channels
+    pub fn ddr4() -> Self {
+        Self {
+            channels: 2,
+            banks: 16,
+            row_size: 8192,
+            block_size: 64,
+            queue_size: 32,
+            t_rcd: 16,
+            t_rp: 16,
+            t_cl: 16,
+            t_burst: 4,
+        }
+    }
+    /// hbm, 8 channels
+    pub fn hbm() -> Self {
+        Self {
+            channels: 8,
+            banks: 16,
+            row_size: 2048,
+            block_size: 64,
+            queue_size: 32,
+            t_rcd: 14,
+            t_rp: 14,
+            t_cl: 14,
+            t_burst: 2,
+        }
+    }
+    pub fn from_dram_type(dram_type: &DramType) -> Self {
+        match dram_type {
+            DramType::DDR4 => Self::ddr4(),
+            DramType::HBM => Self::hbm(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct DramReq {
+    addr: u64,
+    is_write: bool,
+    bank: usize,
+    row: u64,
+    arrival_cycle: usize,
+}
+#[derive(Debug, Default, Clone)]
+struct Bank {
+    open_row: Option<u64>,
+    /// the cycle the bank can accept a new command
+    ready_cycle: usize,
+}
+#[derive(Debug)]
+struct Channel {
+    queue: VecDeque<DramReq>,
+    banks: Vec<Bank>,
+    /// the cycle the data bus is free
+    bus_ready_cycle: usize,
+}
+
+/// # DramModel
+/// the dram timing model written in rust, it's deterministic and needs no native library
+/// - the lines are interleaved across the channels, then the columns of a row, then the banks
+/// - each channel schedules the requests by FR-FCFS: the oldest row hit first, then the oldest request
+/// - a row hit costs `t_cl`, a closed bank costs `t_rcd + t_cl` and a row conflict costs `t_rp + t_rcd + t_cl`
+/// - each channel issues at most one request each call of [`DramBackend::cycle`]
+pub struct DramModel {
+    timing: DramTiming,
+    channels: Vec<Channel>,
+    on_going_reqs: WaitingTask<u64>,
+    finished_reqs: VecDeque<u64>,
+}
+
+impl DramModel {
+    pub fn new(timing: DramTiming) -> Self {
+        let channels = (0..timing.channels)
+            .map(|_| Channel {
+                queue: VecDeque::new(),
+                banks: vec![Bank::default(); timing.banks as usize],
+                bus_ready_cycle: 0,
+            })
+            .collect();
+        Self {
+            timing,
+            channels,
+            on_going_reqs: WaitingTask::new(),
+            finished_reqs: VecDeque::new(),
+        }
+    }
+    /// return the (channel, bank, row) of the addr
+    fn map_addr(&self, addr: u64) -> (usize, usize, u64) {
+        let line = addr / self.timing.block_size;
+        let channel = line % self.timing.channels;
+        let rest = line / self.timing.channels / (self.timing.row_size / self.timing.block_size);
+        let bank = rest % self.timing.banks;
+        let row = rest / self.timing.banks;
+        (channel as usize, bank as usize, row)
+    }
+}
+
+impl DramBackend for DramModel {
+    fn available(&mut self, addr: u64, _is_write: bool) -> bool {
+        let (channel, _, _) = self.map_addr(addr);
+        self.channels[channel].queue.len() < self.timing.queue_size
+    }
+    fn send(&mut self, addr: u64, is_write: bool, current_cycle: usize) {
+        let (channel, bank, row) = self.map_addr(addr);
+        tracing::debug!(addr, channel, bank, row, is_write, "dram receive request");
+        self.channels[channel].queue.push_back(DramReq {
+            addr,
+            is_write,
+            bank,
+            row,
+            arrival_cycle: current_cycle,
+        });
+    }
+    fn ret_available(&mut self) -> bool {
+        !self.finished_reqs.is_empty()
+    }
+    fn pop(&mut self) -> u64 {
+        self.finished_reqs.pop_front().unwrap()
+    }
+    fn cycle(&mut self, current_cycle: usize, stat: &mut DramStatistics) {
+        let timing = self.timing;
+        for channel in &mut self.channels {
+            let is_ready = |req: &DramReq| channel.banks[req.bank].ready_cycle <= current_cycle;
+            // FR-FCFS: the oldest row hit, then the oldest request
+            let selected = channel
+                .queue
+                .iter()
+                .position(|req| is_ready(req) && channel.banks[req.bank].open_row == Some(req.row))
+                .or_else(|| channel.queue.iter().position(is_ready));
+            if let Some(index) = selected {
+                let req = channel.queue.remove(index).unwrap();
+                let bank = &mut channel.banks[req.bank];
+                let row_latency = match bank.open_row {
+                    Some(row) if row == req.row => {
+                        stat.row_hits += 1;
+                        0
+                    }
+                    Some(_) => {
+                        stat.row_conflicts += 1;
+                        timing.t_rp + timing.t_rcd
+                    }
+                    None => {
+                        stat.row_misses += 1;
+                        timing.t_rcd
+                    }
+                };
+                bank.open_row = Some(req.row);
+                bank.ready_cycle = current_cycle + row_latency + timing.t_burst;
+                let data_start =
+                    (current_cycle + row_latency + timing.t_cl).max(channel.bus_ready_cycle);
+                channel.bus_ready_cycle = data_start + timing.t_burst;
+                if req.is_write {
+                    stat.writes += 1;
+                    continue;
+                }
+                stat.latency
+                    .add(channel.bus_ready_cycle - req.arrival_cycle);
+                self.on_going_reqs.push(req.addr, channel.bus_ready_cycle);
+            }
+        }
+        while let Some((leaving_cycle, addr)) = self.on_going_reqs.pop() {
+            if leaving_cycle > current_cycle {
+                self.on_going_reqs.push(addr, leaving_cycle);
+                break;
+            }
+            self.finished_reqs.push_back(addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    /// send the addrs at cycle 0, return the finishing cycle of each addr
+    fn run(dram: &mut DramModel, addrs: &[u64], stat: &mut DramStatistics) -> Vec<(u64, usize)> {
+        for &addr in addrs {
+            assert!(dram.available(addr, false));
+            dram.send(addr, false, 0);
+        }
+        let mut finished = vec![];
+        let mut cycle = 0;
+        while finished.len() < addrs.len() {
+            dram.cycle(cycle, stat);
+            while dram.ret_available() {
+                finished.push((dram.pop(), cycle));
+            }
+            cycle += 1;
+        }
+        finished
+    }
+    #[test]
+    fn test_row_buffer() {
+        let timing = DramTiming {
+            channels: 1,
+            banks: 2,
+            row_size: 256,
+            block_size: 64,
+            queue_size: 32,
+            t_rcd: 10,
+            t_rp: 10,
+            t_cl: 10,
+            t_burst: 1,
+        };
+        let mut dram = DramModel::new(timing);
+        let mut stat = DramStatistics::default();
+        // row 0 of bank 0, then row 0 of bank 1, then row 1 of bank 0 and row 0 of bank 0 again
+        let finished = run(&mut dram, &[0, 256, 512, 64], &mut stat);
+        assert_eq!(stat.row_misses, 2);
+        assert_eq!(stat.row_conflicts, 1);
+        assert_eq!(stat.row_hits, 1);
+        // the row hit to 64 is scheduled before the conflict to 512
+        let order = finished.iter().map(|(addr, _)| *addr).collect::<Vec<_>>();
+        assert_eq!(order, vec![0, 256, 64, 512]);
+        assert_eq!(finished[0].1, 21);
+    }
+    #[test]
+    fn test_presets_are_deterministic() {
+        for timing in [DramTiming::ddr4(), DramTiming::hbm()] {
+            let addrs = (0..64).map(|i| i * 4160).collect::<Vec<_>>();
+            let first = run(&mut DramModel::new(timing), &addrs, &mut Default::default());
+            let second = run(&mut DramModel::new(timing), &addrs, &mut Default::default());
+            assert_eq!(first, second);
+        }
+    }
+}
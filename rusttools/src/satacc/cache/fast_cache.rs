@@ -1,118 +1,391 @@
 use serde::{Deserialize, Serialize};
 
-use super::{get_bit_lens, get_set_number_from_addr, AccessResult};
+use crate::satacc::MemReqKind;
 
+use super::{get_bit_lens, AccessResult, AddrMapping, PrefetcherType};
+
+/// # FastCache
+/// the tags of the cache, the timing is handled by the cache simulators
+/// - the sets are evenly split into `channels` slices, the channel of a line is chosen by [`ChannelMapping`]
+/// - each channel serves one access every `channel_busy_cycles`, see [`FastCache::try_reserve_channel`]
+/// - the write kinds allocate the lines like the reads and make them dirty, the evicted dirty line should be written back
 pub struct FastCache {
     pub cache_config: CacheConfig,
     sets: Vec<Set>,
-    set_bit_len: u64,
-    block_bit_len: u64,
-    channel_bit_len: u64,
+    addr_mapping: AddrMapping,
+    /// the cycle that each channel can accept a new access
+    channel_ready_cycle: Vec<usize>,
 }
 #[derive(Debug, Clone)]
 pub struct Set {
-    lines: Vec<u64>,
+    /// the tag in each way
+    lines: Vec<Option<u64>>,
+    /// the line in each way is written since it's filled
+    dirty: Vec<bool>,
     replace_ptr: usize,
 }
+/// the line evicted by [`FastCache::access_with_evict`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvictedLine {
+    pub addr: u64,
+    /// the line should be written back to the next level
+    pub dirty: bool,
+}
+/// how a kind of request uses the cache
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[repr(C)]
+pub struct ReqKindPolicy {
+    /// the ways that this kind can allocate lines in, bit i is way i, 0 means all the ways
+    /// - the lookup still searches all the ways, so a line allocated by other kinds can hit
+    pub way_mask: u64,
+    /// do not allocate lines for this kind, the missed requests go to the next level directly
+    pub bypass: bool,
+}
+/// the [`ReqKindPolicy`] of each [`MemReqKind`]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[repr(C)]
+pub struct ReqKindPolicies {
+    pub meta_data: ReqKindPolicy,
+    pub watcher_data: ReqKindPolicy,
+    pub blocker: ReqKindPolicy,
+    pub clause_data: ReqKindPolicy,
+    pub clause_value: ReqKindPolicy,
+    pub watch_list_write: ReqKindPolicy,
+}
+impl ReqKindPolicies {
+    pub fn get(&self, kind: MemReqKind) -> &ReqKindPolicy {
+        match kind {
+            MemReqKind::MetaData => &self.meta_data,
+            MemReqKind::WatcherData => &self.watcher_data,
+            MemReqKind::Blocker => &self.blocker,
+            MemReqKind::ClauseData => &self.clause_data,
+            MemReqKind::ClauseValue => &self.clause_value,
+            MemReqKind::WatchListWrite => &self.watch_list_write,
+        }
+    }
+}
+/// how to choose the channel of a line
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ChannelMapping {
+    /// the lowest bits of the line
+    BitSlice,
+    /// the xor of all the bits groups of the line, so the strided lines are spread across the channels
+    XorHash,
+}
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[repr(C)]
 pub struct CacheConfig {
     pub sets: u64,
     pub associativity: u64,
     pub block_size: u64,
+    /// the number of slices of the cache, the `sets` are evenly split into the channels
     pub channels: u64,
+    pub channel_mapping: ChannelMapping,
+    /// the cycles a channel is busy for each access, the other accesses to it stall, 0 means no conflict
+    pub channel_busy_cycles: u64,
     pub alway_hit: bool,
+    /// the number of mshr entries, that is, the max number of missed lines in flight
+    pub mshr_entries: u64,
+    /// the max number of requests that can be merged into one mshr entry
+    pub mshr_max_targets: u64,
+    pub prefetcher: PrefetcherType,
+    /// the number of lines to prefetch each time
+    pub prefetch_degree: u64,
+    /// the bytes per cycle of the next level channel of each partition, the missed lines of the partition queue up on it, 0 means unlimited
+    pub miss_bytes_per_cycle: u64,
+    /// the way partition and the bypass of each kind of request
+    pub req_kind_policies: ReqKindPolicies,
 }
 
 impl FastCache {
     pub fn new(cache_config: &CacheConfig) -> Self {
         let sets = vec![
             Set {
-                lines: vec![],
+                lines: vec![None; cache_config.associativity as usize],
+                dirty: vec![false; cache_config.associativity as usize],
                 replace_ptr: 0,
             };
             cache_config.sets as usize
         ];
-        let set_bit_len = get_bit_lens(cache_config.sets);
-        let block_bit_len = get_bit_lens(cache_config.block_size);
-        let channel_bit_len = get_bit_lens(cache_config.channels);
+        assert!(
+            cache_config.sets.is_multiple_of(cache_config.channels),
+            "the sets should be evenly split into the channels"
+        );
+        let addr_mapping = AddrMapping {
+            set_bit_len: get_bit_lens(cache_config.sets / cache_config.channels),
+            block_bit_len: get_bit_lens(cache_config.block_size),
+            channel_bit_len: get_bit_lens(cache_config.channels),
+            channel_mapping: cache_config.channel_mapping,
+        };
         FastCache {
             cache_config: cache_config.clone(),
             sets,
-            set_bit_len,
-            block_bit_len,
-            channel_bit_len,
+            addr_mapping,
+            channel_ready_cycle: vec![0; cache_config.channels as usize],
         }
     }
-    pub fn access(&mut self, addr: u64) -> AccessResult {
-        let (set_number, tag) = get_set_number_from_addr(
-            addr,
-            self.set_bit_len,
-            self.block_bit_len,
-            self.channel_bit_len,
-        );
+    pub fn access(&mut self, addr: u64, kind: MemReqKind) -> AccessResult {
+        self.access_with_evict(addr, kind).0
+    }
+    /// the request of this kind do not allocate lines in the cache
+    pub fn is_bypass(&self, kind: MemReqKind) -> bool {
+        self.cache_config.req_kind_policies.get(kind).bypass
+    }
+    /// the ways that the kind can allocate lines in
+    fn get_way_mask(&self, kind: MemReqKind) -> u64 {
+        let all_ways = match self.cache_config.associativity {
+            64.. => u64::MAX,
+            associativity => (1 << associativity) - 1,
+        };
+        match self.cache_config.req_kind_policies.get(kind).way_mask & all_ways {
+            0 => all_ways,
+            way_mask => way_mask,
+        }
+    }
+    /// return the index of the set in `sets` and the tag
+    fn get_set_number_and_tag(&self, addr: u64) -> (usize, u64) {
+        let (channel, set, tag) = self.addr_mapping.map(addr);
+        let set_number = (channel << self.addr_mapping.set_bit_len) | set;
+        (set_number as usize, tag)
+    }
+    fn get_channel(&self, addr: u64) -> usize {
+        self.addr_mapping.map(addr).0 as usize
+    }
+    /// occupy the channel of the addr for `channel_busy_cycles`, return false if the channel is still busy
+    pub fn try_reserve_channel(&mut self, addr: u64, current_cycle: usize) -> bool {
+        let channel = self.get_channel(addr);
+        if self.channel_ready_cycle[channel] > current_cycle {
+            return false;
+        }
+        self.channel_ready_cycle[channel] =
+            current_cycle + self.cache_config.channel_busy_cycles as usize;
+        true
+    }
+    /// access the cache, also return the evicted line if any
+    /// - the missed line is allocated in the ways of the `kind`, the bypassed kind do not allocate
+    /// - the write kind makes the line dirty
+    pub fn access_with_evict(
+        &mut self,
+        addr: u64,
+        kind: MemReqKind,
+    ) -> (AccessResult, Option<EvictedLine>) {
+        let line_addr = self.addr_mapping.get_line_addr(addr);
         // todo! always hit
         match self.cache_config.alway_hit {
-            true => AccessResult::Hit(tag),
+            true => (AccessResult::Hit(line_addr), None),
             false => {
-                let set = &mut self.sets[set_number as usize];
-                for line in &set.lines {
-                    if *line == tag {
-                        return AccessResult::Hit(tag);
-                    }
+                let way_mask = self.get_way_mask(kind);
+                let bypass = self.is_bypass(kind);
+                let associativity = self.cache_config.associativity as usize;
+                let (set_number, tag) = self.get_set_number_and_tag(addr);
+                let set = &mut self.sets[set_number];
+                if let Some(way) = set.lines.iter().position(|line| *line == Some(tag)) {
+                    set.dirty[way] |= kind.is_write();
+                    return (AccessResult::Hit(line_addr), None);
                 }
                 // not in the set
-                if set.lines.len() < self.cache_config.associativity as usize {
-                    set.lines.push(tag);
-                } else {
-                    set.lines[set.replace_ptr] = tag;
-                    set.replace_ptr =
-                        (set.replace_ptr + 1) % self.cache_config.associativity as usize;
+                if bypass {
+                    return (AccessResult::Miss(line_addr), None);
                 }
+                let is_allowed = |way: usize| way_mask >> way & 1 == 1;
+                let empty_way =
+                    (0..associativity).find(|&way| is_allowed(way) && set.lines[way].is_none());
+                let way = empty_way.unwrap_or_else(|| {
+                    // the next allowed way from the replace pointer
+                    let way = (0..associativity)
+                        .map(|i| (set.replace_ptr + i) % associativity)
+                        .find(|&way| is_allowed(way))
+                        .unwrap();
+                    set.replace_ptr = (way + 1) % associativity;
+                    way
+                });
+                let evicted_tag = set.lines[way].replace(tag);
+                let dirty = std::mem::replace(&mut set.dirty[way], kind.is_write());
 
-                AccessResult::Miss(tag)
+                let evicted = evicted_tag.map(|evicted_tag| {
+                    let (channel, set, _) = self.addr_mapping.map(addr);
+                    EvictedLine {
+                        addr: self.addr_mapping.unmap(channel, set, evicted_tag),
+                        dirty,
+                    }
+                });
+                (AccessResult::Miss(line_addr), evicted)
             }
         }
     }
-    pub fn get_set_bit_len(&self) -> u64 {
-        self.set_bit_len
+    /// test if the addr is in the cache, the cache status will not be changed
+    pub fn probe(&self, addr: u64) -> bool {
+        if self.cache_config.alway_hit {
+            return true;
+        }
+        let (set_number, tag) = self.get_set_number_and_tag(addr);
+        self.sets[set_number].lines.contains(&Some(tag))
+    }
+    /// remove the line of the addr from the cache, return false if it's not in the cache
+    pub fn invalidate(&mut self, addr: u64) -> bool {
+        let (set_number, tag) = self.get_set_number_and_tag(addr);
+        let set = &mut self.sets[set_number];
+        match set.lines.iter().position(|line| *line == Some(tag)) {
+            Some(way) => {
+                set.lines[way] = None;
+                set.dirty[way] = false;
+                true
+            }
+            None => false,
+        }
     }
-    pub fn get_block_bit_len(&self) -> u64 {
-        self.block_bit_len
+    /// the addr and the dirty bit of each line in the cache, sorted by the addr
+    pub fn resident_lines(&self) -> Vec<(u64, bool)> {
+        let set_mask = (1 << self.addr_mapping.set_bit_len) - 1;
+        let mut lines = self
+            .sets
+            .iter()
+            .enumerate()
+            .flat_map(|(set_number, set)| {
+                let channel = set_number as u64 >> self.addr_mapping.set_bit_len;
+                let set_index = set_number as u64 & set_mask;
+                set.lines
+                    .iter()
+                    .zip(&set.dirty)
+                    .filter_map(move |(tag, &dirty)| {
+                        tag.map(|tag| (self.addr_mapping.unmap(channel, set_index, tag), dirty))
+                    })
+            })
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines
     }
-    pub fn get_channel_bit_len(&self) -> u64 {
-        self.channel_bit_len
+    /// get the addr of the line, it's the same addr returned by [`FastCache::access`]
+    pub fn get_line_addr(&self, addr: u64) -> u64 {
+        self.addr_mapping.get_line_addr(addr)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::test_utils;
+
     use super::*;
     #[test]
     fn test_cache() {
-        let cache_config = CacheConfig {
-            sets: 2,
-            associativity: 2,
-            block_size: 4,
-            channels: 1,
-            alway_hit: false,
-        };
+        let cache_config = test_utils::cache_config();
         let mut cache = FastCache::new(&cache_config);
         // the first one is miss and then the later 4 is in a block so it's hit
-        assert!(cache.access(0).as_miss().is_some());
-        assert!(cache.access(1).as_hit().is_some());
-        assert!(cache.access(2).as_hit().is_some());
-        assert!(cache.access(3).as_hit().is_some());
+        assert!(cache.access(0, MemReqKind::WatcherData).as_miss().is_some());
+        assert!(cache.access(1, MemReqKind::WatcherData).as_hit().is_some());
+        assert!(cache.access(2, MemReqKind::WatcherData).as_hit().is_some());
+        assert!(cache.access(3, MemReqKind::WatcherData).as_hit().is_some());
         // the next 3 is miss and will full the cache
-        assert!(cache.access(4).as_miss().is_some());
-        assert!(cache.access(8).as_miss().is_some());
-        assert!(cache.access(12).as_miss().is_some());
+        assert!(cache.access(4, MemReqKind::WatcherData).as_miss().is_some());
+        assert!(cache.access(8, MemReqKind::WatcherData).as_miss().is_some());
+        assert!(cache
+            .access(12, MemReqKind::WatcherData)
+            .as_miss()
+            .is_some());
 
         // the next one is hit because 0 is not evicted now
-        assert!(cache.access(0).as_hit().is_some());
+        assert!(cache.access(0, MemReqKind::WatcherData).as_hit().is_some());
         // the new one will evict the first one
-        assert!(cache.access(16).as_miss().is_some());
+        assert!(cache
+            .access(16, MemReqKind::WatcherData)
+            .as_miss()
+            .is_some());
         // the first one will miss
-        assert!(cache.access(0).as_miss().is_some());
+        assert!(cache.access(0, MemReqKind::WatcherData).as_miss().is_some());
+        // the invalidated line will miss again
+        assert!(cache.invalidate(2));
+        assert!(!cache.invalidate(2));
+        assert!(!cache.probe(0));
+        assert!(cache.access(0, MemReqKind::WatcherData).as_miss().is_some());
+        assert!(cache
+            .access(12, MemReqKind::WatchListWrite)
+            .as_hit()
+            .is_some());
+        assert_eq!(
+            cache.resident_lines(),
+            [(0, false), (4, false), (12, true), (16, false)]
+        );
+    }
+    #[test]
+    fn test_way_partition_and_bypass() {
+        let cache_config = CacheConfig {
+            sets: 1,
+            associativity: 4,
+            req_kind_policies: ReqKindPolicies {
+                clause_value: ReqKindPolicy {
+                    way_mask: 0b0011,
+                    bypass: false,
+                },
+                clause_data: ReqKindPolicy {
+                    way_mask: 0b1100,
+                    bypass: false,
+                },
+                blocker: ReqKindPolicy {
+                    way_mask: 0,
+                    bypass: true,
+                },
+                ..Default::default()
+            },
+            ..test_utils::cache_config()
+        };
+        let mut cache = FastCache::new(&cache_config);
+        assert!(cache.access(0, MemReqKind::ClauseValue).as_miss().is_some());
+        assert!(cache.access(4, MemReqKind::ClauseValue).as_miss().is_some());
+        // the clause data only evict each other
+        for addr in [8, 12, 16, 20, 24] {
+            assert!(cache
+                .access(addr, MemReqKind::ClauseData)
+                .as_miss()
+                .is_some());
+        }
+        assert!(cache.access(0, MemReqKind::ClauseValue).as_hit().is_some());
+        assert!(cache.access(4, MemReqKind::ClauseValue).as_hit().is_some());
+        assert!(cache.access(8, MemReqKind::ClauseData).as_miss().is_some());
+        // the bypassed kind can hit the line of others but never allocate
+        assert!(cache.access(0, MemReqKind::Blocker).as_hit().is_some());
+        assert!(matches!(
+            cache.access_with_evict(28, MemReqKind::Blocker),
+            (AccessResult::Miss(_), None)
+        ));
+        assert!(!cache.probe(28));
+    }
+    #[test]
+    fn test_dirty_eviction() {
+        let cache_config = CacheConfig {
+            sets: 1,
+            ..test_utils::cache_config()
+        };
+        let mut cache = FastCache::new(&cache_config);
+        // line 0 is written after the read, line 4 is written by the miss
+        cache.access(0, MemReqKind::WatcherData);
+        cache.access(1, MemReqKind::WatchListWrite);
+        cache.access(4, MemReqKind::WatchListWrite);
+        assert_eq!(
+            cache.access_with_evict(8, MemReqKind::WatcherData).1,
+            Some(EvictedLine {
+                addr: 0,
+                dirty: true
+            })
+        );
+        assert_eq!(
+            cache.access_with_evict(12, MemReqKind::WatcherData).1,
+            Some(EvictedLine {
+                addr: 4,
+                dirty: true
+            })
+        );
+        // the refilled way is clean again
+        assert_eq!(
+            cache.access_with_evict(16, MemReqKind::WatcherData).1,
+            Some(EvictedLine {
+                addr: 8,
+                dirty: false
+            })
+        );
+        // the invalidated line is dropped without the write back
+        cache.access(16, MemReqKind::WatchListWrite);
+        assert!(cache.invalidate(16));
+        assert_eq!(cache.access_with_evict(20, MemReqKind::WatcherData).1, None);
     }
 }
@@ -17,31 +17,130 @@ pub(self) fn get_bit_lens(size: u64) -> u64 {
     }
     len
 }
-/// return the index of the set and the tag
-pub(self) fn get_set_number_from_addr(
-    addr: u64,
+/// xor all the `channel_bit_len` bits groups of the line
+fn xor_fold(line: u64, channel_bit_len: u64) -> u64 {
+    if channel_bit_len == 0 {
+        return 0;
+    }
+    let mask = (1 << channel_bit_len) - 1;
+    let mut rest = line;
+    let mut result = 0;
+    while rest != 0 {
+        result ^= rest & mask;
+        rest >>= channel_bit_len;
+    }
+    result
+}
+/// the split of the addr into `| tag | set | channel | block |`
+/// - the channel is the bits above the block by [`ChannelMapping::BitSlice`],
+///   or the xor of all the bits groups of the line by [`ChannelMapping::XorHash`]
+/// - the set is the index of the set inside the channel
+#[derive(Debug, Clone, Copy)]
+pub(self) struct AddrMapping {
     set_bit_len: u64,
-    block_bit_line: u64,
-    _channel_bit_len: u64,
-) -> (u64, u64) {
-    let set_number = (addr >> block_bit_line) & ((1 << set_bit_len) - 1);
-    let tag = addr & !((1 << block_bit_line) - 1);
-    (set_number, tag)
+    block_bit_len: u64,
+    channel_bit_len: u64,
+    channel_mapping: ChannelMapping,
+}
+impl AddrMapping {
+    /// return the channel, the index of the set in the channel and the tag
+    fn map(&self, addr: u64) -> (u64, u64, u64) {
+        let line = addr >> self.block_bit_len;
+        let high = line >> self.channel_bit_len;
+        let channel = match self.channel_mapping {
+            ChannelMapping::BitSlice => line & ((1 << self.channel_bit_len) - 1),
+            ChannelMapping::XorHash => xor_fold(line, self.channel_bit_len),
+        };
+        let set = high & ((1 << self.set_bit_len) - 1);
+        let tag = high >> self.set_bit_len;
+        (channel, set, tag)
+    }
+    /// the addr of the line, the inverse of [`AddrMapping::map`]
+    fn unmap(&self, channel: u64, set: u64, tag: u64) -> u64 {
+        let high = (tag << self.set_bit_len) | set;
+        let low = match self.channel_mapping {
+            ChannelMapping::BitSlice => channel,
+            ChannelMapping::XorHash => channel ^ xor_fold(high, self.channel_bit_len),
+        };
+        ((high << self.channel_bit_len) | low) << self.block_bit_len
+    }
+    /// the addr of the first byte of the line
+    fn get_line_addr(&self, addr: u64) -> u64 {
+        addr & !((1 << self.block_bit_len) - 1)
+    }
 }
 
 mod cache_with_fix_time;
 mod cache_with_ramulator;
+mod dram;
 mod fast_cache;
+mod mshr;
+mod prefetcher;
 #[derive(Debug, EnumAsInner)]
 pub enum AccessResult {
     Hit(u64),
     Miss(u64),
 }
+/// # L3Cache
+/// the shared cache that can be warmed up between the simulated rounds
+pub trait L3Cache: SimComponent<SharedStatus = SataccStatus> {
+    /// access the tags of the partition, the timing and the statistics are not changed
+    fn warm_up(&mut self, partition: usize, addr: u64, kind: MemReqKind);
+    /// the addr and the dirty bit of the lines of all the partitions, see [`FastCache::resident_lines`]
+    fn resident_lines(&self) -> Vec<(u64, bool)>;
+}
+impl SimComponent for Box<dyn L3Cache> {
+    type SharedStatus = SataccStatus;
+    fn update(
+        &mut self,
+        shared_status: &mut Self::SharedStatus,
+        current_cycle: usize,
+    ) -> (bool, bool) {
+        self.as_mut().update(shared_status, current_cycle)
+    }
+}
+impl L3Cache for CacheWithFixTime {
+    fn warm_up(&mut self, _partition: usize, addr: u64, kind: MemReqKind) {
+        self.fast_cache.access(addr, kind);
+    }
+    fn resident_lines(&self) -> Vec<(u64, bool)> {
+        self.fast_cache.resident_lines()
+    }
+}
+impl<D: DramBackend> L3Cache for CacheWithRamulator<D> {
+    fn warm_up(&mut self, _partition: usize, addr: u64, kind: MemReqKind) {
+        self.fast_cache.access(addr, kind);
+    }
+    fn resident_lines(&self) -> Vec<(u64, bool)> {
+        self.fast_cache.resident_lines()
+    }
+}
+/// the slices of the nuca l3 cache, each partition is a slice
+impl L3Cache for Vec<CacheWithFixTime> {
+    fn warm_up(&mut self, partition: usize, addr: u64, kind: MemReqKind) {
+        self[partition].fast_cache.access(addr, kind);
+    }
+    fn resident_lines(&self) -> Vec<(u64, bool)> {
+        let mut lines = self
+            .iter()
+            .flat_map(|slice| slice.fast_cache.resident_lines())
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines
+    }
+}
 pub use cache_with_fix_time::CacheWithFixTime;
 pub use cache_with_ramulator::CacheWithRamulator;
+use dram::DramBackend;
+pub use dram::{DramModel, DramTiming};
 use enum_as_inner::EnumAsInner;
-pub use fast_cache::CacheConfig;
 pub use fast_cache::FastCache;
+pub use fast_cache::{CacheConfig, ChannelMapping};
+pub use prefetcher::PrefetcherType;
+
+use crate::sim::SimComponent;
+
+use super::{MemReqKind, SataccStatus};
 #[cfg(test)]
 mod test {
     use super::*;
@@ -51,4 +150,35 @@ mod test {
         assert_eq!(get_bit_lens(4), 2);
         assert_eq!(get_bit_lens(8), 3);
     }
+    #[test]
+    fn test_addr_mapping() {
+        for channel_mapping in [ChannelMapping::BitSlice, ChannelMapping::XorHash] {
+            let mapping = AddrMapping {
+                set_bit_len: 2,
+                block_bit_len: 6,
+                channel_bit_len: 3,
+                channel_mapping,
+            };
+            for addr in (0..1 << 16).step_by(64) {
+                let (channel, set, tag) = mapping.map(addr);
+                assert!(channel < 8 && set < 4);
+                assert_eq!(mapping.unmap(channel, set, tag), addr);
+            }
+        }
+        let mapping = AddrMapping {
+            set_bit_len: 2,
+            block_bit_len: 6,
+            channel_bit_len: 3,
+            channel_mapping: ChannelMapping::BitSlice,
+        };
+        assert_eq!(mapping.map(64 * 9 + 3), (1, 1, 0));
+        // the lines with the same low bits are spread by the xor hash
+        let mapping = AddrMapping {
+            channel_mapping: ChannelMapping::XorHash,
+            ..mapping
+        };
+        assert_eq!(mapping.map(64 * 8).0, 1);
+        assert_eq!(mapping.map(64 * 9).0, 0);
+        assert_eq!(mapping.map(64 * 64).0, 1);
+    }
 }
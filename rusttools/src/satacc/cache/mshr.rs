@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+
+use crate::satacc::MemReq;
+
+/// the result of trying to put a missed request into the mshr
+#[derive(Debug)]
+pub enum MshrAddResult {
+    /// a new entry is allocated, the line should be fetched from the next level
+    NewEntry,
+    /// the request is merged into an on-going entry, no need to fetch again
+    Merged,
+}
+
+/// # Mshr
+/// the miss status holding registers of a cache
+/// - each entry tracks one missing line and holds the requests waiting for it
+/// - `num_entries` bounds the number of lines in flight
+/// - `max_targets` bounds the number of requests merged into one entry
+#[derive(Debug)]
+pub struct Mshr {
+    entries: BTreeMap<u64, Vec<MemReq>>,
+    num_entries: usize,
+    max_targets: usize,
+}
+
+impl Mshr {
+    pub fn new(num_entries: usize, max_targets: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            num_entries,
+            max_targets,
+        }
+    }
+    /// the line is already in flight
+    pub fn contains(&self, tag: u64) -> bool {
+        self.entries.contains_key(&tag)
+    }
+    /// test if a request to `tag` can be accepted without touching the mshr
+    pub fn can_accept(&self, tag: u64) -> bool {
+        match self.entries.get(&tag) {
+            Some(targets) => targets.len() < self.max_targets,
+            None => self.entries.len() < self.num_entries,
+        }
+    }
+    /// add a missed request, allocate a new entry or merge it into an on-going one
+    /// - when there is no free entry or the entry is full of targets, the request is returned
+    pub fn add(&mut self, tag: u64, req: MemReq) -> Result<MshrAddResult, MemReq> {
+        match self.entries.get_mut(&tag) {
+            Some(targets) => {
+                if targets.len() < self.max_targets {
+                    targets.push(req);
+                    Ok(MshrAddResult::Merged)
+                } else {
+                    Err(req)
+                }
+            }
+            None => {
+                if self.entries.len() < self.num_entries {
+                    self.entries.insert(tag, vec![req]);
+                    Ok(MshrAddResult::NewEntry)
+                } else {
+                    Err(req)
+                }
+            }
+        }
+    }
+    /// allocate an entry without any waiting request, it's used by the prefetcher
+    /// - return false if the line is in flight or there is no free entry
+    pub fn allocate(&mut self, tag: u64) -> bool {
+        if self.entries.contains_key(&tag) || self.entries.len() >= self.num_entries {
+            return false;
+        }
+        self.entries.insert(tag, vec![]);
+        true
+    }
+    /// the line is filled, release the entry and return all waiting requests
+    pub fn fill(&mut self, tag: u64) -> Option<Vec<MemReq>> {
+        self.entries.remove(&tag)
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::satacc::MemReqType;
+
+    use super::*;
+    fn req(id: usize) -> MemReq {
+        MemReq {
+            addr: 0,
+            id,
+            watcher_pe_id: 0,
+            mem_id: 0,
+            is_write: false,
+            req_type: MemReqType::WatcherReadData,
+            next_addr: None,
+        }
+    }
+    #[test]
+    fn test_mshr() {
+        let mut mshr = Mshr::new(2, 2);
+        assert!(matches!(mshr.add(0, req(0)), Ok(MshrAddResult::NewEntry)));
+        assert!(matches!(mshr.add(0, req(1)), Ok(MshrAddResult::Merged)));
+        // the entry is full of targets
+        assert!(mshr.add(0, req(2)).is_err());
+        assert!(!mshr.can_accept(0));
+        assert!(matches!(mshr.add(64, req(3)), Ok(MshrAddResult::NewEntry)));
+        // no free entry
+        assert!(mshr.add(128, req(4)).is_err());
+        assert_eq!(mshr.fill(0).unwrap().len(), 2);
+        assert!(mshr.can_accept(128));
+        assert!(mshr.fill(0).is_none());
+        assert_eq!(mshr.len(), 1);
+    }
+}
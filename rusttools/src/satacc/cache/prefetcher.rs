@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::satacc::{statistics::CacheStatistics, MemReq, MemReqKind, MemReqType};
+
+use super::{fast_cache::EvictedLine, mshr::Mshr, CacheConfig, FastCache};
+
+/// the hardware prefetcher attached to a cache
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum PrefetcherType {
+    NoPrefetch,
+    /// fetch the next `prefetch_degree` lines after each demand access
+    NextLine,
+    /// detect the stride of each request type of each pe
+    Stride,
+    /// fetch the watcher list as soon as the watcher meta data is read
+    WatchList,
+}
+
+/// the prefetch issued by [`PrefetchUnit::issue_prefetch`]
+#[derive(Debug, Clone, Copy)]
+pub struct IssuedPrefetch {
+    /// the line that should be fetched from the next level
+    pub line_addr: u64,
+    /// the kind of the demand that triggered it
+    pub kind: MemReqKind,
+    /// the partition of the demand that triggered it
+    pub mem_id: usize,
+    /// the line evicted by the prefetched line, it should be written back if it's dirty
+    pub evicted: Option<EvictedLine>,
+}
+
+/// a prefetcher observes the demand accesses and generates the addrs to prefetch
+pub trait Prefetcher {
+    /// observe a demand access to the line `line_addr`, return the addrs to prefetch
+    fn on_access(&mut self, req: &MemReq, line_addr: u64) -> Vec<u64>;
+}
+
+pub struct NextLinePrefetcher {
+    degree: u64,
+    block_size: u64,
+}
+impl Prefetcher for NextLinePrefetcher {
+    fn on_access(&mut self, _req: &MemReq, line_addr: u64) -> Vec<u64> {
+        (1..=self.degree)
+            .map(|i| line_addr + i * self.block_size)
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct StrideEntry {
+    last_line: u64,
+    stride: i64,
+    confidence: usize,
+}
+/// the stride prefetcher, there is no pc in the accelerator, so the request type of each pe is used instead
+pub struct StridePrefetcher {
+    degree: u64,
+    table: BTreeMap<(usize, MemReqKind), StrideEntry>,
+}
+impl Prefetcher for StridePrefetcher {
+    fn on_access(&mut self, req: &MemReq, line_addr: u64) -> Vec<u64> {
+        let entry = self
+            .table
+            .entry((req.watcher_pe_id, req.req_type.get_kind()))
+            .or_insert_with(|| StrideEntry {
+                last_line: line_addr,
+                ..Default::default()
+            });
+        let stride = line_addr as i64 - entry.last_line as i64;
+        if stride == 0 {
+            return vec![];
+        }
+        if stride == entry.stride {
+            entry.confidence += 1;
+        } else {
+            entry.stride = stride;
+            entry.confidence = 0;
+        }
+        entry.last_line = line_addr;
+        // need to see the same stride twice before prefetching
+        match entry.confidence {
+            0 => vec![],
+            _ => (1..=self.degree as i64)
+                .map(|i| (line_addr as i64 + i * stride) as u64)
+                .collect(),
+        }
+    }
+}
+
+/// the watcher list is read right after the meta data, so fetch it when the meta data request arrives
+pub struct WatchListPrefetcher {
+    degree: u64,
+    block_size: u64,
+}
+impl Prefetcher for WatchListPrefetcher {
+    fn on_access(&mut self, req: &MemReq, _line_addr: u64) -> Vec<u64> {
+        match (&req.req_type, req.next_addr) {
+            (MemReqType::WatcherReadMetaData, Some(watcher_addr)) => (0..self.degree)
+                .map(|i| watcher_addr + i * self.block_size)
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// # PrefetchUnit
+/// the prefetcher and the bookkeeping of the prefetched lines of a cache
+/// - the cache calls `on_demand_access` for every demand request, then issues the prefetches by `issue_prefetch`
+/// - a prefetched line is useful if a demand hits it, late if a demand arrives while it's still in flight,
+///   and useless if it's evicted before any demand
+pub struct PrefetchUnit {
+    prefetcher: Option<Box<dyn Prefetcher>>,
+    /// the addrs waiting to be prefetched, and the kind and the partition of the demand that triggered it
+    queue: VecDeque<(u64, MemReqKind, usize)>,
+    /// the prefetched lines in flight that no demand merged yet
+    in_flight_lines: BTreeSet<u64>,
+    /// the prefetched lines in the cache that no demand used yet
+    prefetched_lines: BTreeSet<u64>,
+}
+/// do not keep too many prefetch candidates, the old ones are useless anyway
+const MAX_PREFETCH_QUEUE: usize = 64;
+
+impl PrefetchUnit {
+    pub fn new(config: &CacheConfig) -> Self {
+        let degree = config.prefetch_degree;
+        let block_size = config.block_size;
+        let prefetcher: Option<Box<dyn Prefetcher>> = match config.prefetcher {
+            PrefetcherType::NoPrefetch => None,
+            PrefetcherType::NextLine => Some(Box::new(NextLinePrefetcher { degree, block_size })),
+            PrefetcherType::Stride => Some(Box::new(StridePrefetcher {
+                degree,
+                table: BTreeMap::new(),
+            })),
+            PrefetcherType::WatchList => {
+                Some(Box::new(WatchListPrefetcher { degree, block_size }))
+            }
+        };
+        Self {
+            prefetcher,
+            queue: VecDeque::new(),
+            in_flight_lines: BTreeSet::new(),
+            prefetched_lines: BTreeSet::new(),
+        }
+    }
+    /// a demand request accessed the line `line_addr`
+    pub fn on_demand_access(
+        &mut self,
+        req: &MemReq,
+        line_addr: u64,
+        stat: &mut CacheStatistics,
+    ) {
+        if self.prefetched_lines.remove(&line_addr) {
+            stat.prefetch_useful += 1;
+        } else if self.in_flight_lines.remove(&line_addr) {
+            stat.prefetch_late += 1;
+        }
+        if let Some(prefetcher) = self.prefetcher.as_mut() {
+            for addr in prefetcher.on_access(req, line_addr) {
+                if self.queue.len() >= MAX_PREFETCH_QUEUE {
+                    self.queue.pop_front();
+                }
+                self.queue
+                    .push_back((addr, req.req_type.get_kind(), req.mem_id));
+            }
+        }
+    }
+    /// try to issue the next prefetch, allocate the line in the cache and a mshr entry for it
+    /// - the prefetch to a line that already in the cache or in flight is dropped
+    /// - the prefetched line is allocated by the policy of the kind that triggered it, and dropped if the kind bypasses the cache
+    pub fn issue_prefetch(
+        &mut self,
+        fast_cache: &mut FastCache,
+        mshr: &mut Mshr,
+        stat: &mut CacheStatistics,
+    ) -> Option<IssuedPrefetch> {
+        let (addr, kind, mem_id) = self.queue.pop_front()?;
+        let line_addr = fast_cache.get_line_addr(addr);
+        if fast_cache.is_bypass(kind) || mshr.contains(line_addr) || fast_cache.probe(addr) {
+            return None;
+        }
+        if !mshr.allocate(line_addr) {
+            // no free mshr entry, try it later
+            self.queue.push_front((addr, kind, mem_id));
+            return None;
+        }
+        let (_, evicted) = fast_cache.access_with_evict(addr, kind);
+        if let Some(evicted) = evicted {
+            self.on_evict(evicted.addr, stat);
+        }
+        self.in_flight_lines.insert(line_addr);
+        stat.prefetch_issued += 1;
+        Some(IssuedPrefetch {
+            line_addr,
+            kind,
+            mem_id,
+            evicted,
+        })
+    }
+    /// the line `line_addr` is filled
+    pub fn on_fill(&mut self, line_addr: u64) {
+        if self.in_flight_lines.remove(&line_addr) {
+            self.prefetched_lines.insert(line_addr);
+        }
+    }
+    /// the line `line_addr` is evicted from the cache
+    pub fn on_evict(&mut self, line_addr: u64, stat: &mut CacheStatistics) {
+        if self.prefetched_lines.remove(&line_addr) {
+            stat.prefetch_useless += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn req(addr: u64, req_type: MemReqType, next_addr: Option<u64>) -> MemReq {
+        MemReq {
+            addr,
+            id: 0,
+            watcher_pe_id: 0,
+            mem_id: 0,
+            is_write: false,
+            req_type,
+            next_addr,
+        }
+    }
+    #[test]
+    fn test_stride_prefetcher() {
+        let mut prefetcher = StridePrefetcher {
+            degree: 2,
+            table: BTreeMap::new(),
+        };
+        assert!(prefetcher
+            .on_access(&req(0, MemReqType::WatcherReadData, None), 0)
+            .is_empty());
+        assert!(prefetcher
+            .on_access(&req(128, MemReqType::WatcherReadData, None), 128)
+            .is_empty());
+        // the same stride is seen twice
+        assert_eq!(
+            prefetcher.on_access(&req(256, MemReqType::WatcherReadData, None), 256),
+            vec![384, 512]
+        );
+        // other request types have their own stride
+        assert!(prefetcher
+            .on_access(&req(1024, MemReqType::WatcherReadBlocker, None), 1024)
+            .is_empty());
+    }
+    #[test]
+    fn test_watch_list_prefetcher() {
+        let mut prefetcher = WatchListPrefetcher {
+            degree: 1,
+            block_size: 64,
+        };
+        assert_eq!(
+            prefetcher.on_access(&req(0, MemReqType::WatcherReadMetaData, Some(4096)), 0),
+            vec![4096]
+        );
+        assert!(prefetcher
+            .on_access(&req(4096, MemReqType::WatcherReadData, None), 4096)
+            .is_empty());
+    }
+}
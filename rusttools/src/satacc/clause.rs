@@ -5,7 +5,12 @@ use crate::{
     sim::{InOutPort, SimComponent, SimReciver},
 };
 
-use super::{icnt::IcntMsgWrapper, satacc_minisat_task::ClauseTask, MemReq, SataccStatus};
+use super::{
+    icnt::IcntMsgWrapper,
+    pipeline::{Pipeline, PipelineConfig},
+    satacc_minisat_task::ClauseTask,
+    MemReq, SataccStatus,
+};
 
 struct ClauseValueTracker {
     clause_task: ClauseTask,
@@ -21,7 +26,8 @@ pub struct ClauseUnit {
     private_cache_port: InOutPort<IcntMsgWrapper<MemReq>>,
     clause_data_ready_queue: VecDeque<ClauseTask>,
     clause_value_ready_queue: VecDeque<ClauseTask>,
-    current_processing_task: Option<(usize, ClauseTask)>,
+    /// the clauses that are evaluated
+    processing_tasks: Pipeline<ClauseTask>,
     current_reading_value_task: Option<ClauseValueTracker>,
     /// task_id,(num_waiting_memreq,clauseTask)
     current_waiting_reading_value_tasks: BTreeMap<usize, (usize, ClauseTask)>,
@@ -33,6 +39,9 @@ pub struct ClauseUnit {
     total_clause_value_mem_ongoing: usize,
     current_task_id: usize,
     pipeline_clause_value_read: bool,
+    /// the watch list writes of the finished clauses, they do not block the clauses
+    watch_list_write_reqs: VecDeque<IcntMsgWrapper<MemReq>>,
+    total_watch_list_write_ongoing: usize,
 }
 #[derive(Debug)]
 enum IdleReason {
@@ -59,7 +68,7 @@ impl ClauseUnit {
             private_cache_port,
             clause_data_ready_queue: VecDeque::new(),
             clause_value_ready_queue: VecDeque::new(),
-            current_processing_task: None,
+            processing_tasks: Pipeline::new(PipelineConfig::single(1)),
             watcher_pe_id,
             total_watchers,
             clause_pe_id,
@@ -72,6 +81,72 @@ impl ClauseUnit {
             current_waiting_reading_value_tasks: BTreeMap::new(),
             current_waiting_value_memid_to_task_id: BTreeMap::new(),
             pipeline_clause_value_read,
+            watch_list_write_reqs: VecDeque::new(),
+            total_watch_list_write_ongoing: 0,
+        }
+    }
+    /// evaluate the clauses in the pipeline
+    pub fn with_pipeline(mut self, pipeline_config: PipelineConfig) -> Self {
+        self.processing_tasks = Pipeline::new(pipeline_config);
+        self
+    }
+    /// a value is returned from the l3 cache, the scratchpad or the private cache
+    fn finish_value_req(&mut self, req_id: usize) {
+        self.total_clause_value_mem_ongoing -= 1;
+        match self.pipeline_clause_value_read {
+            true => {
+                let task_id = self
+                    .current_waiting_value_memid_to_task_id
+                    .remove(&req_id)
+                    .unwrap();
+                let (req_len, _task) = self
+                    .current_waiting_reading_value_tasks
+                    .get_mut(&task_id)
+                    .unwrap();
+                *req_len -= 1;
+                tracing::debug!(req_len, task_id, "receive a req");
+                if *req_len == 0 {
+                    let (_, clause_task) = self
+                        .current_waiting_reading_value_tasks
+                        .remove(&task_id)
+                        .unwrap();
+                    self.clause_value_ready_queue.push_back(clause_task);
+                }
+            }
+            false => {
+                let current_waiting = self.current_reading_value_task.as_mut().unwrap();
+                current_waiting.unfinished_req_id.remove(&req_id);
+                if current_waiting.unfinished_req_id.is_empty() {
+                    let current_waiting = self.current_reading_value_task.take().unwrap();
+                    self.clause_value_ready_queue
+                        .push_back(current_waiting.clause_task);
+                };
+            }
+        }
+    }
+    /// cancel the clauses that are not reading values, return true if any is cancelled
+    /// - the clauses reading the data or the values are cancelled when the reads return
+    fn cancel_pending_tasks(&mut self, context: &mut SataccStatus) -> bool {
+        let cancelled = std::iter::from_fn(|| self.clause_task_in.recv().ok()).count()
+            + self.clause_data_ready_queue.len()
+            + self.clause_value_ready_queue.len()
+            + self.processing_tasks.clear();
+        self.clause_data_ready_queue.clear();
+        self.clause_value_ready_queue.clear();
+        context.finish_level_tasks(cancelled);
+        context.statistics.conflict_statistics.cancelled_clauses += cancelled;
+        cancelled > 0
+    }
+    /// the values out of the scratchpad range are read in the private cache when `private_value_cache` is set
+    fn send_value_req(
+        mem_icnt_port: &InOutPort<IcntMsgWrapper<MemReq>>,
+        private_cache_port: &InOutPort<IcntMsgWrapper<MemReq>>,
+        context: &SataccStatus,
+        req: IcntMsgWrapper<MemReq>,
+    ) -> Result<(), IcntMsgWrapper<MemReq>> {
+        match context.read_value_in_private_cache(req.msg.addr) {
+            true => private_cache_port.out_port.send(req),
+            false => mem_icnt_port.out_port.send(req),
         }
     }
 }
@@ -80,10 +155,15 @@ impl SimComponent for ClauseUnit {
     type SharedStatus = SataccStatus;
     /// ClauseUnit is a component that is responsible for sending data to the
     fn update(&mut self, context: &mut Self::SharedStatus, current_cycle: usize) -> (bool, bool) {
-        let mut busy = self.current_processing_task.is_some();
+        let mut busy = !self.processing_tasks.is_empty();
         let mut updated = false;
         // the reason for not updated
         let mut idle_reason = IdleReason::NoTask;
+        // a conflict is signaled, drop the work of the round
+        if context.is_aborting(current_cycle) && self.cancel_pending_tasks(context) {
+            busy = true;
+            updated = true;
+        }
         // first read the clause data
         if self.total_clause_data_mem_ongoing < 256 && self.clause_data_ready_queue.len() < 256 {
             if let Ok(task) = self.clause_task_in.recv() {
@@ -212,7 +292,12 @@ impl SimComponent for ClauseUnit {
                         busy = true;
                         // let id = req.msg.id;
                         let req_id = req.msg.id;
-                        match self.mem_icnt_port.out_port.send(req) {
+                        match Self::send_value_req(
+                            &self.mem_icnt_port,
+                            &self.private_cache_port,
+                            context,
+                            req,
+                        ) {
                             Ok(_) => {
                                 updated = true;
                                 self.total_clause_value_mem_ongoing += 1;
@@ -241,7 +326,12 @@ impl SimComponent for ClauseUnit {
                         if let Some(req) = reqs.waiting_to_send_reqs.pop_front() {
                             busy = true;
                             let id = req.msg.id;
-                            match self.mem_icnt_port.out_port.send(req) {
+                            match Self::send_value_req(
+                                &self.mem_icnt_port,
+                                &self.private_cache_port,
+                                context,
+                                req,
+                            ) {
                                 Ok(_) => {
                                     updated = true;
                                     reqs.unfinished_req_id.insert(id);
@@ -273,30 +363,63 @@ impl SimComponent for ClauseUnit {
             );
         }
 
-        // then update current process task
-        if let Some((finished_cycle, task)) = self.current_processing_task.take() {
+        // then update the processing tasks
+        if !self.processing_tasks.is_empty() {
             busy = true;
             updated = true;
-            if finished_cycle >= current_cycle {
-                self.current_processing_task = Some((finished_cycle, task));
-            } else {
+            while let Some(task) = self.processing_tasks.pop_finished(current_cycle) {
                 // finished
-                context.current_level_finished_tasks += 1;
+                context.finish_clause(&task, current_cycle);
                 tracing::debug!(current_cycle, "ClauseUnit finished task! ");
+                let write_reqs = task.get_watch_list_write_reqs(
+                    context,
+                    self.watcher_pe_id,
+                    self.clause_pe_id,
+                    self.total_watchers,
+                );
+                context.statistics.clause_statistics[self.watcher_pe_id].single_clause
+                    [self.clause_pe_id]
+                    .total_watch_list_write += write_reqs.len();
+                self.watch_list_write_reqs.extend(write_reqs);
             }
         }
-        // then process the value ready task
-        if self.current_processing_task.is_none() {
-            if let Some(task) = self.clause_value_ready_queue.pop_front() {
-                let process_time = task.get_process_time();
-                busy = true;
-                updated = true;
-                self.current_processing_task = Some((current_cycle + process_time, task));
+        // then process the value ready tasks
+        while self.processing_tasks.can_issue(current_cycle) {
+            let Some(task) = self.clause_value_ready_queue.pop_front() else {
+                break;
+            };
+            let config = &context.statistics.config;
+            let functional = config
+                .functional_config
+                .enable
+                .then_some(&context.functional_state);
+            let process_time = task.get_process_time(&config.clause_timing_config, functional)
+                + self.processing_tasks.latency();
+            busy = true;
+            updated = true;
+            self.processing_tasks
+                .issue(task, current_cycle, current_cycle + process_time);
+        }
+        // write the watch lists, the l3 port is shared with the reads
+        if let Some(req) = self.watch_list_write_reqs.pop_front() {
+            busy = true;
+            match self.mem_icnt_port.out_port.send(req) {
+                Ok(_) => {
+                    updated = true;
+                    self.total_watch_list_write_ongoing += 1;
+                }
+                Err(e) => {
+                    self.watch_list_write_reqs.push_front(e);
+                    idle_reason = IdleReason::SendingL3;
+                }
             }
         }
         // process memory ret
         if let Ok(mem_req) = self.mem_icnt_port.in_port.recv() {
             tracing::debug!(current_cycle, "ClauseUnit Receive mem_req! ");
+            if context.is_aborting(current_cycle) {
+                context.statistics.conflict_statistics.drained_mem_reqs += 1;
+            }
             let req_id = mem_req.msg.id;
             match mem_req.msg.req_type {
                 MemReqType::ClauseReadData(_) => {
@@ -310,38 +433,10 @@ impl SimComponent for ClauseUnit {
                     self.clause_data_ready_queue.push_back(clause_task);
                 }
                 MemReqType::ClauseReadValue(_clause_id) => {
-                    self.total_clause_value_mem_ongoing -= 1;
-                    match self.pipeline_clause_value_read {
-                        true => {
-                            let task_id = self
-                                .current_waiting_value_memid_to_task_id
-                                .remove(&req_id)
-                                .unwrap();
-                            let (req_len, _task) = self
-                                .current_waiting_reading_value_tasks
-                                .get_mut(&task_id)
-                                .unwrap();
-                            *req_len -= 1;
-                            tracing::debug!(req_len, task_id, "receive a req");
-                            if *req_len == 0 {
-                                let (_, clause_task) = self
-                                    .current_waiting_reading_value_tasks
-                                    .remove(&task_id)
-                                    .unwrap();
-                                self.clause_value_ready_queue.push_back(clause_task);
-                            }
-                        }
-                        false => {
-                            let current_waiting = self.current_reading_value_task.as_mut().unwrap();
-                            current_waiting.unfinished_req_id.remove(&mem_req.msg.id);
-                            if current_waiting.unfinished_req_id.is_empty() {
-                                let current_waiting =
-                                    self.current_reading_value_task.take().unwrap();
-                                self.clause_value_ready_queue
-                                    .push_back(current_waiting.clause_task);
-                            };
-                        }
-                    }
+                    self.finish_value_req(req_id);
+                }
+                MemReqType::ClauseWriteWatchList(_) => {
+                    self.total_watch_list_write_ongoing -= 1;
                 }
                 _ => unreachable!(),
             }
@@ -350,20 +445,22 @@ impl SimComponent for ClauseUnit {
         }
 
         // process private cache ret
-        if let Ok(_mem_req) = self.private_cache_port.in_port.recv() {
-            // let req_id = mem_req.msg.id;
-            // tracing::debug!(
-            //     req_id,
-            //     current_cycle,
-            //     "ClauseUnit Receive mem_req from private cache!"
-            // );
-            // self.total_private_mem_ongoing -= 1;
-            // match mem_req.msg.req_type {
-            //     _ => unreachable!(),
-            // }
-            // busy = true;
-            // updated = true;
-            unreachable!();
+        if let Ok(mem_req) = self.private_cache_port.in_port.recv() {
+            let req_id = mem_req.msg.id;
+            if context.is_aborting(current_cycle) {
+                context.statistics.conflict_statistics.drained_mem_reqs += 1;
+            }
+            tracing::debug!(
+                req_id,
+                current_cycle,
+                "ClauseUnit Receive mem_req from private cache!"
+            );
+            match mem_req.msg.req_type {
+                MemReqType::ClauseReadValue(_clause_id) => self.finish_value_req(req_id),
+                _ => unreachable!(),
+            }
+            busy = true;
+            updated = true;
         }
 
         match updated {
@@ -377,7 +474,9 @@ impl SimComponent for ClauseUnit {
                     [self.clause_pe_id]
                     .idle_cycle += 1;
                 // some l3 req in flight
-                if !self.mem_req_id_to_clause_task.is_empty() {
+                if !self.mem_req_id_to_clause_task.is_empty()
+                    || self.total_watch_list_write_ongoing > 0
+                {
                     idle_reason = IdleReason::WaitingL3;
                 }
                 // some private cache inflight
@@ -471,9 +570,10 @@ mod test {
                     clause_data: Some(ClauseData {
                         clause_id: 0,
                         clause_addr: 0,
-                        clause_processing_time: 1,
+                        clause_processing_time: Some(1),
                         clause_value_addr: vec![1, 2, 3],
                         clause_value_id: vec![1, 2, 3],
+                        ..Default::default()
                     }),
                 },
                 mem_target_port: 0,
@@ -528,9 +628,10 @@ mod test {
                     clause_data: Some(ClauseData {
                         clause_id: 0,
                         clause_addr: 0,
-                        clause_processing_time: 1,
+                        clause_processing_time: Some(1),
                         clause_value_addr: vec![1, 2, 3],
                         clause_value_id: vec![1, 2, 3],
+                        ..Default::default()
                     }),
                 },
                 mem_target_port: 0,
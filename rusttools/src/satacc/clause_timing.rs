@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    functional::{FunctionalState, LitValue},
+    satacc_minisat_task::ClauseData,
+};
+
+/// derive the processing time of the clauses instead of the `processing_time` passed by the builder
+/// - a clause costs `setup_cycles`, then the literals are evaluated `lanes` at a time
+/// - the literals are the ones carried by the clause, see `add_single_watcher_clause_lit`,
+///   a clause without them evaluates the values it reads
+/// - with `early_exit`, the evaluation stops at the first true literal, the values come from the
+///   functional state in the closed-loop mode and from the trace otherwise
+/// - the clauses built without a processing time, see `add_single_watcher_task_untimed`, are always derived
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default)]
+pub struct ClauseTimingConfig {
+    pub enable: bool,
+    /// the cycles before the first literal is evaluated
+    pub setup_cycles: usize,
+    /// the literals evaluated each cycle, at least 1
+    pub lanes: usize,
+    pub early_exit: bool,
+}
+impl ClauseTimingConfig {
+    /// the cycles to process the clause, `functional` is the state of the closed-loop mode
+    pub fn processing_time(
+        &self,
+        clause_data: &ClauseData,
+        functional: Option<&FunctionalState>,
+    ) -> usize {
+        if let (false, Some(processing_time)) = (self.enable, clause_data.clause_processing_time) {
+            return processing_time;
+        }
+        let lits = match clause_data.clause_lits.is_empty() {
+            true => clause_data.clause_value_addr.len(),
+            false => {
+                let true_lit = clause_data.clause_lits.iter().position(|clause_lit| {
+                    let value = match functional {
+                        Some(functional) => functional.value(clause_lit),
+                        None => clause_lit.value,
+                    };
+                    value == LitValue::True
+                });
+                match true_lit {
+                    Some(position) if self.early_exit => position + 1,
+                    _ => clause_data.clause_lits.len(),
+                }
+            }
+        };
+        self.setup_cycles + lits.div_ceil(self.lanes.max(1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        satacc::{SataccMinisatTask, Simulator},
+    };
+
+    fn build_task(processing_time: usize) -> SataccMinisatTask {
+        let mut task = SataccMinisatTask::new();
+        task.start_new_assgin();
+        task.add_watcher_task(0, 0, 4096, 0);
+        for clause_id in 0..16 {
+            task.add_single_watcher_task(
+                8192,
+                16384 + 64 * clause_id as u64,
+                clause_id,
+                processing_time,
+                0,
+            );
+            task.add_single_watcher_clause_value_addr(256, 1);
+            for lit in 0..32 {
+                let value = match lit {
+                    4 => LitValue::True,
+                    _ => LitValue::False,
+                };
+                task.add_single_watcher_clause_lit(lit + 2, value);
+            }
+        }
+        task
+    }
+
+    #[test]
+    fn test_processing_time() {
+        let mut task = build_task(7);
+        let round = task.pop_next_task().unwrap();
+        let clause_data = round.assignments[0].single_watcher_tasks[0]
+            .clause_data
+            .as_ref()
+            .unwrap();
+        let mut config = ClauseTimingConfig {
+            enable: false,
+            setup_cycles: 3,
+            lanes: 4,
+            early_exit: false,
+        };
+        assert_eq!(config.processing_time(clause_data, None), 7);
+        config.enable = true;
+        assert_eq!(config.processing_time(clause_data, None), 3 + 8);
+        // the true literal is the fifth one
+        config.early_exit = true;
+        assert_eq!(config.processing_time(clause_data, None), 3 + 2);
+        // the closed-loop mode decides by the functional state, not by the trace values
+        let mut state = FunctionalState::default();
+        state.start_round(&round);
+        assert_eq!(config.processing_time(clause_data, Some(&state)), 3 + 2);
+        // the first literal is true by the assignment, the trace value is false
+        state.assign(2);
+        assert_eq!(config.processing_time(clause_data, Some(&state)), 3 + 1);
+        config.lanes = 0;
+        assert_eq!(config.processing_time(clause_data, None), 3 + 5);
+        // the clause built without a processing time is derived even if the timing is disabled
+        let mut task = SataccMinisatTask::new();
+        task.start_new_assgin();
+        task.add_watcher_task(0, 0, 4096, 0);
+        task.add_single_watcher_task_untimed(8192, 16384, 0, 0);
+        task.add_single_watcher_clause_value_addr(256, 1);
+        let round = task.pop_next_task().unwrap();
+        let clause_data = round.assignments[0].single_watcher_tasks[0]
+            .clause_data
+            .as_ref()
+            .unwrap();
+        config.enable = false;
+        config.lanes = 1;
+        assert_eq!(config.processing_time(clause_data, None), 3 + 1);
+    }
+
+    #[test]
+    fn test_derived_timing() {
+        let simulator = Simulator::new_from_config(Config::default());
+        let (_status, passed_cycle) = simulator.run_task(&mut build_task(1)).unwrap();
+        let mut config = Config {
+            clause_timing_config: ClauseTimingConfig {
+                enable: true,
+                setup_cycles: 10,
+                lanes: 1,
+                early_exit: false,
+            },
+            ..Default::default()
+        };
+        let simulator = Simulator::new_from_config(config.clone());
+        let (_status, narrow_cycle) = simulator.run_task(&mut build_task(1)).unwrap();
+        // the processing time passed by the builder is ignored
+        assert!(narrow_cycle > passed_cycle + 16 * 32);
+        config.clause_timing_config.lanes = 8;
+        let simulator = Simulator::new_from_config(config.clone());
+        let (_status, wide_cycle) = simulator.run_task(&mut build_task(1)).unwrap();
+        assert!(wide_cycle < narrow_cycle);
+        config.clause_timing_config.early_exit = true;
+        let simulator = Simulator::new_from_config(config);
+        let (_status, early_exit_cycle) = simulator.run_task(&mut build_task(1)).unwrap();
+        assert!(early_exit_cycle < wide_cycle);
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::{SimComponent, SimReciver, SimSender};
+
+use super::{wating_task::WaitingTask, SataccStatus};
+
+/// the way to keep the copies of the value array in the private caches fresh
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum CoherenceProtocol {
+    /// the writes are not sent to the private caches, the copies may be stale
+    NoCoherence,
+    /// the directory tracks the private caches that hold each value line, a write invalidates these copies
+    DirectoryInvalidate,
+    /// each write is broadcast to all the private caches, the copies are updated in place
+    BroadcastUpdate,
+}
+
+/// the config of the value reads through the private caches
+/// - when `private_value_cache` is set, the clause units read the values out of the scratchpad range in the private cache of its tile
+/// - the trail writes the value of each assignment, the `protocol` sends the writes to the private caches
+/// - the messages share one network from the trail to the tiles, each message takes
+///   `message_bytes / bytes_per_cycle` cycles on it and arrives after `latency`, a broadcast is sent once
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct CoherenceConfig {
+    pub private_value_cache: bool,
+    pub protocol: CoherenceProtocol,
+    /// the size of an invalidation or an update message
+    pub message_bytes: u64,
+    /// the bandwidth of the coherence network, 0 means unlimited
+    pub bytes_per_cycle: u64,
+    pub latency: usize,
+}
+impl CoherenceConfig {
+    /// the writes are sent to the private caches
+    pub fn enabled(&self) -> bool {
+        self.private_value_cache && self.protocol != CoherenceProtocol::NoCoherence
+    }
+}
+
+/// the private caches that hold each value line
+#[derive(Debug, Default)]
+pub struct ValueDirectory {
+    sharers: BTreeMap<u64, BTreeSet<usize>>,
+}
+impl ValueDirectory {
+    pub fn add_sharer(&mut self, line_addr: u64, watcher_pe_id: usize) {
+        self.sharers
+            .entry(line_addr)
+            .or_default()
+            .insert(watcher_pe_id);
+    }
+    pub fn remove_sharer(&mut self, line_addr: u64, watcher_pe_id: usize) {
+        if let Some(sharers) = self.sharers.get_mut(&line_addr) {
+            sharers.remove(&watcher_pe_id);
+            if sharers.is_empty() {
+                self.sharers.remove(&line_addr);
+            }
+        }
+    }
+    /// remove the line from the directory, return the private caches that hold it
+    pub fn take_sharers(&mut self, line_addr: u64) -> BTreeSet<usize> {
+        self.sharers.remove(&line_addr).unwrap_or_default()
+    }
+}
+
+/// the message to the private cache of a tile, it contains the addr of the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoherenceMsg {
+    Invalidate(u64),
+    Update(u64),
+}
+
+/// # CoherenceUnit
+/// send the value writes from the trail to the private caches
+/// - [`CoherenceProtocol::DirectoryInvalidate`] sends an invalidation to each sharer in the [`ValueDirectory`]
+/// - [`CoherenceProtocol::BroadcastUpdate`] sends one update to all the tiles
+pub struct CoherenceUnit {
+    config: CoherenceConfig,
+    block_size: u64,
+    write_receiver: SimReciver<u64>,
+    tile_senders: Vec<SimSender<CoherenceMsg>>,
+    /// the messages on the network, (tile, message, the cycle of the write)
+    in_flight: WaitingTask<(usize, CoherenceMsg, usize)>,
+    /// the arrived messages that wait for the queue of the tile
+    arrived: VecDeque<(usize, CoherenceMsg, usize)>,
+    /// the time the network finishes its queued messages, in bytes(cycle * bytes_per_cycle)
+    busy_until: u64,
+}
+impl CoherenceUnit {
+    pub fn new(
+        config: CoherenceConfig,
+        block_size: u64,
+        write_receiver: SimReciver<u64>,
+        tile_senders: Vec<SimSender<CoherenceMsg>>,
+    ) -> Self {
+        Self {
+            config,
+            block_size,
+            write_receiver,
+            tile_senders,
+            in_flight: WaitingTask::new(),
+            arrived: VecDeque::new(),
+            busy_until: 0,
+        }
+    }
+    /// queue a message on the network, return the cycle it arrives at the tiles
+    fn push_message(&mut self, current_cycle: usize) -> usize {
+        let bytes_per_cycle = self.config.bytes_per_cycle;
+        if bytes_per_cycle == 0 {
+            return current_cycle + self.config.latency;
+        }
+        let start = (current_cycle as u64 * bytes_per_cycle).max(self.busy_until);
+        self.busy_until = start + self.config.message_bytes;
+        self.busy_until.div_ceil(bytes_per_cycle) as usize + self.config.latency
+    }
+}
+
+impl SimComponent for CoherenceUnit {
+    type SharedStatus = SataccStatus;
+    fn update(
+        &mut self,
+        shared_status: &mut Self::SharedStatus,
+        current_cycle: usize,
+    ) -> (bool, bool) {
+        let mut busy = !self.in_flight.is_empty() || !self.arrived.is_empty();
+        let mut updated = false;
+        if let Ok(addr) = self.write_receiver.recv() {
+            busy = true;
+            updated = true;
+            let line_addr = addr / self.block_size * self.block_size;
+            shared_status.statistics.coherence_statistics.value_writes += 1;
+            match self.config.protocol {
+                CoherenceProtocol::NoCoherence => {}
+                CoherenceProtocol::DirectoryInvalidate => {
+                    for tile in shared_status.value_directory.take_sharers(line_addr) {
+                        let arrival_cycle = self.push_message(current_cycle);
+                        let stat = &mut shared_status.statistics.coherence_statistics;
+                        stat.invalidations += 1;
+                        stat.bytes += self.config.message_bytes as usize;
+                        self.in_flight.push(
+                            (tile, CoherenceMsg::Invalidate(line_addr), current_cycle),
+                            arrival_cycle,
+                        );
+                    }
+                }
+                CoherenceProtocol::BroadcastUpdate => {
+                    let arrival_cycle = self.push_message(current_cycle);
+                    let stat = &mut shared_status.statistics.coherence_statistics;
+                    stat.updates += 1;
+                    stat.bytes += self.config.message_bytes as usize;
+                    for tile in 0..self.tile_senders.len() {
+                        self.in_flight.push(
+                            (tile, CoherenceMsg::Update(line_addr), current_cycle),
+                            arrival_cycle,
+                        );
+                    }
+                }
+            }
+        }
+        while let Some((arrival_cycle, msg)) = self.in_flight.pop() {
+            updated = true;
+            if arrival_cycle > current_cycle {
+                self.in_flight.push(msg, arrival_cycle);
+                break;
+            }
+            self.arrived.push_back(msg);
+        }
+        while let Some((tile, msg, write_cycle)) = self.arrived.pop_front() {
+            match self.tile_senders[tile].send(msg) {
+                Ok(_) => {
+                    updated = true;
+                    shared_status
+                        .statistics
+                        .coherence_statistics
+                        .delay
+                        .add(current_cycle - write_cycle);
+                }
+                Err(msg) => {
+                    tracing::debug!(tile, "cannot send the coherence message to the tile");
+                    self.arrived.push_front((tile, msg, write_cycle));
+                    break;
+                }
+            }
+        }
+        (busy, updated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        sim::{ChannelBuilder, SimRunner},
+    };
+
+    #[test]
+    fn test_directory() {
+        let mut directory = ValueDirectory::default();
+        directory.add_sharer(64, 1);
+        directory.add_sharer(64, 3);
+        directory.add_sharer(128, 1);
+        directory.remove_sharer(128, 1);
+        assert_eq!(directory.take_sharers(64), [1, 3].into());
+        assert!(directory.take_sharers(64).is_empty());
+        assert!(directory.take_sharers(128).is_empty());
+    }
+
+    fn run_writes(protocol: CoherenceProtocol) -> (SataccStatus, Vec<Vec<CoherenceMsg>>) {
+        let channel_builder = ChannelBuilder::new();
+        let (write_sender, write_receiver) = channel_builder.sim_channel(10);
+        let (tile_senders, tile_receivers) = channel_builder.sim_channel_array(10, 2);
+        let coherence_config = CoherenceConfig {
+            private_value_cache: true,
+            protocol,
+            message_bytes: 8,
+            bytes_per_cycle: 4,
+            latency: 3,
+        };
+        let unit = CoherenceUnit::new(coherence_config, 64, write_receiver, tile_senders);
+        let mut status = SataccStatus::new(Config {
+            coherence_config,
+            ..Config::default()
+        });
+        status.value_directory.add_sharer(64, 1);
+        let mut sim_runner = SimRunner::new(unit, status);
+        write_sender.send(70).unwrap();
+        write_sender.send(200).unwrap();
+        sim_runner.run().unwrap();
+        let msgs = tile_receivers
+            .iter()
+            .map(|receiver| std::iter::from_fn(|| receiver.recv().ok()).collect())
+            .collect();
+        let (_, status, _) = sim_runner.into_inner();
+        (status, msgs)
+    }
+
+    #[test]
+    fn test_directory_invalidate() {
+        let (status, msgs) = run_writes(CoherenceProtocol::DirectoryInvalidate);
+        // only the sharer of the first line is invalidated
+        assert_eq!(msgs, vec![vec![], vec![CoherenceMsg::Invalidate(64)]]);
+        let stat = &status.statistics.coherence_statistics;
+        assert_eq!(stat.value_writes, 2);
+        assert_eq!(stat.invalidations, 1);
+        assert_eq!(stat.bytes, 8);
+        // 2 cycles on the network and 3 cycles latency
+        assert!(stat.delay.get_average() >= 5.0);
+    }
+
+    #[test]
+    fn test_broadcast_update() {
+        let (status, msgs) = run_writes(CoherenceProtocol::BroadcastUpdate);
+        let updates = vec![CoherenceMsg::Update(64), CoherenceMsg::Update(192)];
+        assert_eq!(msgs, vec![updates.clone(), updates]);
+        let stat = &status.statistics.coherence_statistics;
+        assert_eq!(stat.updates, 2);
+        assert_eq!(stat.bytes, 16);
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+/// stop the round at the first conflict like minisat
+/// - a clause is conflicting if the trace marks it, or if the functional evaluation finds it, see [`super::FunctionalConfig`]
+/// - `signal_latency` cycles after the conflict, the trail, the watchers and the clause units cancel the work that is not issued yet,
+///   the memory requests in flight are drained and their results are dropped
+/// - the next round starts `flush_latency` cycles after the signal and after all the work of the round is drained
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default)]
+pub struct ConflictConfig {
+    pub enable: bool,
+    /// the cycles for the conflict signal to reach the trail and the pes
+    pub signal_latency: usize,
+    /// the cycles to flush the pipelines after the signal
+    pub flush_latency: usize,
+}
+impl ConflictConfig {
+    /// the pes cancel their work for the conflict found at `conflict_cycle`
+    pub fn is_aborting(&self, conflict_cycle: Option<usize>, current_cycle: usize) -> bool {
+        match conflict_cycle {
+            Some(conflict_cycle) if self.enable => {
+                current_cycle >= conflict_cycle + self.signal_latency
+            }
+            _ => false,
+        }
+    }
+    /// the first cycle the next round can start
+    pub fn flush_end(&self, conflict_cycle: usize) -> usize {
+        conflict_cycle + self.signal_latency + self.flush_latency
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        satacc::{FunctionalConfig, LitValue, SataccMinisatTask, Simulator},
+    };
+
+    #[test]
+    fn test_is_aborting() {
+        let mut config = ConflictConfig {
+            enable: false,
+            signal_latency: 4,
+            flush_latency: 10,
+        };
+        assert!(!config.is_aborting(Some(10), 20));
+        config.enable = true;
+        assert!(!config.is_aborting(None, 20));
+        assert!(!config.is_aborting(Some(10), 13));
+        assert!(config.is_aborting(Some(10), 14));
+        assert_eq!(config.flush_end(10), 24);
+    }
+
+    /// the clause of the first assignment is conflicting, the other assignments have long watch lists
+    fn build_conflict_round(mark_conflict: bool) -> SataccMinisatTask {
+        let mut task = SataccMinisatTask::new();
+        task.start_new_assgin();
+        task.add_watcher_task(0, 0, 4096, 0);
+        task.add_single_watcher_task(8192, 16384, 0, 4, 0);
+        task.add_single_watcher_clause_value_addr(256, 1);
+        task.add_single_watcher_clause_lit(1, LitValue::False);
+        task.add_single_watcher_clause_lit(3, LitValue::False);
+        if mark_conflict {
+            task.set_clause_conflict();
+        }
+        for lit in 1..32 {
+            let lit = lit * 4;
+            task.add_watcher_task(1, 64 * lit as u64, 65536 * lit as u64, lit);
+            for watcher in 0..64 {
+                let clause_id = lit * 64 + watcher;
+                task.add_single_watcher_task(
+                    0x100_0000 + 64 * clause_id as u64,
+                    0x1000_0000 + 64 * clause_id as u64,
+                    clause_id,
+                    8,
+                    lit,
+                );
+                task.add_single_watcher_clause_value_addr(0x10_0000 + 64 * clause_id as u64, 1);
+            }
+        }
+        task
+    }
+
+    #[test]
+    fn test_conflict_abort() {
+        let simulator = Simulator::new_from_config(Config::default());
+        let (status, full_cycle) = simulator.run_task(&mut build_conflict_round(true)).unwrap();
+        assert_eq!(status.statistics.conflict_statistics.conflicts, 0);
+
+        let mut config = Config {
+            conflict_config: ConflictConfig {
+                enable: true,
+                signal_latency: 2,
+                flush_latency: 50,
+            },
+            ..Default::default()
+        };
+        let simulator = Simulator::new_from_config(config.clone());
+        let (status, cycle) = simulator.run_task(&mut build_conflict_round(true)).unwrap();
+        let statistics = &status.statistics.conflict_statistics;
+        assert_eq!(statistics.conflicts, 1);
+        assert!(statistics.cancelled_assignments + statistics.cancelled_watchers > 0);
+        assert!(statistics.flush_cycles >= 52);
+        assert!(cycle < full_cycle);
+
+        // the conflict found by the functional evaluation aborts the round too
+        config.functional_config = FunctionalConfig {
+            enable: true,
+            feedback_latency: 0,
+        };
+        let simulator = Simulator::new_from_config(config);
+        let (status, _cycle) = simulator
+            .run_task(&mut build_conflict_round(false))
+            .unwrap();
+        assert_eq!(status.statistics.functional_statistics.conflict, 1);
+        let statistics = &status.statistics.conflict_statistics;
+        assert_eq!(statistics.conflicts, 1);
+        // the other assignments wait to be implied, so they are cancelled in the trail
+        assert_eq!(statistics.cancelled_assignments, 31);
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+
+/// the fast-forward mode, the fast-forwarded rounds only warm up the caches without timing
+/// - the first `warm_up_rounds` are fast-forwarded, then `detailed_rounds` are simulated in detail,
+///   then `fast_forward_rounds` are fast-forwarded again, and so on
+/// - the statistics and the cycles only count the detailed windows
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default)]
+pub struct FastForwardConfig {
+    pub enable: bool,
+    /// the rounds fast-forwarded before the first detailed window
+    pub warm_up_rounds: usize,
+    /// the rounds of each detailed window, 0 means the first window runs to the end
+    pub detailed_rounds: usize,
+    /// the rounds fast-forwarded after each detailed window
+    pub fast_forward_rounds: usize,
+}
+impl FastForwardConfig {
+    /// the round is simulated in detail, all the rounds are detailed if the mode is disabled
+    pub fn is_detailed(&self, round: usize) -> bool {
+        if !self.enable {
+            return true;
+        }
+        if round < self.warm_up_rounds {
+            return false;
+        }
+        if self.detailed_rounds == 0 {
+            return true;
+        }
+        (round - self.warm_up_rounds) % (self.detailed_rounds + self.fast_forward_rounds)
+            < self.detailed_rounds
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        satacc::{
+            simulator::RunMode, Cnf, CoherenceProtocol, LayoutConfig, MemoryLayout,
+            SataccMinisatTask, SataccStatus, Simulator, SolveLimits, Solver, SyntheticConfig,
+            SyntheticGenerator,
+        },
+        sim::SimRunner,
+    };
+
+    #[test]
+    fn test_is_detailed() {
+        let mut config = FastForwardConfig {
+            enable: false,
+            warm_up_rounds: 3,
+            detailed_rounds: 2,
+            fast_forward_rounds: 1,
+        };
+        assert!((0..10).all(|round| config.is_detailed(round)));
+        config.enable = true;
+        let detailed = (0..10)
+            .filter(|&round| config.is_detailed(round))
+            .collect::<Vec<_>>();
+        assert_eq!(detailed, [3, 4, 6, 7, 9]);
+        config.detailed_rounds = 0;
+        let detailed = (0..10)
+            .filter(|&round| config.is_detailed(round))
+            .collect::<Vec<_>>();
+        assert_eq!(detailed, [3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        let synthetic = SyntheticConfig {
+            rounds: 20,
+            ..Default::default()
+        };
+        let config = Config {
+            fast_forward_config: FastForwardConfig {
+                enable: true,
+                warm_up_rounds: 10,
+                detailed_rounds: 4,
+                fast_forward_rounds: 2,
+            },
+            ..Default::default()
+        };
+        let (status, cycle) = Simulator::new_from_config(config.clone())
+            .run_task(
+                &mut SyntheticGenerator::new(synthetic.clone())
+                    .unwrap()
+                    .generate()
+                    .unwrap(),
+            )
+            .unwrap();
+        let stat = &status.statistics.fast_forward_statistics;
+        assert_eq!(stat.fast_forward_rounds, 12);
+        assert_eq!(stat.detailed_rounds, 8);
+        let windows = stat
+            .windows
+            .iter()
+            .map(|window| (window.first_round, window.rounds))
+            .collect::<Vec<_>>();
+        assert_eq!(windows, [(10, 4), (16, 4)]);
+        assert_eq!(
+            stat.windows
+                .iter()
+                .map(|window| window.cycles)
+                .sum::<usize>(),
+            cycle
+        );
+        // only the detailed rounds are counted
+        assert_eq!(status.statistics.average_assignments.count, 8);
+
+        // the same 4 rounds miss less after the warm-up
+        let run_window = |warm_up_rounds: usize| {
+            let mut task = SyntheticGenerator::new(synthetic.clone())
+                .unwrap()
+                .generate()
+                .unwrap();
+            task.decisions.drain(..10 - warm_up_rounds);
+            task.decisions.truncate(warm_up_rounds + 4);
+            let config = Config {
+                fast_forward_config: FastForwardConfig {
+                    enable: true,
+                    warm_up_rounds,
+                    detailed_rounds: 0,
+                    fast_forward_rounds: 0,
+                },
+                ..Default::default()
+            };
+            let (status, _) = Simulator::new_from_config(config)
+                .run_task(&mut task)
+                .unwrap();
+            status.statistics.l3_cache_statistics.cache_misses
+        };
+        assert!(run_window(10) < run_window(0));
+    }
+
+    /// the warm-up leaves the same lines in the caches as the detailed simulation of the solver rounds,
+    /// the caches are large enough that the order of the accesses does not matter
+    #[test]
+    fn test_warm_up_fidelity() {
+        let cnf = Cnf::from_file("test_data/pigeonhole_5.cnf").unwrap();
+        let run = |config: &Config, detailed: bool| {
+            let mut task = SataccMinisatTask::new();
+            Solver::new(&cnf, MemoryLayout::new(LayoutConfig::default(), 16))
+                .unwrap()
+                .solve(SolveLimits::default(), &mut task)
+                .unwrap();
+            let (task_sender, comp) =
+                Simulator::new_from_config(config.clone()).build(RunMode::RealRoundGap);
+            let mut sim_runner = SimRunner::new(comp, SataccStatus::new(config.clone()));
+            while let Some(round) = task.pop_next_task() {
+                if detailed {
+                    assert!(task_sender.send(round).is_ok());
+                    sim_runner.run().unwrap();
+                } else {
+                    let (sim, status) = sim_runner.get_sim_and_status_mut();
+                    sim.warm_up(&round, status);
+                }
+            }
+            sim_runner.into_inner().0.cache_lines()
+        };
+        let mut config = Config::default();
+        for private_value_cache in [false, true] {
+            config.coherence_config.private_value_cache = private_value_cache;
+            config.coherence_config.protocol = CoherenceProtocol::NoCoherence;
+            let (l3_lines, private_lines) = run(&config, true);
+            assert!(l3_lines.iter().any(|&(_, dirty)| dirty));
+            assert_eq!(
+                private_lines.iter().any(|lines| !lines.is_empty()),
+                private_value_cache
+            );
+            assert_eq!(run(&config, false), (l3_lines, private_lines));
+        }
+    }
+}
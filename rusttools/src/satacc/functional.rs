@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    satacc_minisat_task::{ClauseData, SingleRoundTask},
+    statistics::FunctionalStatistics,
+};
+
+/// the value of a literal
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum LitValue {
+    True,
+    False,
+    Undef,
+}
+impl From<Option<bool>> for LitValue {
+    fn from(value: Option<bool>) -> Self {
+        match value {
+            Some(true) => LitValue::True,
+            Some(false) => LitValue::False,
+            None => LitValue::Undef,
+        }
+    }
+}
+
+/// a literal of the clause and its value when the software inspected the clause
+/// - the literal index is the same as the `watcher_id`, the variable is `lit / 2`
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct ClauseLit {
+    pub lit: usize,
+    pub value: LitValue,
+}
+
+/// the closed-loop mode, the clause units evaluate the clauses and the trail sends an assignment when it's implied
+/// - the clauses carry their literals, see `add_single_watcher_clause_lit`
+/// - the first level of a round is sent at once, the other assignments wait until a clause unit implies them
+/// - an assignment that is never implied is sent in the software order when all the sent work is finished
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default)]
+pub struct FunctionalConfig {
+    pub enable: bool,
+    /// the cycles from a clause unit finding the implied literal to the trail receiving it
+    pub feedback_latency: usize,
+}
+
+/// the result of a clause evaluation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClauseResult {
+    Satisfied,
+    /// all the other literals are false, the literal is implied
+    Unit(usize),
+    Conflict,
+    /// more than one literal is unassigned, or the clause carries no literal
+    Unresolved,
+}
+
+/// evaluate the clause by the values of its literals
+pub fn evaluate_clause(lits: impl IntoIterator<Item = ClauseLit>) -> ClauseResult {
+    let mut any_lit = false;
+    let mut undef = None;
+    let mut undefs = 0;
+    for ClauseLit { lit, value } in lits {
+        any_lit = true;
+        match value {
+            LitValue::True => return ClauseResult::Satisfied,
+            LitValue::False => {}
+            LitValue::Undef => {
+                undefs += 1;
+                undef = Some(lit);
+            }
+        }
+    }
+    match (any_lit, undefs, undef) {
+        (false, _, _) => ClauseResult::Unresolved,
+        (true, 0, _) => ClauseResult::Conflict,
+        (true, 1, Some(lit)) => ClauseResult::Unit(lit),
+        _ => ClauseResult::Unresolved,
+    }
+}
+
+/// # FunctionalState
+/// the assignments of the current round in the closed-loop mode
+/// - a variable of the round is unassigned until the trail accepts its assignment,
+///   the other variables keep the value carried by the clause
+#[derive(Debug, Default)]
+pub struct FunctionalState {
+    round_vars: HashSet<usize>,
+    /// the variable to the assigned literal
+    assigned: HashMap<usize, usize>,
+    /// the literals implied by the clause units, with the cycle the trail receives them
+    implied: VecDeque<(usize, usize)>,
+}
+impl FunctionalState {
+    pub fn start_round(&mut self, round: &SingleRoundTask) {
+        self.round_vars = round
+            .assignments
+            .iter()
+            .map(|assignment| assignment.watcher_id / 2)
+            .collect();
+        self.assigned.clear();
+        self.implied.clear();
+    }
+    /// assign the literal, false if the variable is already assigned
+    pub fn assign(&mut self, lit: usize) -> bool {
+        match self.assigned.entry(lit / 2) {
+            std::collections::hash_map::Entry::Occupied(_) => false,
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(lit);
+                true
+            }
+        }
+    }
+    pub fn value(&self, clause_lit: &ClauseLit) -> LitValue {
+        let var = clause_lit.lit / 2;
+        match self.assigned.get(&var) {
+            Some(&lit) => match lit == clause_lit.lit {
+                true => LitValue::True,
+                false => LitValue::False,
+            },
+            None if self.round_vars.contains(&var) => LitValue::Undef,
+            None => clause_lit.value,
+        }
+    }
+    /// evaluate the clause by the current assignments, the implied literal reaches the trail at `ready_cycle`
+    pub fn evaluate(
+        &mut self,
+        clause_data: &ClauseData,
+        ready_cycle: usize,
+        statistics: &mut FunctionalStatistics,
+    ) -> ClauseResult {
+        let result = evaluate_clause(clause_data.clause_lits.iter().map(|clause_lit| ClauseLit {
+            lit: clause_lit.lit,
+            value: self.value(clause_lit),
+        }));
+        match result {
+            ClauseResult::Satisfied => statistics.satisfied += 1,
+            ClauseResult::Unit(lit) => {
+                statistics.unit += 1;
+                self.implied.push_back((ready_cycle, lit));
+            }
+            ClauseResult::Conflict => statistics.conflict += 1,
+            ClauseResult::Unresolved => statistics.unresolved += 1,
+        }
+        result
+    }
+    /// the implied literal that reaches the trail before `current_cycle`
+    pub fn pop_implied(&mut self, current_cycle: usize) -> Option<usize> {
+        match self.implied.front() {
+            Some(&(ready_cycle, lit)) if ready_cycle <= current_cycle => {
+                self.implied.pop_front();
+                Some(lit)
+            }
+            _ => None,
+        }
+    }
+    pub fn has_implied(&self) -> bool {
+        !self.implied.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        satacc::{SataccMinisatTask, Simulator},
+    };
+
+    fn lit(lit: usize, value: LitValue) -> ClauseLit {
+        ClauseLit { lit, value }
+    }
+
+    #[test]
+    fn test_evaluate_clause() {
+        use LitValue::*;
+        assert_eq!(
+            evaluate_clause([lit(0, False), lit(2, True)]),
+            ClauseResult::Satisfied
+        );
+        assert_eq!(
+            evaluate_clause([lit(0, False), lit(3, Undef)]),
+            ClauseResult::Unit(3)
+        );
+        assert_eq!(
+            evaluate_clause([lit(0, False), lit(3, False)]),
+            ClauseResult::Conflict
+        );
+        assert_eq!(
+            evaluate_clause([lit(0, Undef), lit(3, Undef)]),
+            ClauseResult::Unresolved
+        );
+        assert_eq!(evaluate_clause([]), ClauseResult::Unresolved);
+    }
+
+    /// a chain `x0 -> x1 -> x2`, the clause `(!x0 | x1)` is read by the watch list of `x0`
+    fn build_chain() -> SataccMinisatTask {
+        let mut task = SataccMinisatTask::new();
+        task.start_new_assgin();
+        for (level, lit, clause_lits) in [(0, 0, [1, 2]), (1, 2, [3, 4]), (2, 4, [5, 0])] {
+            task.add_watcher_task(level, 64 * lit as u64, 4096 + 64 * lit as u64, lit);
+            task.add_single_watcher_task(8192, 16384 + 64 * lit as u64, lit, 4, lit);
+            task.add_single_watcher_clause_value_addr(256 + lit as u64, lit / 2 + 1);
+            for clause_lit in clause_lits {
+                task.add_single_watcher_clause_lit(clause_lit, LitValue::False);
+            }
+        }
+        task
+    }
+
+    #[test]
+    fn test_state() {
+        let mut task = build_chain();
+        let round = task.pop_next_task().unwrap();
+        let mut state = FunctionalState::default();
+        let mut statistics = FunctionalStatistics::default();
+        state.start_round(&round);
+        state.assign(0);
+        let clause_data = |index: usize| {
+            round.assignments[index].single_watcher_tasks[0]
+                .clause_data
+                .as_ref()
+                .unwrap()
+        };
+        // x1 is a variable of the round, it's unassigned until the trail accepts it
+        assert_eq!(
+            state.evaluate(clause_data(0), 5, &mut statistics),
+            ClauseResult::Unit(2)
+        );
+        assert_eq!(state.pop_implied(4), None);
+        assert_eq!(state.pop_implied(5), Some(2));
+        assert!(state.assign(2));
+        assert!(!state.assign(3));
+        // the carried value of x2 is false, but x2 is in the round
+        assert_eq!(state.value(&lit(5, LitValue::False)), LitValue::Undef);
+        assert_eq!(state.value(&lit(7, LitValue::True)), LitValue::True);
+        assert_eq!(statistics.unit, 1);
+    }
+
+    #[test]
+    fn test_closed_loop() {
+        let simulator = Simulator::new_from_config(Config::default());
+        let (_status, replay_cycle) = simulator.run_task(&mut build_chain()).unwrap();
+
+        let mut config = Config::default();
+        config.functional_config.enable = true;
+        let simulator = Simulator::new_from_config(config.clone());
+        let (status, cycle) = simulator.run_task(&mut build_chain()).unwrap();
+        let statistics = &status.statistics.functional_statistics;
+        assert_eq!(statistics.unit, 2);
+        assert_eq!(statistics.implied_assignments, 2);
+        assert_eq!(statistics.unimplied_assignments, 0);
+        assert_eq!(statistics.satisfied, 1);
+        // the implied assignments wait for the clauses that imply them
+        assert!(cycle > replay_cycle);
+
+        config.functional_config.feedback_latency = 100;
+        let simulator = Simulator::new_from_config(config);
+        let (_status, delayed_cycle) = simulator.run_task(&mut build_chain()).unwrap();
+        // both implications wait for the feedback
+        assert!(delayed_cycle > cycle + 150);
+
+        // without the literals nothing is implied, the assignments are sent one by one
+        let mut task = SataccMinisatTask::new();
+        for record in build_chain().to_records() {
+            if !matches!(record, crate::satacc::TraceRecord::ClauseLit { .. }) {
+                task.apply_record(record);
+            }
+        }
+        let mut config = Config::default();
+        config.functional_config.enable = true;
+        let simulator = Simulator::new_from_config(config);
+        let (status, _cycle) = simulator.run_task(&mut task).unwrap();
+        let statistics = &status.statistics.functional_statistics;
+        assert_eq!(statistics.unresolved, 3);
+        assert_eq!(statistics.unimplied_assignments, 2);
+    }
+}
@@ -10,6 +10,8 @@ pub trait IcntMessage {
 pub struct SimpleIcnt<T> {
     pub ports: Vec<InOutPort<T>>,
     in_transit_messages: WaitingTask<T>,
+    /// the mesh node of each port, several ports can share a node
+    port_nodes: Vec<usize>,
     row_size: usize,
     ideal_icnt: bool,
 }
@@ -23,10 +25,19 @@ impl<T> SimpleIcnt<T> {
         SimpleIcnt {
             ports,
             in_transit_messages: WaitingTask::new(),
+            port_nodes: (0..num_ports).collect(),
             row_size: ports_sqrt,
             ideal_icnt,
         }
     }
+    /// place the ports on the mesh nodes, the ports on the same node have no hop between them
+    pub fn with_port_nodes(mut self, port_nodes: Vec<usize>) -> Self {
+        assert_eq!(port_nodes.len(), self.ports.len());
+        let num_nodes = port_nodes.iter().max().map_or(0, |node| node + 1);
+        self.row_size = ((num_nodes as f64).sqrt().floor() as usize).max(1);
+        self.port_nodes = port_nodes;
+        self
+    }
     pub fn new_with_config(
         n_ports: usize,
         channel_size: usize,
@@ -93,11 +104,12 @@ where
                     let cycle_to_go = match self.ideal_icnt {
                         true => 1,
                         false => {
-                            let output_port = message.get_target_port();
-                            let input_row = input_port / self.row_size;
-                            let input_col = input_port % self.row_size;
-                            let output_row = output_port / self.row_size;
-                            let output_col = output_port % self.row_size;
+                            let input_node = self.port_nodes[input_port];
+                            let output_node = self.port_nodes[message.get_target_port()];
+                            let input_row = input_node / self.row_size;
+                            let input_col = input_node % self.row_size;
+                            let output_row = output_node / self.row_size;
+                            let output_col = output_node % self.row_size;
                             input_row.abs_diff(output_row) + input_col.abs_diff(output_col)
                         }
                     };
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use eyre::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// how the variable values are packed in the value array
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ValueEncoding {
+    /// one byte for each variable
+    Byte,
+    /// two bits for each variable like minisat's `lbool`, four variables share a byte
+    TwoBit,
+}
+
+/// where the watch lists and the clauses are placed
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutPlacement {
+    /// the watch lists and the clauses are allocated one after another in one region
+    Packed,
+    /// each watcher pe owns `partition_bytes` of each region, a watch list is placed in the partition of its pe,
+    /// a clause is placed in the partition of the pe that first reads it
+    PerPePartitioned,
+}
+
+/// the config of the layout model, it places the solver data by the logical ids
+/// - the value array is shared by all the pes, the `value_encoding` decides the bytes of a variable
+/// - the watch list meta data has `watch_meta_bytes` for each literal, the watchers of a literal are a growing vector
+/// - the clauses are in the arena, each has a `clause_header_bytes` header and `lit_bytes` for each literal
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct LayoutConfig {
+    pub value_base: u64,
+    pub value_encoding: ValueEncoding,
+    pub watch_meta_base: u64,
+    pub watch_meta_bytes: u64,
+    pub watch_data_base: u64,
+    /// a watcher is the clause ref and the blocker
+    pub watcher_bytes: u64,
+    pub clause_base: u64,
+    pub clause_header_bytes: u64,
+    pub lit_bytes: u64,
+    pub placement: LayoutPlacement,
+    /// the bytes of each partition of a region, used by [`LayoutPlacement::PerPePartitioned`]
+    pub partition_bytes: u64,
+}
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            value_base: 0x1000_0000,
+            value_encoding: ValueEncoding::Byte,
+            watch_meta_base: 0x2000_0000,
+            watch_meta_bytes: 16,
+            watch_data_base: 0x4000_0000,
+            watcher_bytes: 8,
+            clause_base: 0x8000_0000,
+            clause_header_bytes: 4,
+            lit_bytes: 4,
+            placement: LayoutPlacement::Packed,
+            partition_bytes: 0x100_0000,
+        }
+    }
+}
+
+/// # MemoryLayout
+/// the addresses of the solver data, it follows the layout of minisat
+/// - a watch list is reallocated with double capacity when it's full, the old space is not reused
+/// - the clauses are allocated one after another in the arena when they are first seen, the learnt clauses too
+/// - the literal index is the `watcher_id`, so the pe of a watch list is the same as [`super::satacc_minisat_task::WatcherTask::get_watcher_pe_id`]
+#[derive(Debug, Clone)]
+pub struct MemoryLayout {
+    config: LayoutConfig,
+    num_partitions: usize,
+    watch_data_addrs: Vec<u64>,
+    watch_capacities: Vec<usize>,
+    /// the next free addr of each partition
+    next_watch_data_addrs: Vec<u64>,
+    next_clause_addrs: Vec<u64>,
+    clause_addrs: HashMap<usize, u64>,
+}
+impl MemoryLayout {
+    /// `num_partitions` is the number of watcher pes, it's only used by [`LayoutPlacement::PerPePartitioned`]
+    pub fn new(config: LayoutConfig, num_partitions: usize) -> Self {
+        let num_partitions = match config.placement {
+            LayoutPlacement::Packed => 1,
+            LayoutPlacement::PerPePartitioned => num_partitions.max(1),
+        };
+        let partition_bases = |base: u64| {
+            (0..num_partitions as u64)
+                .map(|partition| base + partition * config.partition_bytes)
+                .collect::<Vec<_>>()
+        };
+        Self {
+            num_partitions,
+            watch_data_addrs: vec![],
+            watch_capacities: vec![],
+            next_watch_data_addrs: partition_bases(config.watch_data_base),
+            next_clause_addrs: partition_bases(config.clause_base),
+            clause_addrs: HashMap::new(),
+            config,
+        }
+    }
+    /// the partition of the watch list of the literal index
+    fn partition(&self, lit: usize) -> usize {
+        (lit / 2) % self.num_partitions
+    }
+    pub fn value_addr(&self, var: usize) -> u64 {
+        match self.config.value_encoding {
+            ValueEncoding::Byte => self.config.value_base + var as u64,
+            ValueEncoding::TwoBit => self.config.value_base + var as u64 / 4,
+        }
+    }
+    pub fn watch_meta_addr(&self, lit: usize) -> u64 {
+        let partition = self.partition(lit) as u64;
+        // the literals of a partition are contiguous
+        let local_index = (lit / 2 / self.num_partitions * 2 + lit % 2) as u64;
+        self.config.watch_meta_base
+            + partition * self.config.partition_bytes
+            + local_index * self.config.watch_meta_bytes
+    }
+    /// the addr of the watchers of the literal, a list without watchers is at the start of its partition
+    pub fn watch_data_addr(&self, lit: usize) -> u64 {
+        match self.watch_capacities.get(lit) {
+            Some(&capacity) if capacity > 0 => self.watch_data_addrs[lit],
+            _ => {
+                self.config.watch_data_base
+                    + self.partition(lit) as u64 * self.config.partition_bytes
+            }
+        }
+    }
+    /// the addr of the `index`th watcher of the literal
+    pub fn watcher_addr(&self, lit: usize, index: usize) -> u64 {
+        self.watch_data_addr(lit) + index as u64 * self.config.watcher_bytes
+    }
+    /// the end of the partition of a region that starts at `base`, a packed region has no end
+    fn partition_end(&self, base: u64, partition: usize) -> u64 {
+        match self.config.placement {
+            LayoutPlacement::Packed => u64::MAX,
+            LayoutPlacement::PerPePartitioned => {
+                base + (partition as u64 + 1) * self.config.partition_bytes
+            }
+        }
+    }
+    /// make room for `len` watchers in the watch list of the literal
+    /// - fails if the list doesn't fit in the rest of its partition
+    pub fn reserve_watchers(&mut self, lit: usize, len: usize) -> Result<()> {
+        if lit >= self.watch_capacities.len() {
+            self.watch_capacities.resize(lit + 1, 0);
+            self.watch_data_addrs.resize(lit + 1, 0);
+        }
+        let capacity = self.watch_capacities[lit];
+        if len <= capacity {
+            return Ok(());
+        }
+        let capacity = len.max(capacity * 2).max(4);
+        let partition = self.partition(lit);
+        let addr = self.next_watch_data_addrs[partition];
+        let end = addr + capacity as u64 * self.config.watcher_bytes;
+        if end > self.partition_end(self.config.watch_data_base, partition) {
+            bail!(
+                "the watch list of literal {lit} with {capacity} watchers doesn't fit in partition {partition} of {} bytes",
+                self.config.partition_bytes
+            );
+        }
+        self.watch_capacities[lit] = capacity;
+        self.watch_data_addrs[lit] = addr;
+        self.next_watch_data_addrs[partition] = end;
+        Ok(())
+    }
+    /// allocate a clause of `len` literals, it's placed in the partition of the watch list `lit`
+    /// - fails if the clause doesn't fit in the rest of the partition
+    pub fn alloc_clause(&mut self, len: usize, lit: usize) -> Result<u64> {
+        let partition = self.partition(lit);
+        let addr = self.next_clause_addrs[partition];
+        let end = addr + self.config.clause_header_bytes + len as u64 * self.config.lit_bytes;
+        if end > self.partition_end(self.config.clause_base, partition) {
+            bail!(
+                "the clause of {len} literals doesn't fit in partition {partition} of {} bytes",
+                self.config.partition_bytes
+            );
+        }
+        self.next_clause_addrs[partition] = end;
+        Ok(addr)
+    }
+    /// the addr of the clause, it's allocated when it's first read by the watch list `lit`
+    pub fn clause_addr(&mut self, clause_id: usize, len: usize, lit: usize) -> Result<u64> {
+        if let Some(&addr) = self.clause_addrs.get(&clause_id) {
+            return Ok(addr);
+        }
+        let addr = self.alloc_clause(len, lit)?;
+        self.clause_addrs.insert(clause_id, addr);
+        Ok(addr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::satacc::SataccMinisatTask;
+
+    #[test]
+    fn test_layout() {
+        let mut layout = MemoryLayout::new(LayoutConfig::default(), 4);
+        let lit = 3;
+        assert_eq!(layout.value_addr(1), 0x1000_0001);
+        assert_eq!(layout.watch_meta_addr(lit), 0x2000_0000 + 3 * 16);
+        layout.reserve_watchers(lit, 1).unwrap();
+        layout.reserve_watchers(0, 1).unwrap();
+        assert_eq!(layout.watch_data_addr(lit), 0x4000_0000);
+        // the list grows out of its 4 watchers and moves after the other list
+        layout.reserve_watchers(lit, 4).unwrap();
+        assert_eq!(layout.watch_data_addr(lit), 0x4000_0000);
+        layout.reserve_watchers(lit, 5).unwrap();
+        assert_eq!(layout.watch_data_addr(lit), 0x4000_0000 + 2 * 4 * 8);
+        assert_eq!(layout.alloc_clause(3, lit).unwrap(), 0x8000_0000);
+        assert_eq!(layout.clause_addr(9, 2, lit).unwrap(), 0x8000_0000 + 16);
+        assert_eq!(layout.clause_addr(9, 2, 0).unwrap(), 0x8000_0000 + 16);
+    }
+
+    #[test]
+    fn test_partitioned_layout() {
+        let config = LayoutConfig {
+            value_encoding: ValueEncoding::TwoBit,
+            placement: LayoutPlacement::PerPePartitioned,
+            ..Default::default()
+        };
+        let mut layout = MemoryLayout::new(config, 2);
+        assert_eq!(layout.value_addr(7), layout.value_addr(4));
+        assert_ne!(layout.value_addr(7), layout.value_addr(8));
+        // literal 2 and 3 are on pe 1, literal 4 and 5 are on pe 0
+        assert_eq!(layout.watch_meta_addr(2), 0x2000_0000 + 0x100_0000);
+        assert_eq!(layout.watch_meta_addr(5), 0x2000_0000 + 16 * 3);
+        layout.reserve_watchers(2, 1).unwrap();
+        layout.reserve_watchers(4, 1).unwrap();
+        assert_eq!(layout.watch_data_addr(2), 0x4000_0000 + 0x100_0000);
+        assert_eq!(layout.watch_data_addr(4), 0x4000_0000);
+        assert_eq!(
+            layout.clause_addr(0, 3, 3).unwrap(),
+            0x8000_0000 + 0x100_0000
+        );
+        assert_eq!(layout.clause_addr(1, 3, 5).unwrap(), 0x8000_0000);
+    }
+
+    #[test]
+    fn test_partition_full() {
+        let config = LayoutConfig {
+            placement: LayoutPlacement::PerPePartitioned,
+            partition_bytes: 64,
+            ..Default::default()
+        };
+        let mut layout = MemoryLayout::new(config, 2);
+        // 8 watchers fill the partition, the next list doesn't spill into partition 1
+        layout.reserve_watchers(0, 8).unwrap();
+        assert!(layout.reserve_watchers(1, 1).is_err());
+        assert_eq!(layout.watch_data_addr(1), 0x4000_0000);
+        layout.reserve_watchers(2, 8).unwrap();
+        assert_eq!(layout.watch_data_addr(2), 0x4000_0000 + 64);
+        // 4 clauses of 3 literals fill the partition
+        for clause_id in 0..4 {
+            layout.clause_addr(clause_id, 3, 0).unwrap();
+        }
+        assert!(layout.clause_addr(4, 3, 0).is_err());
+        assert_eq!(layout.clause_addr(5, 3, 2).unwrap(), 0x8000_0000 + 64);
+        // the packed layout has no partition to fill
+        let mut layout = MemoryLayout::new(
+            LayoutConfig {
+                partition_bytes: 64,
+                ..Default::default()
+            },
+            2,
+        );
+        layout.reserve_watchers(0, 100).unwrap();
+        assert_eq!(layout.alloc_clause(100, 0).unwrap(), 0x8000_0000);
+    }
+
+    #[test]
+    fn test_task_by_id() {
+        let mut task =
+            SataccMinisatTask::with_layout(MemoryLayout::new(LayoutConfig::default(), 1));
+        task.start_new_assgin();
+        task.add_watcher_task_by_id(0, 5, 2);
+        task.add_single_watcher_task_no_clause_by_id(7, 5);
+        task.add_single_watcher_task_by_id(8, 3, 3, 5);
+        task.add_single_watcher_clause_value_by_id(9);
+        task.set_clause_new_watch_by_id(6, 0, 1);
+        let assignment = &task.decisions[0].assignments[0];
+        assert_eq!(assignment.meta_data_addr, 0x2000_0000 + 5 * 16);
+        assert_eq!(assignment.watcher_addr, 0x4000_0000);
+        assert_eq!(assignment.value_addr, Some(0x1000_0002));
+        assert_eq!(assignment.single_watcher_tasks[0].blocker_addr, 0x1000_0007);
+        let clause_data = assignment.single_watcher_tasks[1]
+            .clause_data
+            .as_ref()
+            .unwrap();
+        assert_eq!(clause_data.clause_addr, 0x8000_0000);
+        assert_eq!(clause_data.clause_processing_time, None);
+        assert_eq!(clause_data.clause_value_addr, [0x1000_0009]);
+        assert_eq!(clause_data.clause_value_id, [9]);
+        // literal 6 gets its first watcher list after the list of literal 5
+        let new_watch = clause_data.new_watch.unwrap();
+        assert_eq!(new_watch.append_addr, 0x4000_0000 + 4 * 8);
+        assert_eq!(new_watch.compact_addr, 0x4000_0000 + 8);
+    }
+}
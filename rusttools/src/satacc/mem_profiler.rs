@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::sim::{ChannelBuilder, InOutPort, SimComponent, SimReciver, SimSender};
+
+use super::{icnt::IcntMsgWrapper, statistics::ReuseProfile, MemReq, MemReqKind, SataccStatus};
+
+/// # StackDistance
+/// get the lru stack distance of each access in `O(log n)`
+/// - each line keeps the time of its last access, a fenwick tree marks the times that are the last access of some line
+/// - the distance of an access is the number of marks after the last access of the same line
+#[derive(Debug, Default)]
+struct StackDistance {
+    last_access: BTreeMap<u64, usize>,
+    /// the fenwick tree, 1-based
+    tree: Vec<i64>,
+    current_time: usize,
+}
+impl StackDistance {
+    fn add(&mut self, time: usize, value: i64) {
+        let mut index = time;
+        while index < self.tree.len() {
+            self.tree[index] += value;
+            index += index & index.wrapping_neg();
+        }
+    }
+    fn prefix_sum(&self, time: usize) -> i64 {
+        let mut index = time;
+        let mut sum = 0;
+        while index > 0 {
+            sum += self.tree[index];
+            index -= index & index.wrapping_neg();
+        }
+        sum
+    }
+    /// the tree is full, renumber the last accesses to `1..=lines` in order and mark them again
+    /// - the distances only depend on the order of the marks, so they are kept
+    /// - the tree has room for as many new accesses as the lines, so it's bounded by the distinct lines
+    fn compact(&mut self) {
+        let mut times = self
+            .last_access
+            .iter()
+            .map(|(&line, &time)| (time, line))
+            .collect::<Vec<_>>();
+        times.sort_unstable();
+        self.tree = vec![0; ((times.len() + 1) * 2).max(1024)];
+        for (index, (_, line)) in times.into_iter().enumerate() {
+            self.last_access.insert(line, index + 1);
+            self.add(index + 1, 1);
+        }
+        self.current_time = self.last_access.len() + 1;
+    }
+    /// access the line, return the stack distance, `None` for the first access
+    fn access(&mut self, line: u64) -> Option<usize> {
+        self.current_time += 1;
+        if self.current_time >= self.tree.len() {
+            self.compact();
+        }
+        let now = self.current_time;
+        let distance = self.last_access.insert(line, now).map(|last_time| {
+            let distance = self.prefix_sum(now - 1) - self.prefix_sum(last_time);
+            self.add(last_time, -1);
+            distance as usize
+        });
+        self.add(now, 1);
+        distance
+    }
+}
+
+/// the bucket of the distance in the log2 histogram, bucket 0 is distance 0, bucket i is `[2^(i-1), 2^i)`
+fn get_bucket(distance: usize) -> usize {
+    (usize::BITS - distance.leading_zeros()) as usize
+}
+
+/// the profiler of one stream of lines
+#[derive(Debug, Default)]
+struct StreamProfiler {
+    stack_distance: StackDistance,
+    current_window: usize,
+    window_lines: BTreeSet<u64>,
+}
+impl StreamProfiler {
+    fn access(&mut self, line: u64, window: usize, profile: &mut ReuseProfile) {
+        profile.accesses += 1;
+        match self.stack_distance.access(line) {
+            Some(distance) => {
+                let bucket = get_bucket(distance);
+                if profile.stack_distance_histogram.len() <= bucket {
+                    profile.stack_distance_histogram.resize(bucket + 1, 0);
+                }
+                profile.stack_distance_histogram[bucket] += 1;
+            }
+            None => {
+                profile.cold_accesses += 1;
+            }
+        }
+        if window != self.current_window {
+            self.current_window = window;
+            self.window_lines.clear();
+        }
+        if profile.working_set_sizes.len() <= window {
+            profile.working_set_sizes.resize(window + 1, 0);
+        }
+        if self.window_lines.insert(line) {
+            profile.working_set_sizes[window] += 1;
+        }
+    }
+}
+
+/// # MemProfiler
+/// the analysis component that sits in front of the l3 cache,
+/// it forwards the requests to the cache without any delay and profiles the stream of lines
+/// - the lru stack distance histogram and the working set size of each window are written to the statistics
+/// - it's profiled for all requests and for each [`MemReqKind`]
+pub struct MemProfiler {
+    /// the ports from the icnt
+    from_icnt: Vec<SimReciver<IcntMsgWrapper<MemReq>>>,
+    /// the ports to the cache
+    to_cache: Vec<SimSender<IcntMsgWrapper<MemReq>>>,
+    block_size: u64,
+    window_cycles: usize,
+    all: StreamProfiler,
+    per_kind: BTreeMap<MemReqKind, StreamProfiler>,
+}
+
+impl MemProfiler {
+    /// build the profiler in front of the `cache_ports`, return the profiler and the new ports for the cache
+    pub fn new(
+        cache_ports: Vec<InOutPort<IcntMsgWrapper<MemReq>>>,
+        channel_builder: &ChannelBuilder,
+        channel_size: usize,
+        block_size: u64,
+        window_cycles: usize,
+    ) -> (Self, Vec<InOutPort<IcntMsgWrapper<MemReq>>>) {
+        let mut from_icnt = vec![];
+        let mut to_cache = vec![];
+        let mut new_cache_ports = vec![];
+        for InOutPort { in_port, out_port } in cache_ports {
+            let (sender, receiver) = channel_builder.sim_channel(channel_size);
+            from_icnt.push(in_port);
+            to_cache.push(sender);
+            new_cache_ports.push(InOutPort {
+                in_port: receiver,
+                out_port,
+            });
+        }
+        (
+            Self {
+                from_icnt,
+                to_cache,
+                block_size,
+                window_cycles,
+                all: StreamProfiler::default(),
+                per_kind: BTreeMap::new(),
+            },
+            new_cache_ports,
+        )
+    }
+}
+
+impl SimComponent for MemProfiler {
+    type SharedStatus = SataccStatus;
+    fn update(
+        &mut self,
+        shared_status: &mut Self::SharedStatus,
+        current_cycle: usize,
+    ) -> (bool, bool) {
+        let mut busy = false;
+        let mut updated = false;
+        let window = current_cycle / self.window_cycles;
+        let profile = &mut shared_status.statistics.mem_profile;
+        for (from_icnt, to_cache) in self.from_icnt.iter().zip(self.to_cache.iter()) {
+            while to_cache.have_space() {
+                let req = match from_icnt.recv() {
+                    Ok(req) => req,
+                    Err(_) => break,
+                };
+                busy = true;
+                updated = true;
+                let line = req.msg.addr / self.block_size;
+                let kind = req.msg.req_type.get_kind();
+                self.all.access(line, window, &mut profile.all);
+                self.per_kind.entry(kind).or_default().access(
+                    line,
+                    window,
+                    profile.per_kind.entry(kind).or_default(),
+                );
+                to_cache.send(req).unwrap_or_else(|_| unreachable!());
+            }
+        }
+        (busy, updated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_stack_distance() {
+        let mut stack_distance = StackDistance::default();
+        assert_eq!(stack_distance.access(1), None);
+        assert_eq!(stack_distance.access(2), None);
+        assert_eq!(stack_distance.access(1), Some(1));
+        assert_eq!(stack_distance.access(1), Some(0));
+        assert_eq!(stack_distance.access(3), None);
+        assert_eq!(stack_distance.access(2), Some(2));
+        // the tree will be compacted
+        for line in 0..3000 {
+            stack_distance.access(line % 100);
+        }
+        // the lines 51..100 are accessed after the last access of 50
+        assert_eq!(stack_distance.access(50), Some(49));
+        // the tree is bounded by the distinct lines, not by the accesses
+        for line in 0..100_000 {
+            stack_distance.access(line % 100);
+        }
+        assert!(stack_distance.tree.len() <= 1024);
+        assert_eq!(stack_distance.access(0), Some(99));
+        assert_eq!(get_bucket(0), 0);
+        assert_eq!(get_bucket(1), 1);
+        assert_eq!(get_bucket(99), 7);
+    }
+    #[test]
+    fn test_working_set() {
+        let mut profiler = StreamProfiler::default();
+        let mut profile = ReuseProfile::default();
+        for (line, window) in [(0, 0), (1, 0), (0, 0), (0, 2), (3, 2)] {
+            profiler.access(line, window, &mut profile);
+        }
+        assert_eq!(profile.working_set_sizes, vec![2, 0, 2]);
+        assert_eq!(profile.cold_accesses, 3);
+        assert_eq!(profile.stack_distance_histogram, vec![1, 1]);
+    }
+}
@@ -1,16 +1,51 @@
+pub(self) mod analysis;
 pub(self) mod cache;
 pub(self) mod clause;
+pub(self) mod clause_timing;
+pub(self) mod coherence;
+pub(self) mod conflict;
+pub(self) mod fast_forward;
+pub(self) mod functional;
 pub(self) mod icnt;
-pub(self) mod satacc_minisat_task;
+pub(self) mod layout;
+pub(self) mod mem_profiler;
+pub(self) mod nuca;
+pub(self) mod pipeline;
+pub(self) mod sampling;
+pub(crate) mod satacc_minisat_task;
+pub(self) mod scratchpad;
 pub mod simulator;
+pub(self) mod solver;
 pub(self) mod statistics;
+pub(self) mod synthetic;
+pub(self) mod task_source;
+pub(self) mod trace;
 pub(self) mod trail;
+pub(self) mod validation;
 pub(self) mod watcher;
 pub(self) mod watcher_interface;
 pub(self) mod wating_task;
 use std::fs::File;
 
+pub use analysis::{analyze_trace, TaskAnalyzer, TaskReport};
 pub use cache::CacheConfig;
+pub use cache::ChannelMapping;
+pub use cache::PrefetcherType;
+pub use clause_timing::ClauseTimingConfig;
+pub use coherence::{CoherenceConfig, CoherenceProtocol};
+pub use conflict::ConflictConfig;
+pub use fast_forward::FastForwardConfig;
+pub use functional::{evaluate_clause, ClauseLit, ClauseResult, FunctionalConfig, LitValue};
+pub use layout::{LayoutConfig, LayoutPlacement, MemoryLayout, ValueEncoding};
+pub use nuca::NucaConfig;
+pub use pipeline::PipelineConfig;
+pub use sampling::{
+    round_features, ClusterEstimate, RoundFeatures, SampledEstimate, SamplingConfig, SamplingPlan,
+};
+pub use scratchpad::ScratchpadConfig;
+
+/// the number of partitions of the l3 cache, each partition has its own icnt port, see [`NucaConfig`] for the distributed l3
+const NUM_MEM_PARTITIONS: usize = 8;
 #[derive(Debug)]
 pub enum WatcherAccessType {
     ReadMeta,
@@ -28,6 +63,37 @@ pub enum MemReqType {
     WatcherReadMetaData,
     WatcherReadData,
     WatcherReadBlocker,
+    /// the write of the watch lists by the clause that found a new watch
+    ClauseWriteWatchList(usize),
+}
+/// the kind of the [`MemReqType`], without the id of the clause unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum MemReqKind {
+    MetaData,
+    WatcherData,
+    Blocker,
+    ClauseData,
+    ClauseValue,
+    /// the writes allocate the lines like the reads and make them dirty, the dirty lines are written back when evicted
+    WatchListWrite,
+}
+impl MemReqKind {
+    /// the request writes the line
+    pub fn is_write(&self) -> bool {
+        matches!(self, MemReqKind::WatchListWrite)
+    }
+}
+impl MemReqType {
+    pub fn get_kind(&self) -> MemReqKind {
+        match self {
+            MemReqType::ClauseReadData(_) => MemReqKind::ClauseData,
+            MemReqType::ClauseReadValue(_) => MemReqKind::ClauseValue,
+            MemReqType::WatcherReadMetaData => MemReqKind::MetaData,
+            MemReqType::WatcherReadData => MemReqKind::WatcherData,
+            MemReqType::WatcherReadBlocker => MemReqKind::Blocker,
+            MemReqType::ClauseWriteWatchList(_) => MemReqKind::WatchListWrite,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -38,15 +104,25 @@ pub struct MemReq {
     pub mem_id: usize,
     pub is_write: bool,
     pub req_type: MemReqType,
+    /// the addr that will be read right after this request, it's a hint for the prefetcher
+    pub next_addr: Option<u64>,
 }
 
 use enum_as_inner::EnumAsInner;
-pub use satacc_minisat_task::SataccMinisatTask;
+pub use satacc_minisat_task::{BuilderStatus, SataccMinisatTask};
+use serde::{Deserialize, Serialize};
 pub use simulator::Simulator;
+pub use solver::{Cnf, Lit, SolveLimits, SolveResult, Solver, SolverTaskSource};
+pub use synthetic::{Distribution, SyntheticConfig, SyntheticGenerator};
+pub use task_source::{ChannelTaskSource, TaskSource};
+pub use trace::{TraceReader, TraceRecord, TraceWriter};
+pub use validation::{validate_task, validate_trace, ValidationError, ValidationErrorKind};
 
 use crate::config::Config;
 
 use self::{
+    coherence::ValueDirectory,
+    functional::FunctionalState,
     satacc_minisat_task::{ClauseTask, SingleRoundTask},
     statistics::Statistics,
 };
@@ -56,6 +132,14 @@ pub struct SataccStatus {
     pub statistics: Statistics,
     pub verbose_mode: bool,
     pub current_level_finished_tasks: usize,
+    /// the level tasks finished or cancelled since the start, the trail compares it with the sent ones
+    pub finished_level_tasks: usize,
+    /// the cycle the conflict of the current round is found, see [`ConflictConfig`]
+    pub conflict_cycle: Option<usize>,
+    /// the private caches that hold each value line, used by [`CoherenceProtocol::DirectoryInvalidate`]
+    pub value_directory: ValueDirectory,
+    /// the assignments of the current round, used by the closed-loop mode of [`FunctionalConfig`]
+    pub functional_state: FunctionalState,
 }
 
 impl SataccStatus {
@@ -66,6 +150,10 @@ impl SataccStatus {
             statistics,
             verbose_mode: false,
             current_level_finished_tasks: 0,
+            finished_level_tasks: 0,
+            conflict_cycle: None,
+            value_directory: ValueDirectory::default(),
+            functional_state: FunctionalState::default(),
         }
     }
 
@@ -73,6 +161,74 @@ impl SataccStatus {
         self.current_mem_req_id += 1;
         self.current_mem_req_id
     }
+    /// the l3 partition of the request, it's the index of the partition after the watcher ports of the icnt
+    pub fn get_l3_partition(
+        &mut self,
+        addr: u64,
+        watcher_pe_id: usize,
+        req_kind: MemReqKind,
+    ) -> usize {
+        let config = &self.statistics.config;
+        let nuca_config = config.nuca_config;
+        let partition = nuca_config.get_partition(addr, watcher_pe_id, req_kind, config.n_watchers);
+        if nuca_config.enable {
+            let nuca_statistics = &mut self.statistics.nuca_statistics;
+            match partition == watcher_pe_id {
+                true => nuca_statistics.local_reqs += 1,
+                false => nuca_statistics.remote_reqs += 1,
+            }
+        }
+        partition
+    }
+    /// the number of the l3 partitions, the scratchpad ports are right after them
+    pub fn num_l3_partitions(&self) -> usize {
+        let config = &self.statistics.config;
+        config.nuca_config.num_partitions(config.n_watchers)
+    }
+    /// the value is read in the private cache of the tile instead of the l3 cache
+    pub fn read_value_in_private_cache(&self, addr: u64) -> bool {
+        let config = &self.statistics.config;
+        config.coherence_config.private_value_cache && !config.scratchpad_config.contains(addr)
+    }
+    /// the watcher list, the single watcher or the clause is finished or cancelled
+    pub fn finish_level_tasks(&mut self, tasks: usize) {
+        self.current_level_finished_tasks += tasks;
+        self.finished_level_tasks += tasks;
+    }
+    /// the clause unit finished the clause, it's evaluated in the closed-loop mode,
+    /// otherwise it's conflicting if the trace marks it
+    pub fn finish_clause(&mut self, clause_task: &ClauseTask, current_cycle: usize) {
+        self.finish_level_tasks(1);
+        let Some(clause_data) = &clause_task.clause_data else {
+            return;
+        };
+        let functional_config = self.statistics.config.functional_config;
+        let conflict = match functional_config.enable {
+            true => {
+                self.functional_state.evaluate(
+                    clause_data,
+                    current_cycle + functional_config.feedback_latency,
+                    &mut self.statistics.functional_statistics,
+                ) == ClauseResult::Conflict
+            }
+            false => clause_data.conflict,
+        };
+        if conflict
+            && self.statistics.config.conflict_config.enable
+            && self.conflict_cycle.is_none()
+        {
+            tracing::debug!(current_cycle, "conflict found");
+            self.conflict_cycle = Some(current_cycle);
+            self.statistics.conflict_statistics.conflicts += 1;
+        }
+    }
+    /// the pes are cancelling the work of the round
+    pub fn is_aborting(&self, current_cycle: usize) -> bool {
+        self.statistics
+            .config
+            .conflict_config
+            .is_aborting(self.conflict_cycle, current_cycle)
+    }
     /// update each round's statistics
     pub fn update_single_round_task(&mut self, single_round_task: &SingleRoundTask) {
         self.statistics.update_single_round_task(single_round_task);
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use super::{MemReqKind, NUM_MEM_PARTITIONS};
+
+/// the config of the distributed l3 cache
+/// - when enabled, each watcher tile owns a slice of the l3 cache at its mesh node,
+///   the lines are interleaved across the slices, so the hops depend on where the line lives
+/// - each slice is a fixed latency cache with `1 / n_watchers` of the sets of `l3_cache_config`
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct NucaConfig {
+    pub enable: bool,
+    /// put the watcher meta data, the watcher list and the blockers in the slice of the watcher that reads them
+    pub local_watch_lists: bool,
+}
+impl NucaConfig {
+    /// the number of the l3 partitions, each partition has its own icnt port
+    pub fn num_partitions(&self, n_watchers: usize) -> usize {
+        match self.enable {
+            true => n_watchers,
+            false => NUM_MEM_PARTITIONS,
+        }
+    }
+    /// the l3 partition that serves the addr
+    pub fn get_partition(
+        &self,
+        addr: u64,
+        watcher_pe_id: usize,
+        req_kind: MemReqKind,
+        n_watchers: usize,
+    ) -> usize {
+        let line = (addr >> 6) as usize;
+        match req_kind {
+            MemReqKind::MetaData
+            | MemReqKind::WatcherData
+            | MemReqKind::Blocker
+            | MemReqKind::WatchListWrite
+                if self.enable && self.local_watch_lists =>
+            {
+                watcher_pe_id
+            }
+            _ => line % self.num_partitions(n_watchers),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_partition() {
+        let mut config = NucaConfig {
+            enable: false,
+            local_watch_lists: true,
+        };
+        assert_eq!(
+            config.get_partition(64 * 13, 3, MemReqKind::WatcherData, 16),
+            5
+        );
+        config.enable = true;
+        assert_eq!(
+            config.get_partition(64 * 13, 3, MemReqKind::WatcherData, 16),
+            3
+        );
+        assert_eq!(
+            config.get_partition(64 * 13, 3, MemReqKind::ClauseData, 16),
+            13
+        );
+        config.local_watch_lists = false;
+        assert_eq!(
+            config.get_partition(64 * 13, 3, MemReqKind::Blocker, 16),
+            13
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// the processing pipeline of the watcher and the clause unit
+/// - `issue_width` tasks can enter the pipeline each cycle, at most `depth` tasks are in flight
+/// - a task leaves the pipeline `latency` cycles after it enters, the clause unit adds the processing time of the clause
+/// - the width and the depth are at least 1
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct PipelineConfig {
+    /// the cycles of a task in the pipeline
+    pub latency: usize,
+    /// the max number of tasks that enter the pipeline each cycle
+    pub issue_width: usize,
+    /// the max number of tasks in flight
+    pub depth: usize,
+}
+impl PipelineConfig {
+    /// one task at a time
+    pub fn single(latency: usize) -> Self {
+        Self {
+            latency,
+            issue_width: 1,
+            depth: 1,
+        }
+    }
+}
+
+/// # Pipeline
+/// the tasks in flight, a finished task can leave before the older ones
+#[derive(Debug)]
+pub struct Pipeline<T> {
+    config: PipelineConfig,
+    /// the task with the cycle it finishes
+    in_flight: VecDeque<(usize, T)>,
+    issue_cycle: usize,
+    issued: usize,
+}
+impl<T> Pipeline<T> {
+    pub fn new(config: PipelineConfig) -> Self {
+        Self {
+            config,
+            in_flight: VecDeque::new(),
+            issue_cycle: 0,
+            issued: 0,
+        }
+    }
+    pub fn latency(&self) -> usize {
+        self.config.latency
+    }
+    /// a new task can enter the pipeline at `current_cycle`
+    pub fn can_issue(&self, current_cycle: usize) -> bool {
+        let issued = match self.issue_cycle == current_cycle {
+            true => self.issued,
+            false => 0,
+        };
+        issued < self.config.issue_width.max(1) && self.in_flight.len() < self.config.depth.max(1)
+    }
+    /// the task leaves the pipeline at `finished_cycle`, check [`Pipeline::can_issue`] first
+    pub fn issue(&mut self, task: T, current_cycle: usize, finished_cycle: usize) {
+        debug_assert!(self.can_issue(current_cycle));
+        if self.issue_cycle != current_cycle {
+            self.issue_cycle = current_cycle;
+            self.issued = 0;
+        }
+        self.issued += 1;
+        self.in_flight.push_back((finished_cycle, task));
+    }
+    /// the oldest task that finished before `current_cycle`
+    pub fn pop_finished(&mut self, current_cycle: usize) -> Option<T> {
+        let index = self
+            .in_flight
+            .iter()
+            .position(|&(finished_cycle, _)| finished_cycle <= current_cycle)?;
+        self.in_flight.remove(index).map(|(_, task)| task)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.in_flight.is_empty()
+    }
+    /// drop all the tasks in flight, return the number of them
+    pub fn clear(&mut self) -> usize {
+        let len = self.in_flight.len();
+        self.in_flight.clear();
+        len
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        satacc::{SataccMinisatTask, Simulator},
+    };
+
+    #[test]
+    fn test_pipeline() {
+        let mut pipeline = Pipeline::new(PipelineConfig {
+            latency: 2,
+            issue_width: 2,
+            depth: 3,
+        });
+        assert!(pipeline.can_issue(0));
+        pipeline.issue(0, 0, 2);
+        pipeline.issue(1, 0, 10);
+        // the width is used up in this cycle
+        assert!(!pipeline.can_issue(0));
+        assert!(pipeline.can_issue(1));
+        pipeline.issue(2, 1, 3);
+        // the depth is used up
+        assert!(!pipeline.can_issue(2));
+        assert_eq!(pipeline.pop_finished(1), None);
+        assert_eq!(pipeline.pop_finished(3), Some(0));
+        // the later task finishes first
+        assert_eq!(pipeline.pop_finished(3), Some(2));
+        assert_eq!(pipeline.pop_finished(3), None);
+        assert_eq!(pipeline.clear(), 1);
+        assert!(pipeline.is_empty());
+    }
+
+    /// one assignment with a long watch list, the blockers are all true
+    fn build_watch_list() -> SataccMinisatTask {
+        let mut task = SataccMinisatTask::new();
+        task.start_new_assgin();
+        task.add_watcher_task(0, 0, 4096, 0);
+        for _ in 0..256 {
+            task.add_single_watcher_task_no_clause(8192, 0);
+        }
+        task
+    }
+
+    #[test]
+    fn test_wide_watcher() {
+        let mut config = Config::default();
+        config.watcher_pipeline.latency = 16;
+        let simulator = Simulator::new_from_config(config.clone());
+        let (_status, narrow_cycle) = simulator.run_task(&mut build_watch_list()).unwrap();
+        config.watcher_pipeline.issue_width = 4;
+        config.watcher_pipeline.depth = 64;
+        let simulator = Simulator::new_from_config(config);
+        let (status, wide_cycle) = simulator.run_task(&mut build_watch_list()).unwrap();
+        assert_eq!(status.statistics.watcher_statistics[0].total_watchers, 256);
+        // the blocker checks overlap in the pipeline
+        assert!(wide_cycle * 4 < narrow_cycle);
+    }
+
+    #[test]
+    fn test_wide_clause_unit() {
+        let build_task = || {
+            let mut task = SataccMinisatTask::new();
+            task.start_new_assgin();
+            task.add_watcher_task(0, 0, 4096, 0);
+            for clause_id in 0..64 {
+                task.add_single_watcher_task(8192, 16384 + 64 * clause_id as u64, clause_id, 50, 0);
+                task.add_single_watcher_clause_value_addr(256, 1);
+            }
+            task
+        };
+        let mut config = Config::default();
+        let simulator = Simulator::new_from_config(config.clone());
+        let (_status, narrow_cycle) = simulator.run_task(&mut build_task()).unwrap();
+        config.clause_pipeline.depth = 8;
+        let simulator = Simulator::new_from_config(config);
+        let (_status, wide_cycle) = simulator.run_task(&mut build_task()).unwrap();
+        // the clauses are evaluated in 8 lanes
+        assert!(wide_cycle * 3 < narrow_cycle);
+    }
+}
@@ -0,0 +1,463 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::File,
+};
+
+use eyre::{Context, Result};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{satacc_minisat_task::SingleRoundTask, task_source::TaskSource};
+
+/// the z value of the 95% confidence interval
+const Z_95: f64 = 1.96;
+
+/// the config of the sampled simulation, see [`SamplingPlan`]
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct SamplingConfig {
+    /// the max number of clusters of the rounds
+    pub clusters: usize,
+    /// the number of rounds simulated in detail in each cluster, at least 2 to get the variance
+    pub samples_per_cluster: usize,
+    /// the max iterations of the k-means
+    pub max_iterations: usize,
+    pub seed: u64,
+}
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            clusters: 8,
+            samples_per_cluster: 4,
+            max_iterations: 50,
+            seed: 0,
+        }
+    }
+}
+
+/// the feature vector of a round, the rounds with close features have close cycles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundFeatures {
+    pub assignments: usize,
+    pub watchers: usize,
+    pub clauses: usize,
+    pub levels: usize,
+}
+impl RoundFeatures {
+    pub fn from_round(round: &SingleRoundTask) -> Self {
+        let levels = round
+            .assignments
+            .iter()
+            .map(|assignment| assignment.level)
+            .collect::<HashSet<_>>();
+        Self {
+            assignments: round.assignments.len(),
+            watchers: round
+                .assignments
+                .iter()
+                .map(|assignment| assignment.single_watcher_tasks.len())
+                .sum(),
+            clauses: round
+                .assignments
+                .iter()
+                .flat_map(|assignment| &assignment.single_watcher_tasks)
+                .filter(|watcher| watcher.clause_data.is_some())
+                .count(),
+            levels: levels.len(),
+        }
+    }
+    /// the counts are heavy tailed, so they are compared in log scale
+    fn to_vector(self) -> [f64; 4] {
+        [self.assignments, self.watchers, self.clauses, self.levels]
+            .map(|count| (count as f64).ln_1p())
+    }
+}
+
+/// read the features of all the rounds of the source
+pub fn round_features(source: &mut impl TaskSource) -> Result<Vec<RoundFeatures>> {
+    let mut features = vec![];
+    while let Some(round) = source.next_round()? {
+        features.push(RoundFeatures::from_round(&round));
+    }
+    Ok(features)
+}
+
+fn distance(a: &[f64; 4], b: &[f64; 4]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// scale each dimension to unit variance, a constant dimension becomes 0
+fn standardize(vectors: &mut [[f64; 4]]) {
+    let n = vectors.len() as f64;
+    for dim in 0..4 {
+        let mean = vectors.iter().map(|vector| vector[dim]).sum::<f64>() / n;
+        let var = vectors
+            .iter()
+            .map(|vector| (vector[dim] - mean) * (vector[dim] - mean))
+            .sum::<f64>()
+            / n;
+        let std = var.sqrt();
+        for vector in vectors.iter_mut() {
+            vector[dim] = if std > 0. {
+                (vector[dim] - mean) / std
+            } else {
+                0.
+            };
+        }
+    }
+}
+
+/// k-means with the k-means++ seeding, return the cluster of each vector, the empty clusters are removed
+fn k_means(vectors: &[[f64; 4]], k: usize, max_iterations: usize, rng: &mut StdRng) -> Vec<usize> {
+    if vectors.is_empty() {
+        return vec![];
+    }
+    let mut centroids = vec![vectors[rng.gen_range(0..vectors.len())]];
+    while centroids.len() < k.min(vectors.len()) {
+        let distances = vectors
+            .iter()
+            .map(|vector| {
+                centroids
+                    .iter()
+                    .map(|centroid| distance(vector, centroid))
+                    .fold(f64::MAX, f64::min)
+            })
+            .collect::<Vec<_>>();
+        let total: f64 = distances.iter().sum();
+        if total == 0. {
+            // all the vectors are already centroids
+            break;
+        }
+        let mut target = rng.gen_range(0. ..total);
+        let next = distances
+            .iter()
+            .position(|&distance| {
+                target -= distance;
+                target < 0.
+            })
+            .unwrap_or(vectors.len() - 1);
+        centroids.push(vectors[next]);
+    }
+    let mut clusters = vec![0; vectors.len()];
+    for iteration in 0..max_iterations.max(1) {
+        let mut changed = false;
+        for (vector, cluster) in vectors.iter().zip(clusters.iter_mut()) {
+            let nearest = (0..centroids.len())
+                .min_by(|&a, &b| {
+                    distance(vector, &centroids[a]).total_cmp(&distance(vector, &centroids[b]))
+                })
+                .unwrap();
+            if nearest != *cluster {
+                *cluster = nearest;
+                changed = true;
+            }
+        }
+        if !changed && iteration > 0 {
+            break;
+        }
+        let mut sums = vec![([0.; 4], 0); centroids.len()];
+        for (vector, &cluster) in vectors.iter().zip(&clusters) {
+            let (sum, count) = &mut sums[cluster];
+            for dim in 0..4 {
+                sum[dim] += vector[dim];
+            }
+            *count += 1;
+        }
+        for (centroid, (sum, count)) in centroids.iter_mut().zip(sums) {
+            if count > 0 {
+                *centroid = sum.map(|sum| sum / count as f64);
+            }
+        }
+    }
+    // renumber the clusters by their first round
+    let mut ids = vec![None; centroids.len()];
+    let mut next_id = 0;
+    clusters
+        .into_iter()
+        .map(|cluster| {
+            *ids[cluster].get_or_insert_with(|| {
+                next_id += 1;
+                next_id - 1
+            })
+        })
+        .collect()
+}
+
+/// # SamplingPlan
+/// the rounds are clustered by their [`RoundFeatures`], a few random rounds of each cluster are simulated in detail
+/// - the other rounds only warm up the caches, see [`super::simulator::TrailAndOthers::warm_up`]
+/// - the total cycles are extrapolated from the samples of each cluster, see [`SampledEstimate`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingPlan {
+    /// the cluster of each round
+    pub clusters: Vec<usize>,
+    /// the rounds of each cluster that are simulated in detail
+    pub samples: Vec<Vec<usize>>,
+    detailed_rounds: BTreeSet<usize>,
+}
+impl SamplingPlan {
+    pub fn new(features: &[RoundFeatures], config: &SamplingConfig) -> Self {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let mut vectors = features
+            .iter()
+            .map(|features| features.to_vector())
+            .collect::<Vec<_>>();
+        standardize(&mut vectors);
+        let clusters = k_means(
+            &vectors,
+            config.clusters.max(1),
+            config.max_iterations,
+            &mut rng,
+        );
+        let num_clusters = clusters.iter().max().map_or(0, |max| max + 1);
+        let mut members = vec![vec![]; num_clusters];
+        for (round, &cluster) in clusters.iter().enumerate() {
+            members[cluster].push(round);
+        }
+        let samples = members
+            .iter()
+            .map(|rounds| {
+                let mut samples = rounds
+                    .choose_multiple(&mut rng, config.samples_per_cluster.max(2))
+                    .copied()
+                    .collect::<Vec<_>>();
+                samples.sort_unstable();
+                samples
+            })
+            .collect::<Vec<Vec<_>>>();
+        let detailed_rounds = samples.iter().flatten().copied().collect();
+        Self {
+            clusters,
+            samples,
+            detailed_rounds,
+        }
+    }
+    /// the round is simulated in detail
+    pub fn is_detailed(&self, round: usize) -> bool {
+        self.detailed_rounds.contains(&round)
+    }
+    pub fn total_rounds(&self) -> usize {
+        self.clusters.len()
+    }
+}
+
+/// the cycles of a cluster
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClusterEstimate {
+    pub rounds: usize,
+    pub samples: usize,
+    pub mean_cycles: f64,
+    /// the sample standard deviation, a single sample of a larger cluster takes its mean as a conservative bound
+    pub std_cycles: f64,
+}
+
+/// # SampledEstimate
+/// the total cycles extrapolated by the stratified sampling, each cluster is a stratum
+/// - `estimated_cycles` is the sum of `mean_cycles * rounds` of the clusters
+/// - the confidence interval uses the variance of each cluster with the finite population correction,
+///   a cluster with a single sample has no sample variance, so its std is taken as its mean
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SampledEstimate {
+    pub total_rounds: usize,
+    pub simulated_rounds: usize,
+    /// the cycles of the rounds simulated in detail
+    pub simulated_cycles: usize,
+    pub estimated_cycles: f64,
+    /// the half width of the 95% confidence interval of `estimated_cycles`
+    pub confidence_interval: f64,
+    pub clusters: Vec<ClusterEstimate>,
+}
+impl SampledEstimate {
+    /// `cycles` are the cycles of the samples of each cluster of the plan
+    pub fn new(plan: &SamplingPlan, cycles: &[Vec<usize>]) -> Self {
+        let mut rounds = vec![0; plan.samples.len()];
+        for &cluster in &plan.clusters {
+            rounds[cluster] += 1;
+        }
+        let clusters = rounds
+            .iter()
+            .zip(cycles)
+            .map(|(&rounds, cycles)| {
+                let samples = cycles.len();
+                let mean_cycles = cycles.iter().sum::<usize>() as f64 / samples.max(1) as f64;
+                let std_cycles = match samples {
+                    0 => 0.,
+                    1 => mean_cycles,
+                    _ => {
+                        let var = cycles
+                            .iter()
+                            .map(|&cycle| (cycle as f64 - mean_cycles).powi(2))
+                            .sum::<f64>()
+                            / (samples - 1) as f64;
+                        var.sqrt()
+                    }
+                };
+                ClusterEstimate {
+                    rounds,
+                    samples,
+                    mean_cycles,
+                    std_cycles,
+                }
+            })
+            .collect::<Vec<_>>();
+        let variance: f64 = clusters
+            .iter()
+            .filter(|cluster| cluster.samples > 0)
+            .map(|cluster| {
+                let rounds = cluster.rounds as f64;
+                let samples = cluster.samples as f64;
+                rounds * rounds * (1. - samples / rounds) * cluster.std_cycles.powi(2) / samples
+            })
+            .sum();
+        Self {
+            total_rounds: plan.total_rounds(),
+            simulated_rounds: cycles.iter().map(Vec::len).sum(),
+            simulated_cycles: cycles.iter().flatten().sum(),
+            estimated_cycles: clusters
+                .iter()
+                .map(|cluster| cluster.mean_cycles * cluster.rounds as f64)
+                .sum(),
+            confidence_interval: Z_95 * variance.sqrt(),
+            clusters,
+        }
+    }
+    pub fn save(&self, path: &str) -> Result<()> {
+        let file = File::create(path).wrap_err_with(|| format!("cannot create estimate {path}"))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        satacc::{
+            Cnf, Distribution, LayoutConfig, MemoryLayout, SataccMinisatTask, Simulator,
+            SolveLimits, Solver, SyntheticConfig, SyntheticGenerator,
+        },
+    };
+
+    fn features(assignments: usize, watchers: usize) -> RoundFeatures {
+        RoundFeatures {
+            assignments,
+            watchers,
+            clauses: watchers / 2,
+            levels: 1,
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let rounds = (0..30)
+            .map(|round| match round % 3 {
+                0 => features(1, 2),
+                1 => features(30, 300),
+                _ => features(31, 310),
+            })
+            .collect::<Vec<_>>();
+        let config = SamplingConfig {
+            clusters: 2,
+            samples_per_cluster: 3,
+            ..Default::default()
+        };
+        let plan = SamplingPlan::new(&rounds, &config);
+        assert_eq!(plan.samples.len(), 2);
+        assert_eq!(plan.clusters[0], 0);
+        for (round, &cluster) in plan.clusters.iter().enumerate() {
+            assert_eq!(cluster, (round % 3 != 0) as usize);
+        }
+        for (cluster, samples) in plan.samples.iter().enumerate() {
+            assert_eq!(samples.len(), 3);
+            assert!(samples
+                .iter()
+                .all(|&round| plan.clusters[round] == cluster && plan.is_detailed(round)));
+        }
+
+        let estimate = SampledEstimate::new(&plan, &[vec![10, 10, 10], vec![90, 100, 110]]);
+        assert_eq!(estimate.simulated_rounds, 6);
+        assert_eq!(estimate.simulated_cycles, 330);
+        assert!((estimate.estimated_cycles - (10. * 10. + 20. * 100.)).abs() < 1e-9);
+        // only the second cluster varies: 1.96 * sqrt(20^2 * (1 - 3/20) * 10^2 / 3)
+        let expected = Z_95 * (400. * 0.85 * 100. / 3f64).sqrt();
+        assert!((estimate.confidence_interval - expected).abs() < 1e-9);
+        // a single sample takes its mean as the std: 10^2 * (1 - 1/10) * 10^2 / 1
+        let estimate = SampledEstimate::new(&plan, &[vec![10], vec![90, 100, 110]]);
+        assert_eq!(estimate.clusters[0].std_cycles, 10.);
+        let expected = Z_95 * (100. * 0.9 * 100. + 400. * 0.85 * 100. / 3f64).sqrt();
+        assert!((estimate.confidence_interval - expected).abs() < 1e-9);
+    }
+
+    /// the estimate is close to the full run, the rounds have different sizes
+    #[test]
+    fn test_sampled_simulation() {
+        let synthetic = SyntheticConfig {
+            rounds: 120,
+            assignments_per_round: Distribution::Histogram {
+                edges: vec![1, 4, 40, 64],
+                counts: vec![5, 0, 1],
+            },
+            watchers_per_assignment: Distribution::Uniform { min: 1, max: 6 },
+            ..Default::default()
+        };
+        let config = Config {
+            sampling_config: SamplingConfig {
+                clusters: 4,
+                samples_per_cluster: 6,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let simulator = Simulator::new_from_config(config);
+        let (_, full_cycles) = simulator
+            .run_task(
+                &mut SyntheticGenerator::new(synthetic.clone())
+                    .unwrap()
+                    .generate()
+                    .unwrap(),
+            )
+            .unwrap();
+        let (status, estimate) = simulator
+            .run_sampled(
+                &mut SyntheticGenerator::new(synthetic)
+                    .unwrap()
+                    .generate()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(estimate.total_rounds, 120);
+        assert!(estimate.simulated_rounds <= 24);
+        assert_eq!(status.statistics.total_cycle, estimate.simulated_cycles);
+        let error = (estimate.estimated_cycles - full_cycles as f64).abs();
+        assert!(
+            error < 0.1 * full_cycles as f64,
+            "estimate {estimate:?} full {full_cycles}"
+        );
+    }
+
+    /// the full cycles of the solver rounds of the pigeonhole instance are within the confidence interval
+    #[test]
+    fn test_sampled_cnf() {
+        let cnf = Cnf::from_file("test_data/pigeonhole_5.cnf").unwrap();
+        let generate = || {
+            let mut task = SataccMinisatTask::new();
+            Solver::new(&cnf, MemoryLayout::new(LayoutConfig::default(), 16))
+                .unwrap()
+                .solve(SolveLimits::default(), &mut task)
+                .unwrap();
+            task
+        };
+        let simulator = Simulator::new_from_config(Config::default());
+        let (_, full_cycles) = simulator.run_task(&mut generate()).unwrap();
+        let (_, estimate) = simulator.run_sampled(&mut generate()).unwrap();
+        assert!(estimate.simulated_rounds < estimate.total_rounds);
+        assert!(estimate.confidence_interval > 0.);
+        assert!(
+            (estimate.estimated_cycles - full_cycles as f64).abs() <= estimate.confidence_interval,
+            "estimate {} +- {} full {full_cycles}",
+            estimate.estimated_cycles,
+            estimate.confidence_interval
+        );
+    }
+}
@@ -1,11 +1,50 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fs::File, io::BufWriter};
+
+use libc::c_char;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+use super::{
+    clause_timing::ClauseTimingConfig,
+    functional::{ClauseLit, FunctionalState, LitValue},
+    icnt::IcntMsgWrapper,
+    layout::MemoryLayout,
+    trace::{path_from_c, TraceReader, TraceRecord, TraceWriter},
+    MemReq, MemReqKind, MemReqType, SataccStatus, NUM_MEM_PARTITIONS,
+};
+/// the result of a builder call, the call is ignored if it's not [`BuilderStatus::Ok`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuilderStatus {
+    Ok,
+    /// `start_new_assgin` is not called yet
+    NoRound,
+    /// the round has no assignment yet
+    NoAssignment,
+    /// the last watcher of the assignment does not read a clause
+    NoClauseWatcher,
+    /// the task is not created by `create_task_with_layout`
+    NoLayout,
+    /// the watch list or the clause doesn't fit in its partition of the layout
+    PartitionFull,
+}
+impl BuilderStatus {
+    fn report(self, builder: &str) -> Self {
+        tracing::error!("{builder} is ignored: {self:?}");
+        self
+    }
+}
 
-use super::{icnt::IcntMsgWrapper, MemReq, MemReqType, SataccStatus};
 /// # SataccMinisatTask
 /// the full task of the whole SAT solver
 /// - it contains many decisions in [`SingleRoundTask`]
+/// - when tracing, each call of the builder is also written to the trace, see [`TraceRecord`]
+/// - with a layout model, the `_by_id` builders place the data by the logical ids, see [`MemoryLayout`]
 pub struct SataccMinisatTask {
     pub decisions: VecDeque<SingleRoundTask>,
+    pub(crate) trace_writer: Option<TraceWriter<BufWriter<File>>>,
+    layout: Option<MemoryLayout>,
 }
 
 /// # SingleRoundTask
@@ -63,15 +102,35 @@ pub struct WatcherTask {
     /// the time to process the watcher list
     /// the watchers to be processed
     pub(crate) single_watcher_tasks: VecDeque<ClauseTask>,
+    /// the addr of the value of the assigned variable, the trail writes it when sending the task
+    pub(crate) value_addr: Option<u64>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ClauseData {
     pub clause_id: usize,
     pub clause_addr: u64,
-    pub clause_processing_time: usize,
+    /// the processing time passed by the builder, the clauses without it are timed by [`super::ClauseTimingConfig`]
+    pub clause_processing_time: Option<usize>,
     pub clause_value_addr: Vec<u64>,
     pub clause_value_id: Vec<usize>,
+    /// the literals of the clause with their software values, used by the closed-loop mode
+    pub clause_lits: Vec<ClauseLit>,
+    /// the software found the clause conflicting, the round stops at it, see [`super::ConflictConfig`]
+    pub conflict: bool,
+    /// the clause found a new watch, the clause unit writes the watch lists after processing it
+    pub new_watch: Option<NewWatch>,
+}
+
+/// the watcher is moved from the watch list of the assignment to the list of the new watch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewWatch {
+    /// the literal of the new watch, its watch list is in the l3 partition of its pe
+    pub lit: usize,
+    /// the slot at the end of the target watch list
+    pub append_addr: u64,
+    /// the slot of the source watch list that the next kept watcher is moved to
+    pub compact_addr: u64,
 }
 
 /// # ClauseTask
@@ -99,6 +158,7 @@ impl ClauseTask {
             mem_id: 0,
             is_write: false,
             req_type: MemReqType::WatcherReadBlocker,
+            next_addr: None,
         }
     }
     pub fn have_to_read_clause(&self) -> bool {
@@ -113,7 +173,11 @@ impl ClauseTask {
     ) -> IcntMsgWrapper<MemReq> {
         match &self.clause_data {
             Some(clause_data) => {
-                let mem_id = ((clause_data.clause_addr >> 6) & ((1 << 3) - 1)) as usize;
+                let mem_id = context.get_l3_partition(
+                    clause_data.clause_addr,
+                    watcher_pe_id,
+                    MemReqKind::ClauseData,
+                );
                 let req = MemReq {
                     addr: clause_data.clause_addr,
                     id: context.next_mem_id(),
@@ -121,6 +185,7 @@ impl ClauseTask {
                     mem_id,
                     is_write: false,
                     req_type: MemReqType::ClauseReadData(clause_pe_id),
+                    next_addr: None,
                 };
                 IcntMsgWrapper {
                     msg: req,
@@ -139,11 +204,29 @@ impl ClauseTask {
     ) -> Vec<IcntMsgWrapper<MemReq>> {
         let clause_data = self.clause_data.as_ref().unwrap();
         let clause_value_data = clause_data.clause_value_addr.clone();
+        let scratchpad_config = context.statistics.config.scratchpad_config;
 
         clause_value_data
             .into_iter()
             .map(|addr| {
-                let mem_id = ((addr >> 6) & ((1 << 3) - 1)) as usize;
+                // the scratchpad ports are right after the l3 cache ports
+                let (mem_id, mem_target_port) = if scratchpad_config.contains(addr) {
+                    let mem_id = ((addr >> 6) as usize) % NUM_MEM_PARTITIONS;
+                    (
+                        mem_id,
+                        total_watchers + context.num_l3_partitions() + mem_id,
+                    )
+                } else if context.read_value_in_private_cache(addr) {
+                    // the private cache has only one port
+                    (0, watcher_pe_id)
+                } else {
+                    if scratchpad_config.enable {
+                        context.statistics.scratchpad_statistics.out_of_range += 1;
+                    }
+                    let mem_id =
+                        context.get_l3_partition(addr, watcher_pe_id, MemReqKind::ClauseValue);
+                    (mem_id, total_watchers + mem_id)
+                };
 
                 let req = MemReq {
                     addr,
@@ -152,20 +235,59 @@ impl ClauseTask {
                     mem_id,
                     is_write: false,
                     req_type: MemReqType::ClauseReadValue(clause_pe_id),
+                    next_addr: None,
                 };
                 IcntMsgWrapper {
                     msg: req,
-                    mem_target_port: total_watchers + mem_id,
+                    mem_target_port,
                 }
             })
             .collect()
     }
+    /// the writes of the watch lists if the clause found a new watch, the append goes to the pe of the new watch
+    pub fn get_watch_list_write_reqs(
+        &self,
+        context: &mut SataccStatus,
+        watcher_pe_id: usize,
+        clause_pe_id: usize,
+        total_watchers: usize,
+    ) -> Vec<IcntMsgWrapper<MemReq>> {
+        let Some(new_watch) = self.clause_data.as_ref().and_then(|data| data.new_watch) else {
+            return vec![];
+        };
+        [
+            (new_watch.append_addr, new_watch.lit / 2 % total_watchers),
+            (new_watch.compact_addr, watcher_pe_id),
+        ]
+        .into_iter()
+        .map(|(addr, list_pe_id)| {
+            let mem_id = context.get_l3_partition(addr, list_pe_id, MemReqKind::WatchListWrite);
+            let req = MemReq {
+                addr,
+                id: context.next_mem_id(),
+                watcher_pe_id,
+                mem_id,
+                is_write: true,
+                req_type: MemReqType::ClauseWriteWatchList(clause_pe_id),
+                next_addr: None,
+            };
+            IcntMsgWrapper {
+                msg: req,
+                mem_target_port: total_watchers + mem_id,
+            }
+        })
+        .collect()
+    }
     pub fn get_watcher_pe_id(&self, total_watchers: usize) -> usize {
         self.watcher_id / 2 % total_watchers
     }
     /// the process time to process the whole clause
-    pub fn get_process_time(&self) -> usize {
-        self.clause_data.as_ref().unwrap().clause_processing_time
+    pub fn get_process_time(
+        &self,
+        timing_config: &ClauseTimingConfig,
+        functional: Option<&FunctionalState>,
+    ) -> usize {
+        timing_config.processing_time(self.clause_data.as_ref().unwrap(), functional)
     }
     pub fn get_inner_clause_pe_id(&self, num_clause_per_watcher: usize) -> usize {
         self.clause_data.as_ref().unwrap().clause_id % num_clause_per_watcher
@@ -175,8 +297,174 @@ impl SataccMinisatTask {
     pub fn new() -> Self {
         Self {
             decisions: VecDeque::new(),
+            trace_writer: None,
+            layout: None,
+        }
+    }
+    /// the addresses of the `_by_id` builders come from the layout
+    pub fn with_layout(layout: MemoryLayout) -> Self {
+        Self {
+            layout: Some(layout),
+            ..Self::new()
+        }
+    }
+    fn layout(&mut self) -> Result<&mut MemoryLayout, BuilderStatus> {
+        self.layout.as_mut().ok_or(BuilderStatus::NoLayout)
+    }
+    fn last_assignment(&mut self) -> Result<&mut WatcherTask, BuilderStatus> {
+        self.decisions
+            .back_mut()
+            .ok_or(BuilderStatus::NoRound)?
+            .assignments
+            .back_mut()
+            .ok_or(BuilderStatus::NoAssignment)
+    }
+    /// the clause read by the last watcher of the last assignment
+    fn last_clause_data(&mut self) -> Result<&mut ClauseData, BuilderStatus> {
+        self.last_assignment()?
+            .single_watcher_tasks
+            .back_mut()
+            .and_then(|watcher| watcher.clause_data.as_mut())
+            .ok_or(BuilderStatus::NoClauseWatcher)
+    }
+    /// write the record to the trace, stop tracing if it fails
+    fn record(&mut self, record: TraceRecord) {
+        if let Some(trace_writer) = self.trace_writer.as_mut() {
+            if let Err(e) = trace_writer.write(&record) {
+                tracing::error!("stop tracing: {e:?}");
+                self.trace_writer = None;
+            }
+        }
+    }
+    /// apply a record of the trace, it's the same as calling the builder
+    pub fn apply_record(&mut self, record: TraceRecord) -> BuilderStatus {
+        match record {
+            TraceRecord::NewRound => {
+                self.start_new_assgin();
+                BuilderStatus::Ok
+            }
+            TraceRecord::WatcherTask {
+                level,
+                meta_data_addr,
+                watcher_addr,
+                watcher_id,
+            } => self.add_watcher_task(level, meta_data_addr, watcher_addr, watcher_id),
+            TraceRecord::AssignmentValueAddr { value_addr } => {
+                self.set_assignment_value_addr(value_addr)
+            }
+            TraceRecord::SingleWatcherNoClause {
+                blocker_addr,
+                watcher_id,
+            } => self.add_single_watcher_task_no_clause(blocker_addr, watcher_id),
+            TraceRecord::SingleWatcher {
+                blocker_addr,
+                clause_addr,
+                clause_id,
+                processing_time,
+                watcher_id,
+            } => self.add_single_watcher_task(
+                blocker_addr,
+                clause_addr,
+                clause_id,
+                processing_time,
+                watcher_id,
+            ),
+            TraceRecord::SingleWatcherUntimed {
+                blocker_addr,
+                clause_addr,
+                clause_id,
+                watcher_id,
+            } => self.add_single_watcher_task_untimed(
+                blocker_addr,
+                clause_addr,
+                clause_id,
+                watcher_id,
+            ),
+            TraceRecord::ClauseValueAddr {
+                value_addr,
+                clause_id,
+            } => self.add_single_watcher_clause_value_addr(value_addr, clause_id),
+            TraceRecord::ClauseLit { lit, value } => self.add_single_watcher_clause_lit(lit, value),
+            TraceRecord::ClauseConflict => self.set_clause_conflict(),
+            TraceRecord::ClauseNewWatch {
+                lit,
+                append_addr,
+                compact_addr,
+            } => self.set_clause_new_watch(lit, append_addr, compact_addr),
         }
     }
+    /// the records that rebuild the task
+    pub fn to_records(&self) -> Vec<TraceRecord> {
+        let mut records = vec![];
+        for round in self.decisions.iter() {
+            records.push(TraceRecord::NewRound);
+            for assignment in round.assignments.iter() {
+                records.push(TraceRecord::WatcherTask {
+                    level: assignment.level,
+                    meta_data_addr: assignment.meta_data_addr,
+                    watcher_addr: assignment.watcher_addr,
+                    watcher_id: assignment.watcher_id,
+                });
+                if let Some(value_addr) = assignment.value_addr {
+                    records.push(TraceRecord::AssignmentValueAddr { value_addr });
+                }
+                for clause_task in assignment.single_watcher_tasks.iter() {
+                    match &clause_task.clause_data {
+                        None => records.push(TraceRecord::SingleWatcherNoClause {
+                            blocker_addr: clause_task.blocker_addr,
+                            watcher_id: clause_task.watcher_id,
+                        }),
+                        Some(clause_data) => {
+                            records.push(match clause_data.clause_processing_time {
+                                Some(processing_time) => TraceRecord::SingleWatcher {
+                                    blocker_addr: clause_task.blocker_addr,
+                                    clause_addr: clause_data.clause_addr,
+                                    clause_id: clause_data.clause_id,
+                                    processing_time,
+                                    watcher_id: clause_task.watcher_id,
+                                },
+                                None => TraceRecord::SingleWatcherUntimed {
+                                    blocker_addr: clause_task.blocker_addr,
+                                    clause_addr: clause_data.clause_addr,
+                                    clause_id: clause_data.clause_id,
+                                    watcher_id: clause_task.watcher_id,
+                                },
+                            });
+                            records.extend(
+                                clause_data
+                                    .clause_value_addr
+                                    .iter()
+                                    .zip(clause_data.clause_value_id.iter())
+                                    .map(|(&value_addr, &clause_id)| {
+                                        TraceRecord::ClauseValueAddr {
+                                            value_addr,
+                                            clause_id,
+                                        }
+                                    }),
+                            );
+                            records.extend(clause_data.clause_lits.iter().map(|clause_lit| {
+                                TraceRecord::ClauseLit {
+                                    lit: clause_lit.lit,
+                                    value: clause_lit.value,
+                                }
+                            }));
+                            if clause_data.conflict {
+                                records.push(TraceRecord::ClauseConflict);
+                            }
+                            if let Some(new_watch) = clause_data.new_watch {
+                                records.push(TraceRecord::ClauseNewWatch {
+                                    lit: new_watch.lit,
+                                    append_addr: new_watch.append_addr,
+                                    compact_addr: new_watch.compact_addr,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        records
+    }
 
     #[no_mangle]
     /// this will create a simulator task object, do not free it, it will be freed by calling `run_full_expr`
@@ -186,6 +474,7 @@ impl SataccMinisatTask {
 
     #[no_mangle]
     pub extern "C" fn start_new_assgin(&mut self) {
+        self.record(TraceRecord::NewRound);
         self.decisions.push_back(SingleRoundTask {
             assignments: VecDeque::new(),
         });
@@ -194,7 +483,7 @@ impl SataccMinisatTask {
     #[no_mangle]
     pub extern "C" fn release_task(task: *mut Self) {
         unsafe {
-            Box::from_raw(task);
+            drop(Box::from_raw(task));
         }
     }
 
@@ -205,39 +494,59 @@ impl SataccMinisatTask {
         meta_data_addr: u64,
         watcher_addr: u64,
         watcher_id: usize,
-    ) {
-        self.decisions
-            .back_mut()
-            .unwrap()
-            .assignments
-            .push_back(WatcherTask {
-                level,
-                meta_data_addr,
-                watcher_addr,
-                watcher_id,
-                single_watcher_tasks: VecDeque::new(),
-            });
+    ) -> BuilderStatus {
+        let Some(round) = self.decisions.back_mut() else {
+            return BuilderStatus::NoRound.report("add_watcher_task");
+        };
+        round.assignments.push_back(WatcherTask {
+            level,
+            meta_data_addr,
+            watcher_addr,
+            watcher_id,
+            single_watcher_tasks: VecDeque::new(),
+            value_addr: None,
+        });
+        self.record(TraceRecord::WatcherTask {
+            level,
+            meta_data_addr,
+            watcher_addr,
+            watcher_id,
+        });
+        BuilderStatus::Ok
+    }
+    /// set the addr of the value written by the last assignment
+    #[no_mangle]
+    pub extern "C" fn set_assignment_value_addr(&mut self, value_addr: u64) -> BuilderStatus {
+        match self.last_assignment() {
+            Ok(assignment) => assignment.value_addr = Some(value_addr),
+            Err(status) => return status.report("set_assignment_value_addr"),
+        }
+        self.record(TraceRecord::AssignmentValueAddr { value_addr });
+        BuilderStatus::Ok
     }
     #[no_mangle]
     pub extern "C" fn add_single_watcher_task_no_clause(
         &mut self,
         blocker_addr: u64,
         watcher_id: usize,
-    ) {
-        self.decisions
-            .back_mut()
-            .unwrap()
-            .assignments
-            .back_mut()
-            .unwrap()
-            .single_watcher_tasks
-            .push_back(ClauseTask {
+    ) -> BuilderStatus {
+        match self.last_assignment() {
+            Ok(assignment) => assignment.single_watcher_tasks.push_back(ClauseTask {
                 blocker_addr,
                 watcher_id,
                 clause_data: None,
-            });
+            }),
+            Err(status) => return status.report("add_single_watcher_task_no_clause"),
+        }
+        self.record(TraceRecord::SingleWatcherNoClause {
+            blocker_addr,
+            watcher_id,
+        });
+        BuilderStatus::Ok
     }
 
+    /// add a watcher that reads the clause, it takes `processing_time` cycles unless
+    /// [`super::ClauseTimingConfig`] is enabled
     #[no_mangle]
     pub extern "C" fn add_single_watcher_task(
         &mut self,
@@ -246,54 +555,302 @@ impl SataccMinisatTask {
         clause_id: usize,
         processing_time: usize,
         watcher_id: usize,
-    ) {
-        self.decisions
-            .back_mut()
-            .unwrap()
-            .assignments
-            .back_mut()
-            .unwrap()
-            .single_watcher_tasks
-            .push_back(ClauseTask {
+    ) -> BuilderStatus {
+        self.push_clause_watcher(
+            blocker_addr,
+            clause_addr,
+            clause_id,
+            Some(processing_time),
+            watcher_id,
+            "add_single_watcher_task",
+        )
+    }
+    /// add a watcher that reads the clause, the processing time is always derived by [`super::ClauseTimingConfig`]
+    #[no_mangle]
+    pub extern "C" fn add_single_watcher_task_untimed(
+        &mut self,
+        blocker_addr: u64,
+        clause_addr: u64,
+        clause_id: usize,
+        watcher_id: usize,
+    ) -> BuilderStatus {
+        self.push_clause_watcher(
+            blocker_addr,
+            clause_addr,
+            clause_id,
+            None,
+            watcher_id,
+            "add_single_watcher_task_untimed",
+        )
+    }
+    fn push_clause_watcher(
+        &mut self,
+        blocker_addr: u64,
+        clause_addr: u64,
+        clause_id: usize,
+        processing_time: Option<usize>,
+        watcher_id: usize,
+        name: &str,
+    ) -> BuilderStatus {
+        match self.last_assignment() {
+            Ok(assignment) => assignment.single_watcher_tasks.push_back(ClauseTask {
                 blocker_addr,
                 watcher_id,
                 clause_data: Some(ClauseData {
                     clause_id,
                     clause_addr,
                     clause_processing_time: processing_time,
-                    clause_value_addr: Vec::new(),
-                    clause_value_id: Vec::new(),
+                    ..Default::default()
                 }),
-            });
+            }),
+            Err(status) => return status.report(name),
+        }
+        self.record(match processing_time {
+            Some(processing_time) => TraceRecord::SingleWatcher {
+                blocker_addr,
+                clause_addr,
+                clause_id,
+                processing_time,
+                watcher_id,
+            },
+            None => TraceRecord::SingleWatcherUntimed {
+                blocker_addr,
+                clause_addr,
+                clause_id,
+                watcher_id,
+            },
+        });
+        BuilderStatus::Ok
     }
     #[no_mangle]
     pub extern "C" fn add_single_watcher_clause_value_addr(
         &mut self,
         value_addr: u64,
         clause_id: usize,
-    ) {
-        let last_single_watcher = self
-            .decisions
-            .back_mut()
-            .unwrap()
-            .assignments
-            .back_mut()
-            .unwrap()
-            .single_watcher_tasks
-            .back_mut()
-            .unwrap();
-        last_single_watcher
-            .clause_data
-            .as_mut()
-            .unwrap()
-            .clause_value_addr
-            .push(value_addr);
-        last_single_watcher
-            .clause_data
-            .as_mut()
-            .unwrap()
-            .clause_value_id
-            .push(clause_id);
+    ) -> BuilderStatus {
+        let clause_data = match self.last_clause_data() {
+            Ok(clause_data) => clause_data,
+            Err(status) => return status.report("add_single_watcher_clause_value_addr"),
+        };
+        clause_data.clause_value_addr.push(value_addr);
+        clause_data.clause_value_id.push(clause_id);
+        self.record(TraceRecord::ClauseValueAddr {
+            value_addr,
+            clause_id,
+        });
+        BuilderStatus::Ok
+    }
+    /// the clause of the last watcher has the literal `lit` with the `value` seen by the software
+    #[no_mangle]
+    pub extern "C" fn add_single_watcher_clause_lit(
+        &mut self,
+        lit: usize,
+        value: LitValue,
+    ) -> BuilderStatus {
+        let clause_data = match self.last_clause_data() {
+            Ok(clause_data) => clause_data,
+            Err(status) => return status.report("add_single_watcher_clause_lit"),
+        };
+        clause_data.clause_lits.push(ClauseLit { lit, value });
+        self.record(TraceRecord::ClauseLit { lit, value });
+        BuilderStatus::Ok
+    }
+    /// the clause of the last watcher is conflicting
+    #[no_mangle]
+    pub extern "C" fn set_clause_conflict(&mut self) -> BuilderStatus {
+        let clause_data = match self.last_clause_data() {
+            Ok(clause_data) => clause_data,
+            Err(status) => return status.report("set_clause_conflict"),
+        };
+        clause_data.conflict = true;
+        self.record(TraceRecord::ClauseConflict);
+        BuilderStatus::Ok
+    }
+    /// the clause of the last watcher moves the watcher to the watch list of literal `lit`
+    #[no_mangle]
+    pub extern "C" fn set_clause_new_watch(
+        &mut self,
+        lit: usize,
+        append_addr: u64,
+        compact_addr: u64,
+    ) -> BuilderStatus {
+        let clause_data = match self.last_clause_data() {
+            Ok(clause_data) => clause_data,
+            Err(status) => return status.report("set_clause_new_watch"),
+        };
+        clause_data.new_watch = Some(NewWatch {
+            lit,
+            append_addr,
+            compact_addr,
+        });
+        self.record(TraceRecord::ClauseNewWatch {
+            lit,
+            append_addr,
+            compact_addr,
+        });
+        BuilderStatus::Ok
+    }
+
+    /// write the following builder calls to the trace file at `path`, return false if it cannot be created
+    #[no_mangle]
+    pub extern "C" fn start_trace(&mut self, path: *const c_char) -> bool {
+        match path_from_c(path).and_then(TraceWriter::create) {
+            Ok(trace_writer) => {
+                self.trace_writer = Some(trace_writer);
+                true
+            }
+            Err(e) => {
+                tracing::error!("cannot start tracing: {e:?}");
+                false
+            }
+        }
+    }
+
+    /// flush and close the trace, return false if the trace is not complete
+    #[no_mangle]
+    pub extern "C" fn finish_trace(&mut self) -> bool {
+        match self.trace_writer.take().map(TraceWriter::finish) {
+            Some(Ok(_)) => true,
+            Some(Err(e)) => {
+                tracing::error!("cannot finish the trace: {e:?}");
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// create a task that places the data by the `layout_config` and `n_watchers` of `satacc_config.toml`,
+    /// the `_by_id` builders can be used on it, free it by `release_task`, return null if the config is invalid
+    #[no_mangle]
+    pub extern "C" fn create_task_with_layout() -> *mut Self {
+        match Config::from_config_file("satacc_config.toml") {
+            Ok(config) => {
+                let layout = MemoryLayout::new(config.layout_config, config.n_watchers);
+                Box::into_raw(Box::new(Self::with_layout(layout)))
+            }
+            Err(e) => {
+                tracing::error!("cannot create the task: {e:?}");
+                std::ptr::null_mut()
+            }
+        }
+    }
+    /// add the assignment of literal `lit`, its watch list has `watch_list_len` watchers
+    #[no_mangle]
+    pub extern "C" fn add_watcher_task_by_id(
+        &mut self,
+        level: usize,
+        lit: usize,
+        watch_list_len: usize,
+    ) -> BuilderStatus {
+        if self.decisions.is_empty() {
+            return BuilderStatus::NoRound.report("add_watcher_task_by_id");
+        }
+        let layout = match self.layout() {
+            Ok(layout) => layout,
+            Err(status) => return status.report("add_watcher_task_by_id"),
+        };
+        if let Err(e) = layout.reserve_watchers(lit, watch_list_len) {
+            tracing::error!("{e}");
+            return BuilderStatus::PartitionFull.report("add_watcher_task_by_id");
+        }
+        let meta_data_addr = layout.watch_meta_addr(lit);
+        let watcher_addr = layout.watch_data_addr(lit);
+        let value_addr = layout.value_addr(lit / 2);
+        self.add_watcher_task(level, meta_data_addr, watcher_addr, lit);
+        self.set_assignment_value_addr(value_addr)
+    }
+    /// add a watcher whose blocker is variable `blocker_var`
+    #[no_mangle]
+    pub extern "C" fn add_single_watcher_task_no_clause_by_id(
+        &mut self,
+        blocker_var: usize,
+        watcher_id: usize,
+    ) -> BuilderStatus {
+        let blocker_addr = match self.layout() {
+            Ok(layout) => layout.value_addr(blocker_var),
+            Err(status) => return status.report("add_single_watcher_task_no_clause_by_id"),
+        };
+        self.add_single_watcher_task_no_clause(blocker_addr, watcher_id)
+    }
+    /// add a watcher that reads clause `clause_id` of `clause_len` literals,
+    /// the processing time is derived by [`super::ClauseTimingConfig`]
+    #[no_mangle]
+    pub extern "C" fn add_single_watcher_task_by_id(
+        &mut self,
+        blocker_var: usize,
+        clause_id: usize,
+        clause_len: usize,
+        watcher_id: usize,
+    ) -> BuilderStatus {
+        if let Err(status) = self.last_assignment() {
+            return status.report("add_single_watcher_task_by_id");
+        }
+        let (blocker_addr, clause_addr) = match self.layout() {
+            Ok(layout) => match layout.clause_addr(clause_id, clause_len, watcher_id) {
+                Ok(clause_addr) => (layout.value_addr(blocker_var), clause_addr),
+                Err(e) => {
+                    tracing::error!("{e}");
+                    return BuilderStatus::PartitionFull.report("add_single_watcher_task_by_id");
+                }
+            },
+            Err(status) => return status.report("add_single_watcher_task_by_id"),
+        };
+        self.add_single_watcher_task_untimed(blocker_addr, clause_addr, clause_id, watcher_id)
+    }
+    /// the clause of the last watcher reads the value of variable `var`
+    #[no_mangle]
+    pub extern "C" fn add_single_watcher_clause_value_by_id(
+        &mut self,
+        var: usize,
+    ) -> BuilderStatus {
+        let value_addr = match self.layout() {
+            Ok(layout) => layout.value_addr(var),
+            Err(status) => return status.report("add_single_watcher_clause_value_by_id"),
+        };
+        self.add_single_watcher_clause_value_addr(value_addr, var)
+    }
+    /// the clause of the last watcher moves the watcher to the watch list of literal `lit` as its `append_index`th watcher,
+    /// the source watch list is compacted at `compact_index`
+    #[no_mangle]
+    pub extern "C" fn set_clause_new_watch_by_id(
+        &mut self,
+        lit: usize,
+        append_index: usize,
+        compact_index: usize,
+    ) -> BuilderStatus {
+        let watcher_id = match self.last_assignment() {
+            Ok(assignment) => assignment.watcher_id,
+            Err(status) => return status.report("set_clause_new_watch_by_id"),
+        };
+        let (append_addr, compact_addr) = match self.layout() {
+            Ok(layout) => {
+                if let Err(e) = layout.reserve_watchers(lit, append_index + 1) {
+                    tracing::error!("{e}");
+                    return BuilderStatus::PartitionFull.report("set_clause_new_watch_by_id");
+                }
+                (
+                    layout.watcher_addr(lit, append_index),
+                    layout.watcher_addr(watcher_id, compact_index),
+                )
+            }
+            Err(status) => return status.report("set_clause_new_watch_by_id"),
+        };
+        self.set_clause_new_watch(lit, append_addr, compact_addr)
+    }
+
+    /// read the task from the trace file, return null if the trace is invalid, free it by `release_task`
+    #[no_mangle]
+    pub extern "C" fn load_task_from_trace(path: *const c_char) -> *mut Self {
+        match path_from_c(path)
+            .and_then(TraceReader::open)
+            .and_then(TraceReader::read_task)
+        {
+            Ok(task) => Box::into_raw(Box::new(task)),
+            Err(e) => {
+                tracing::error!("cannot load the trace: {e:?}");
+                std::ptr::null_mut()
+            }
+        }
     }
 }
 
@@ -317,7 +874,7 @@ impl WatcherTask {
         watcher_pe_id: usize,
     ) -> IcntMsgWrapper<MemReq> {
         let addr = self.meta_data_addr;
-        let partion_id = ((addr >> 6) & ((1 << 3) - 1)) as usize;
+        let partion_id = context.get_l3_partition(addr, watcher_pe_id, MemReqKind::MetaData);
         IcntMsgWrapper {
             msg: MemReq {
                 addr: addr,
@@ -325,6 +882,7 @@ impl WatcherTask {
                 mem_id: partion_id,
                 is_write: false,
                 req_type: MemReqType::WatcherReadMetaData,
+                next_addr: Some(self.watcher_addr),
                 watcher_pe_id,
             },
             mem_target_port: total_watchers + partion_id,
@@ -337,7 +895,7 @@ impl WatcherTask {
         watcher_pe_id: usize,
     ) -> IcntMsgWrapper<MemReq> {
         let addr = self.watcher_addr;
-        let partion_id = ((addr >> 6) & ((1 << 3) - 1)) as usize;
+        let partion_id = context.get_l3_partition(addr, watcher_pe_id, MemReqKind::WatcherData);
         IcntMsgWrapper {
             msg: MemReq {
                 addr,
@@ -345,6 +903,7 @@ impl WatcherTask {
                 mem_id: partion_id,
                 is_write: false,
                 req_type: MemReqType::WatcherReadData,
+                next_addr: None,
                 watcher_pe_id,
             },
             mem_target_port: total_watchers + partion_id,
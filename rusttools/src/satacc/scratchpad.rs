@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::{InOutPort, SimComponent};
+
+use super::{icnt::IcntMsgWrapper, wating_task::WaitingTask, MemReq, SataccStatus};
+
+/// the config of the on-chip scratchpad that holds the variable values
+/// - the value requests in `[base_addr, base_addr + capacity)` are served by the scratchpad
+/// - the value requests out of the range still go to the l3 cache
+#[repr(C)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct ScratchpadConfig {
+    pub enable: bool,
+    pub base_addr: u64,
+    /// the capacity in bytes
+    pub capacity: u64,
+    pub banks: u64,
+    /// the bytes of each bank line, continuous lines are interleaved across the banks
+    pub bank_width: u64,
+    pub latency: usize,
+}
+impl ScratchpadConfig {
+    /// the addr should be served by the scratchpad
+    pub fn contains(&self, addr: u64) -> bool {
+        self.enable && addr >= self.base_addr && addr - self.base_addr < self.capacity
+    }
+    pub fn get_bank(&self, addr: u64) -> usize {
+        ((addr - self.base_addr) / self.bank_width % self.banks) as usize
+    }
+}
+
+/// # Scratchpad
+/// the sram that serves the value requests at fixed latency, there is no tag and no dram behind it
+/// - each bank can accept one request per cycle, the other requests to the same bank stall in the in port
+pub struct Scratchpad {
+    config: ScratchpadConfig,
+    req_ports: Vec<InOutPort<IcntMsgWrapper<MemReq>>>,
+    /// the cycle that each bank can accept a new request
+    bank_ready_cycle: Vec<usize>,
+    on_going_reqs: WaitingTask<MemReq>,
+    ready_reqs: VecDeque<MemReq>,
+}
+
+impl Scratchpad {
+    pub fn new(
+        config: &ScratchpadConfig,
+        req_ports: Vec<InOutPort<IcntMsgWrapper<MemReq>>>,
+    ) -> Self {
+        Self {
+            config: *config,
+            req_ports,
+            bank_ready_cycle: vec![0; config.banks as usize],
+            on_going_reqs: WaitingTask::new(),
+            ready_reqs: VecDeque::new(),
+        }
+    }
+}
+
+impl SimComponent for Scratchpad {
+    type SharedStatus = SataccStatus;
+    fn update(
+        &mut self,
+        shared_status: &mut Self::SharedStatus,
+        current_cycle: usize,
+    ) -> (bool, bool) {
+        let mut busy = !self.on_going_reqs.is_empty() || !self.ready_reqs.is_empty();
+        let mut updated = false;
+        // accept the requests, one request per bank
+        for InOutPort {
+            in_port,
+            out_port: _,
+        } in &mut self.req_ports
+        {
+            if let Ok(req) = in_port.recv() {
+                busy = true;
+                let bank = self.config.get_bank(req.msg.addr);
+                if self.bank_ready_cycle[bank] > current_cycle {
+                    tracing::debug!(bank, "scratchpad bank conflict");
+                    shared_status
+                        .statistics
+                        .scratchpad_statistics
+                        .bank_conflicts += 1;
+                    in_port.ret(req);
+                    continue;
+                }
+                self.bank_ready_cycle[bank] = current_cycle + 1;
+                shared_status.statistics.scratchpad_statistics.accesses += 1;
+                self.on_going_reqs
+                    .push(req.msg, current_cycle + self.config.latency);
+                updated = true;
+            }
+        }
+        while let Some((leaving_cycle, req)) = self.on_going_reqs.pop() {
+            if leaving_cycle > current_cycle {
+                self.on_going_reqs.push(req, leaving_cycle);
+                break;
+            } else {
+                updated = true;
+                self.ready_reqs.push_back(req);
+            }
+        }
+        while let Some(req) = self.ready_reqs.pop_front() {
+            let out_id = req.mem_id;
+            let watcher_id = req.watcher_pe_id;
+            match self.req_ports[out_id].out_port.send(IcntMsgWrapper {
+                msg: req,
+                mem_target_port: watcher_id,
+            }) {
+                Ok(_) => {
+                    updated = true;
+                }
+                Err(e) => {
+                    self.ready_reqs.push_front(e.msg);
+                    break;
+                }
+            }
+        }
+        (busy, updated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::Config,
+        satacc::MemReqType,
+        sim::{ChannelBuilder, SimRunner},
+    };
+
+    use super::*;
+    fn value_req(addr: u64, id: usize, mem_id: usize) -> IcntMsgWrapper<MemReq> {
+        IcntMsgWrapper {
+            msg: MemReq {
+                addr,
+                id,
+                watcher_pe_id: 0,
+                mem_id,
+                is_write: false,
+                req_type: MemReqType::ClauseReadValue(0),
+                next_addr: None,
+            },
+            mem_target_port: 1,
+        }
+    }
+    #[test]
+    fn test_scratchpad_bank_conflict() {
+        let config = ScratchpadConfig {
+            enable: true,
+            base_addr: 0,
+            capacity: 1024,
+            banks: 2,
+            bank_width: 64,
+            latency: 2,
+        };
+        assert!(config.contains(1000));
+        assert!(!config.contains(1024));
+        let channel_builder = ChannelBuilder::new();
+        let (inout_base, inout_scratchpad) = channel_builder.in_out_poat_array(16, 2);
+        let scratchpad = Scratchpad::new(&config, inout_scratchpad);
+        let mut sim_runner = SimRunner::new(scratchpad, SataccStatus::new(Config::default()));
+        // the first two go to the same bank in the same cycle, the third one go to the other bank
+        inout_base[0].out_port.send(value_req(0, 0, 0)).unwrap();
+        inout_base[1].out_port.send(value_req(128, 1, 1)).unwrap();
+        inout_base[1].out_port.send(value_req(64, 2, 1)).unwrap();
+        sim_runner.run().unwrap();
+        let mut finished = 0;
+        for port in &inout_base {
+            while port.in_port.recv().is_ok() {
+                finished += 1;
+            }
+        }
+        assert_eq!(finished, 3);
+        let stat = &sim_runner.get_shared_status_mut().statistics;
+        assert_eq!(stat.scratchpad_statistics.accesses, 3);
+        assert_eq!(stat.scratchpad_statistics.bank_conflicts, 1);
+    }
+}
@@ -1,15 +1,27 @@
 use std::fs::File;
 
+use eyre::bail;
+use libc::c_char;
+
 use serde::{Deserialize, Serialize};
 
 use crate::{
     config::Config,
     satacc::{
-        cache::{CacheWithFixTime, CacheWithRamulator},
+        cache::{CacheWithFixTime, CacheWithRamulator, DramModel, DramTiming, L3Cache},
+        coherence::CoherenceUnit,
         icnt::IcntMsgWrapper,
+        layout::MemoryLayout,
+        mem_profiler::MemProfiler,
+        round_features,
         satacc_minisat_task::ClauseTask,
-        watcher_interface::WatcherInterface,
-        MemReq, SataccStatus,
+        scratchpad::Scratchpad,
+        task_source::TaskSource,
+        trace::path_from_c,
+        validation::{ensure_valid, validate_round, validate_task, validate_trace},
+        watcher_interface::{WatcherInterface, WatcherInterfaceConfig},
+        CacheConfig, MemReq, MemReqKind, RoundFeatures, SampledEstimate, SamplingPlan,
+        SataccStatus, SolveLimits, SolveResult, SolverTaskSource, TraceReader, NUM_MEM_PARTITIONS,
     },
     sim::{ChannelBuilder, SimComponent, SimRunner, SimSender},
 };
@@ -33,13 +45,18 @@ pub enum RunMode {
     NoGapBtweenRounds,
     RealRoundGap,
 }
+/// the addr and the dirty bit of the lines of the l3 cache and of each private cache
+pub type CacheLines = (Vec<(u64, bool)>, Vec<Vec<(u64, bool)>>);
 pub struct TrailAndOthers {
     trail: Trail,
     others: (
         Vec<WatcherInterface>,
         SimpleIcnt<IcntMsgWrapper<MemReq>>,
         SimpleIcnt<IcntMsgWrapper<ClauseTask>>,
-        Box<dyn SimComponent<SharedStatus = SataccStatus>>,
+        Option<MemProfiler>,
+        Box<dyn L3Cache>,
+        Scratchpad,
+        Option<CoherenceUnit>,
     ),
     current_running_mode: RunMode,
 }
@@ -69,6 +86,86 @@ impl SimComponent for TrailAndOthers {
     }
 }
 
+impl TrailAndOthers {
+    /// push the reads of the round through the caches without timing, the statistics are not changed
+    /// - each read goes to the same cache as in the detailed simulation, the scratchpad reads are skipped
+    /// - the value writes of the trail are not modeled, the watch list writes are accessed like the reads
+    pub fn warm_up(&mut self, round: &SingleRoundTask, shared_status: &mut SataccStatus) {
+        let config = &shared_status.statistics.config;
+        let n_watchers = config.n_watchers;
+        let nuca_config = config.nuca_config;
+        let scratchpad_config = config.scratchpad_config;
+        let (watchers_interface, _, _, _, l3_cache, _, _) = &mut self.others;
+        for assignment in &round.assignments {
+            let watcher_pe_id = assignment.get_watcher_pe_id(n_watchers);
+            // the nuca partition depends on the pe that owns the data
+            let mut read_l3 = |addr: u64, pe_id: usize, kind: MemReqKind| {
+                let partition = nuca_config.get_partition(addr, pe_id, kind, n_watchers);
+                l3_cache.warm_up(partition, addr, kind);
+            };
+            read_l3(
+                assignment.meta_data_addr,
+                watcher_pe_id,
+                MemReqKind::MetaData,
+            );
+            read_l3(
+                assignment.watcher_addr,
+                watcher_pe_id,
+                MemReqKind::WatcherData,
+            );
+            for watcher in &assignment.single_watcher_tasks {
+                read_l3(watcher.blocker_addr, watcher_pe_id, MemReqKind::Blocker);
+                let Some(clause_data) = &watcher.clause_data else {
+                    continue;
+                };
+                read_l3(
+                    clause_data.clause_addr,
+                    watcher_pe_id,
+                    MemReqKind::ClauseData,
+                );
+                for &addr in &clause_data.clause_value_addr {
+                    if scratchpad_config.contains(addr) {
+                        continue;
+                    }
+                    if shared_status.read_value_in_private_cache(addr) {
+                        watchers_interface[watcher_pe_id].warm_up_private_cache(
+                            addr,
+                            MemReqKind::ClauseValue,
+                            shared_status,
+                            watcher_pe_id,
+                        );
+                    } else {
+                        read_l3(addr, watcher_pe_id, MemReqKind::ClauseValue);
+                    }
+                }
+                if let Some(new_watch) = clause_data.new_watch {
+                    read_l3(
+                        new_watch.append_addr,
+                        new_watch.lit / 2 % n_watchers,
+                        MemReqKind::WatchListWrite,
+                    );
+                    read_l3(
+                        new_watch.compact_addr,
+                        watcher_pe_id,
+                        MemReqKind::WatchListWrite,
+                    );
+                }
+            }
+        }
+    }
+    /// the lines of the l3 cache and of the private cache of each watcher, used to check the warm-up
+    pub fn cache_lines(&self) -> CacheLines {
+        let (watchers_interface, _, _, _, l3_cache, _, _) = &self.others;
+        (
+            l3_cache.resident_lines(),
+            watchers_interface
+                .iter()
+                .map(WatcherInterface::private_cache_lines)
+                .collect(),
+        )
+    }
+}
+
 impl Simulator {
     pub fn new(config_file: &str) -> Self {
         Self {
@@ -110,13 +207,46 @@ impl Simulator {
         unsafe {
             let task = &mut *task;
             let sim = &mut *sim;
-            sim.task_sender
-                .send(task.pop_next_task().unwrap())
-                .unwrap_or_else(|_e| {
-                    panic!("send task error");
-                });
+            let Some(single_round_task) = task.pop_next_task() else {
+                tracing::error!("the task has no round");
+                return false;
+            };
+            let errors = validate_round(sim.total_rounds, &single_round_task);
+            if !errors.is_empty() {
+                for error in &errors {
+                    tracing::error!("{error}");
+                }
+                return false;
+            }
+            let fast_forward_config = sim
+                .sim_runner
+                .get_shared_status()
+                .statistics
+                .config
+                .fast_forward_config;
+            if !fast_forward_config.is_detailed(sim.total_rounds) {
+                let (comp, status) = sim.sim_runner.get_sim_and_status_mut();
+                comp.warm_up(&single_round_task, status);
+                status
+                    .statistics
+                    .fast_forward_statistics
+                    .fast_forward_rounds += 1;
+                sim.total_rounds += 1;
+                return true;
+            }
+            let start_cycle = sim.sim_runner.get_current_cycle();
+            if sim.task_sender.send(single_round_task).is_err() {
+                tracing::error!("cannot send the round {} to the trail", sim.total_rounds);
+                return false;
+            }
             match sim.sim_runner.run() {
                 Ok(_) => {
+                    let cycles = sim.sim_runner.get_current_cycle() - start_cycle;
+                    sim.sim_runner
+                        .get_shared_status_mut()
+                        .statistics
+                        .fast_forward_statistics
+                        .add_detailed_round(sim.total_rounds, cycles);
                     sim.total_rounds += 1;
                     if sim.total_rounds % 1000 == 0 {
                         tracing::info!("total rounds: {}", sim.total_rounds);
@@ -192,36 +322,220 @@ impl Simulator {
     pub extern "C" fn run_full_expr(task: *mut SataccMinisatTask) -> bool {
         tracing_subscriber::fmt::try_init().unwrap_or_default();
         let task = unsafe { &mut *task };
-        let config = Config {
-            init_running_mode: RunMode::RealRoundGap,
-            ..Config::from_config_file("satacc_config.toml").unwrap()
-        };
-
-        let simulator = Self::new_from_config(config.clone());
-        let (task_sender, comp) = simulator.build(config.init_running_mode);
-        let shared_status = SataccStatus::new(config);
+        if task.check_task() != 0 {
+            return false;
+        }
+        let config = Config::from_config_file("satacc_config.toml").unwrap();
+        match Self::new_from_config(config).run_task(task) {
+            Ok((status, cycle)) => {
+                tracing::info!("simulator finished! total cycles: {cycle}");
+                status.save_statistics("statistics.json");
+                serde_json::to_writer_pretty(File::create("cycle.json").unwrap(), &cycle).unwrap();
+                true
+            }
+            Err(e) => {
+                tracing::error!("simulation error: {e:?}");
+                false
+            }
+        }
+    }
+    /// run all the rounds of the task from the start, return the status and the total cycles
+    /// - all the rounds are checked before the simulation, see [`validate_task`]
+    pub fn run_task(&self, task: &mut SataccMinisatTask) -> eyre::Result<(SataccStatus, usize)> {
+        ensure_valid(&validate_task(task))?;
+        self.run_source(task)
+    }
+    /// run the rounds of the source one by one, the next round is taken after the last one is drained
+    /// - the rounds out of the detailed windows of the `fast_forward_config` only warm up the caches
+    /// - the source is checked round by round, [`Simulator::run_task`] and [`Simulator::run_trace`] check the whole input ahead
+    pub fn run_source(&self, source: &mut impl TaskSource) -> eyre::Result<(SataccStatus, usize)> {
+        let fast_forward_config = self.config.fast_forward_config;
+        let status = self.run_rounds(
+            source,
+            |round| fast_forward_config.is_detailed(round),
+            |_, _| {},
+        )?;
+        let cycle = status.statistics.total_cycle;
+        Ok((status, cycle))
+    }
+    /// simulate the rounds that are `detailed`, the other rounds only warm up the caches,
+    /// `on_detailed` gets the index and the cycles of each detailed round
+    fn run_rounds(
+        &self,
+        source: &mut impl TaskSource,
+        mut detailed: impl FnMut(usize) -> bool,
+        mut on_detailed: impl FnMut(usize, usize),
+    ) -> eyre::Result<SataccStatus> {
+        self.config.validate()?;
+        let (task_sender, comp) = self.build(RunMode::RealRoundGap);
+        let shared_status = SataccStatus::new(self.config.clone());
         let mut sim_runner = SimRunner::new(comp, shared_status);
-        while let Some(single_round_task) = task.pop_next_task() {
-            task_sender.send(single_round_task).unwrap_or_else(|_e| {
-                panic!("cannot send task!");
-            });
-            match sim_runner.run() {
-                Ok(_) => {}
-                Err(_) => {
-                    tracing::error!("simulation error!");
-                    return false;
+        let mut round_index = 0;
+        while let Some(single_round_task) = source.next_round()? {
+            ensure_valid(&validate_round(round_index, &single_round_task))?;
+            if detailed(round_index) {
+                let start_cycle = sim_runner.get_current_cycle();
+                if task_sender.send(single_round_task).is_err() {
+                    bail!("cannot send the round {round_index} to the trail");
                 }
+                sim_runner.run()?;
+                let cycles = sim_runner.get_current_cycle() - start_cycle;
+                sim_runner
+                    .get_shared_status_mut()
+                    .statistics
+                    .fast_forward_statistics
+                    .add_detailed_round(round_index, cycles);
+                on_detailed(round_index, cycles);
+            } else {
+                let (sim, status) = sim_runner.get_sim_and_status_mut();
+                sim.warm_up(&single_round_task, status);
+                status
+                    .statistics
+                    .fast_forward_statistics
+                    .fast_forward_rounds += 1;
             }
+            round_index += 1;
         }
-        tracing::info!(
-            "simulator finished! total cycles: {}",
-            sim_runner.get_current_cycle(),
-        );
         let (_, mut status, cycle) = sim_runner.into_inner();
         status.statistics.total_cycle = cycle;
-        status.save_statistics("statistics.json");
-        serde_json::to_writer_pretty(File::create("cycle.json").unwrap(), &cycle).unwrap();
-        return true;
+        Ok(status)
+    }
+    /// replay the trace file recorded by [`SataccMinisatTask::start_trace`], the rounds are read lazily
+    /// - the whole trace is checked before the simulation, see [`validate_trace`]
+    pub fn run_trace(&self, path: &str) -> eyre::Result<(SataccStatus, usize)> {
+        ensure_valid(&validate_trace(path)?)?;
+        self.run_source(&mut TraceReader::open(path)?)
+    }
+    /// replay the trace file with `satacc_config.toml`, the rounds are read lazily so the memory is bounded
+    #[no_mangle]
+    pub extern "C" fn run_trace_expr(path: *const c_char) -> bool {
+        tracing_subscriber::fmt::try_init().unwrap_or_default();
+        match path_from_c(path).and_then(|path| {
+            let config = Config::from_config_file("satacc_config.toml")?;
+            Self::new_from_config(config).run_trace(path)
+        }) {
+            Ok((status, cycle)) => {
+                tracing::info!("simulator finished! total cycles: {cycle}");
+                status.save_statistics("statistics.json");
+                serde_json::to_writer_pretty(File::create("cycle.json").unwrap(), &cycle).unwrap();
+                true
+            }
+            Err(e) => {
+                tracing::error!("simulation error: {e:?}");
+                false
+            }
+        }
+    }
+    /// solve the cnf file and simulate the rounds while the solver runs, the data is placed by the `layout_config`
+    /// - the rounds are checked one by one like [`Simulator::run_source`]
+    pub fn run_cnf(
+        &self,
+        path: &str,
+        limits: SolveLimits,
+    ) -> eyre::Result<(SataccStatus, usize, SolveResult)> {
+        let layout = MemoryLayout::new(self.config.layout_config, self.config.n_watchers);
+        let mut source = SolverTaskSource::from_file(path, layout, limits)?;
+        let (status, cycle) = self.run_source(&mut source)?;
+        let result = source.result().expect("the source is drained");
+        Ok((status, cycle, result))
+    }
+    /// solve the cnf file with the built-in solver and simulate it with `satacc_config.toml`, no trace is needed
+    /// - the search stops after `max_rounds` propagation rounds, 0 means no limit
+    #[no_mangle]
+    pub extern "C" fn run_cnf_expr(path: *const c_char, max_rounds: usize) -> bool {
+        tracing_subscriber::fmt::try_init().unwrap_or_default();
+        let limits = SolveLimits {
+            max_rounds: match max_rounds {
+                0 => usize::MAX,
+                max_rounds => max_rounds,
+            },
+            ..Default::default()
+        };
+        match path_from_c(path).and_then(|path| {
+            let config = Config::from_config_file("satacc_config.toml")?;
+            Self::new_from_config(config).run_cnf(path, limits)
+        }) {
+            Ok((status, cycle, result)) => {
+                tracing::info!("simulator finished! result: {result:?}, total cycles: {cycle}");
+                status.save_statistics("statistics.json");
+                serde_json::to_writer_pretty(File::create("cycle.json").unwrap(), &cycle).unwrap();
+                true
+            }
+            Err(e) => {
+                tracing::error!("simulation error: {e:?}");
+                false
+            }
+        }
+    }
+    /// simulate the rounds of the plan in detail, the other rounds only warm up the caches
+    /// - the statistics and the total cycle of the status only count the detailed rounds
+    /// - the plan decides the detailed rounds, the `fast_forward_config` is not used
+    pub fn run_sampled_source(
+        &self,
+        source: &mut impl TaskSource,
+        plan: &SamplingPlan,
+    ) -> eyre::Result<(SataccStatus, SampledEstimate)> {
+        let mut cycles = vec![vec![]; plan.samples.len()];
+        let status = self.run_rounds(
+            source,
+            |round| plan.is_detailed(round),
+            |round, round_cycle| cycles[plan.clusters[round]].push(round_cycle),
+        )?;
+        let stat = &status.statistics.fast_forward_statistics;
+        let total_rounds = stat.fast_forward_rounds + stat.detailed_rounds;
+        if total_rounds != plan.total_rounds() {
+            bail!(
+                "the source has {total_rounds} rounds but the plan has {}",
+                plan.total_rounds()
+            );
+        }
+        Ok((status, SampledEstimate::new(plan, &cycles)))
+    }
+    /// cluster the rounds of the task by the `sampling_config` and simulate the samples, see [`SamplingPlan`]
+    pub fn run_sampled(
+        &self,
+        task: &mut SataccMinisatTask,
+    ) -> eyre::Result<(SataccStatus, SampledEstimate)> {
+        ensure_valid(&validate_task(task))?;
+        let features = task
+            .decisions
+            .iter()
+            .map(RoundFeatures::from_round)
+            .collect::<Vec<_>>();
+        let plan = SamplingPlan::new(&features, &self.config.sampling_config);
+        self.run_sampled_source(task, &plan)
+    }
+    /// the trace is read three times, for the check, for the features of the rounds, then for the simulation
+    pub fn run_trace_sampled(&self, path: &str) -> eyre::Result<(SataccStatus, SampledEstimate)> {
+        ensure_valid(&validate_trace(path)?)?;
+        let features = round_features(&mut TraceReader::open(path)?)?;
+        let plan = SamplingPlan::new(&features, &self.config.sampling_config);
+        self.run_sampled_source(&mut TraceReader::open(path)?, &plan)
+    }
+    /// the sampled simulation of the trace file with `satacc_config.toml`, the estimate is saved to `sampled_cycle.json`
+    #[no_mangle]
+    pub extern "C" fn run_trace_sampled_expr(path: *const c_char) -> bool {
+        tracing_subscriber::fmt::try_init().unwrap_or_default();
+        match path_from_c(path)
+            .and_then(|path| {
+                let config = Config::from_config_file("satacc_config.toml")?;
+                Self::new_from_config(config).run_trace_sampled(path)
+            })
+            .and_then(|(status, estimate)| {
+                tracing::info!(
+                    "sampled simulation finished! estimated cycles: {} +- {}",
+                    estimate.estimated_cycles,
+                    estimate.confidence_interval
+                );
+                status.save_statistics("statistics.json");
+                estimate.save("sampled_cycle.json")
+            }) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::error!("simulation error: {e:?}");
+                false
+            }
+        }
     }
     /// build the simulator
     pub fn build(&self, init_runing_mode: RunMode) -> (SimSender<SingleRoundTask>, TrailAndOthers) {
@@ -237,17 +551,63 @@ impl Simulator {
             outer_to_trail_ports.1,
             self.config.level_sync,
             self.config.n_watchers,
-        );
+        )
+        .with_functional(self.config.functional_config.enable);
+        // the coherence unit sends the value writes of the trail to the private caches
+        let coherence_config = self.config.coherence_config;
+        let (trail, coherence_unit, coherence_receivers) = match coherence_config.enabled() {
+            true => {
+                let (value_write_sender, value_write_receiver) =
+                    channel_builder.sim_channel(self.config.channel_size);
+                let (tile_senders, tile_receivers) = channel_builder
+                    .sim_channel_array(self.config.channel_size, self.config.n_watchers);
+                let coherence_unit = CoherenceUnit::new(
+                    coherence_config,
+                    self.config.private_cache_config.block_size,
+                    value_write_receiver,
+                    tile_senders,
+                );
+                (
+                    trail.with_value_write_sender(value_write_sender),
+                    Some(coherence_unit),
+                    tile_receivers.into_iter().map(Some).collect(),
+                )
+            }
+            false => (
+                trail,
+                None,
+                (0..self.config.n_watchers)
+                    .map(|_| None)
+                    .collect::<Vec<_>>(),
+            ),
+        };
 
         // build the icnt from pe to cache
-        let num_caches = 8;
+        let nuca_config = self.config.nuca_config;
+        let num_caches = nuca_config.num_partitions(self.config.n_watchers);
+        // the scratchpad has the same number of ports as the l3 cache
+        let num_scratchpad_ports = if self.config.scratchpad_config.enable {
+            NUM_MEM_PARTITIONS
+        } else {
+            0
+        };
         let ideal_icnt = self.config.ideal_icnt;
         let (mem_icnt, cache_base_ports) = SimpleIcnt::<IcntMsgWrapper<MemReq>>::new_with_config(
-            self.config.n_watchers + num_caches,
+            self.config.n_watchers + num_caches + num_scratchpad_ports,
             self.config.channel_size,
             &channel_builder,
             ideal_icnt,
         );
+        // each l3 slice sits at the node of its watcher, the scratchpad ports are after the watchers
+        let mem_icnt = match nuca_config.enable {
+            true => mem_icnt.with_port_nodes(
+                (0..self.config.n_watchers)
+                    .chain(0..num_caches)
+                    .chain((0..num_scratchpad_ports).map(|port| self.config.n_watchers + port))
+                    .collect(),
+            ),
+            false => mem_icnt,
+        };
 
         // first build the icnt from watchers to clauses
 
@@ -263,67 +623,155 @@ impl Simulator {
         } else {
             self.config.miss_latency
         };
+        let watcher_interface_config = WatcherInterfaceConfig {
+            queue_size: self.config.channel_size,
+            cache_config: self.config.private_cache_config.clone(),
+            hit_latency: self.config.l1_hit_latency,
+            private_cache_miss_latency, // the miss latency for l1 is the hit latency for l3
+            num_clauses_per_watcher: self.config.n_clauses,
+            total_watchers: self.config.n_watchers,
+            pipeline_clause_value_read: self.config.pipeline_clause_value_read,
+            watcher_pipeline: self.config.watcher_pipeline,
+            clause_pipeline: self.config.clause_pipeline,
+        };
         // build watchers and clauses
         let watchers_interface = clause_base_port
             .into_iter()
             .zip(trail_to_watcher_ports.1)
             .zip(cache_base_ports.iter().take(self.config.n_watchers))
+            .zip(coherence_receivers)
             .enumerate()
-            .map(|(watcher_pe_id, ((icnt_port, trail_port), cache_port))| {
-                WatcherInterface::new(
-                    cache_port.clone(),
-                    icnt_port,
-                    trail_port,
+            .map(
+                |(watcher_pe_id, (((icnt_port, trail_port), cache_port), coherence_receiver))| {
+                    let watcher_interface = WatcherInterface::new(
+                        cache_port.clone(),
+                        icnt_port,
+                        trail_port,
+                        &channel_builder,
+                        watcher_pe_id,
+                        &watcher_interface_config,
+                    );
+                    match coherence_receiver {
+                        Some(coherence_receiver) => {
+                            watcher_interface.with_coherence_receiver(coherence_receiver)
+                        }
+                        None => watcher_interface,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        // build the caches
+        let l3_cache_ports = cache_base_ports
+            .iter()
+            .skip(self.config.n_watchers)
+            .take(num_caches)
+            .cloned()
+            .collect::<Vec<_>>();
+        // the profiler sits between the icnt and the l3 cache
+        let (mem_profiler, l3_cache_ports) = match self.config.mem_profiler {
+            true => {
+                let (mem_profiler, l3_cache_ports) = MemProfiler::new(
+                    l3_cache_ports,
                     &channel_builder,
                     self.config.channel_size,
-                    &self.config.private_cache_config,
-                    self.config.l1_hit_latency,
-                    private_cache_miss_latency, // the miss latency for l1 is the hit latency for l3
-                    self.config.n_clauses,
-                    watcher_pe_id,
-                    self.config.n_watchers,
-                    self.config.pipeline_clause_value_read,
+                    self.config.l3_cache_config.block_size,
+                    self.config.mem_profiler_window,
+                );
+                (Some(mem_profiler), l3_cache_ports)
+            }
+            false => (None, l3_cache_ports),
+        };
+        let shared_l3_cache: Box<dyn L3Cache> = match self.config.l3_cache_type {
+            crate::config::CacheType::Simple if nuca_config.enable => {
+                // split the sets into the slices, each slice has one port and serves one partition
+                let slice_config = CacheConfig {
+                    sets: self.config.l3_cache_config.sets / num_caches as u64,
+                    channels: 1,
+                    ..self.config.l3_cache_config.clone()
+                };
+                let slices = l3_cache_ports
+                    .into_iter()
+                    .map(|port| {
+                        CacheWithFixTime::new(
+                            &slice_config,
+                            vec![port],
+                            self.config.l3_hit_latency,
+                            self.config.miss_latency,
+                            CacheId::L3Cache,
+                            1,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                Box::new(slices)
+            }
+            _ if nuca_config.enable => {
+                unreachable!(
+                    "the nuca l3 cache only supports the Simple cache type, see Config::validate"
                 )
-            })
-            .collect::<Vec<_>>();
+            }
+            crate::config::CacheType::Simple => {
+                let cache = CacheWithFixTime::new(
+                    &self.config.l3_cache_config,
+                    l3_cache_ports,
+                    self.config.l3_hit_latency,
+                    self.config.miss_latency,
+                    CacheId::L3Cache,
+                    num_caches,
+                );
 
-        // build the caches
-        let shared_l3_cache: Box<dyn SimComponent<SharedStatus = SataccStatus>> =
-            match self.config.l3_cache_type {
-                crate::config::CacheType::Simple => {
-                    let cache = CacheWithFixTime::new(
-                        &self.config.l3_cache_config,
-                        cache_base_ports
-                            .iter()
-                            .skip(self.config.n_watchers)
-                            .cloned()
-                            .collect(),
-                        self.config.l3_hit_latency,
-                        self.config.miss_latency,
-                        CacheId::L3Cache,
-                    );
+                Box::new(cache)
+            }
+            #[cfg(feature = "ramulator")]
+            crate::config::CacheType::Ramu => {
+                let cache = CacheWithRamulator::new(
+                    &self.config.l3_cache_config,
+                    l3_cache_ports,
+                    self.config.ramu_cache_config,
+                    self.config.l3_hit_latency,
+                    CacheId::L3Cache,
+                );
 
-                    Box::new(cache)
-                }
-                crate::config::CacheType::Ramu => {
-                    let cache = CacheWithRamulator::new(
-                        &self.config.l3_cache_config,
-                        cache_base_ports
-                            .iter()
-                            .skip(self.config.n_watchers)
-                            .cloned()
-                            .collect(),
-                        self.config.ramu_cache_config,
-                        self.config.l3_hit_latency,
-                        CacheId::L3Cache,
-                    );
+                Box::new(cache)
+            }
+            #[cfg(not(feature = "ramulator"))]
+            crate::config::CacheType::Ramu => {
+                unreachable!(
+                    "the Ramu cache type needs the ramulator feature, see Config::validate"
+                )
+            }
+            crate::config::CacheType::Dram => {
+                let cache = CacheWithRamulator::new_with_dram(
+                    &self.config.l3_cache_config,
+                    l3_cache_ports,
+                    DramModel::new(DramTiming::from_dram_type(&self.config.dram_config)),
+                    self.config.l3_hit_latency,
+                    CacheId::L3Cache,
+                );
 
-                    Box::new(cache)
-                }
-            };
+                Box::new(cache)
+            }
+        };
+        let scratchpad = Scratchpad::new(
+            &self.config.scratchpad_config,
+            cache_base_ports
+                .iter()
+                .skip(self.config.n_watchers + num_caches)
+                .cloned()
+                .collect(),
+        );
         let simulator = TrailAndOthers {
             trail,
-            others: (watchers_interface, mem_icnt, clause_icnt, shared_l3_cache),
+            others: (
+                watchers_interface,
+                mem_icnt,
+                clause_icnt,
+                mem_profiler,
+                shared_l3_cache,
+                scratchpad,
+                coherence_unit,
+            ),
             current_running_mode: init_runing_mode,
         };
 
@@ -335,10 +783,10 @@ impl Simulator {
 mod test {
 
     use crate::{
-        config::Config,
+        config::{CacheType, Config},
         satacc::{
             satacc_minisat_task::{ClauseData, ClauseTask, SingleRoundTask, WatcherTask},
-            SataccMinisatTask, SataccStatus,
+            CoherenceProtocol, MemReqKind, SataccMinisatTask, SataccStatus, TraceWriter,
         },
         sim::SimRunner,
         test_utils,
@@ -356,27 +804,7 @@ mod test {
         let status = SataccStatus::new(config);
         let mut sim_runner = SimRunner::new(comp, status);
         task_sender
-            .send(SingleRoundTask {
-                assignments: [WatcherTask {
-                    level: 0,
-                    meta_data_addr: 0,
-                    watcher_addr: 100,
-                    watcher_id: 1,
-                    single_watcher_tasks: [ClauseTask {
-                        watcher_id: 1,
-                        blocker_addr: 1000,
-                        clause_data: Some(ClauseData {
-                            clause_id: 1,
-                            clause_addr: 2000,
-                            clause_processing_time: 200,
-                            clause_value_addr: [3000, 4000, 5000].into(),
-                            clause_value_id: [1, 2, 3].into(),
-                        }),
-                    }]
-                    .into(),
-                }]
-                .into(),
-            })
+            .send(test_utils::single_clause_round(200, &[3000, 4000, 5000]))
             .unwrap_or_else(|_| {});
         sim_runner.run().unwrap();
     }
@@ -433,6 +861,7 @@ mod test {
                         meta_data_addr: 0,
                         watcher_addr: 100,
                         watcher_id: 1,
+                        value_addr: None,
                         single_watcher_tasks: [
                             ClauseTask {
                                 watcher_id: 1,
@@ -440,9 +869,10 @@ mod test {
                                 clause_data: Some(ClauseData {
                                     clause_id: 1,
                                     clause_addr: 2000,
-                                    clause_processing_time: 200,
+                                    clause_processing_time: Some(200),
                                     clause_value_addr: [3000, 4000, 5000].into(),
                                     clause_value_id: [1, 2, 3].into(),
+                                    ..Default::default()
                                 }),
                             },
                             ClauseTask {
@@ -458,15 +888,17 @@ mod test {
                         meta_data_addr: 0,
                         watcher_addr: 100,
                         watcher_id: 1,
+                        value_addr: None,
                         single_watcher_tasks: [ClauseTask {
                             watcher_id: 1,
                             blocker_addr: 1000,
                             clause_data: Some(ClauseData {
                                 clause_id: 1,
                                 clause_addr: 2000,
-                                clause_processing_time: 200,
+                                clause_processing_time: Some(200),
                                 clause_value_addr: [3000, 4000, 5000].into(),
                                 clause_value_id: [1, 2, 3].into(),
+                                ..Default::default()
                             }),
                         }]
                         .into(),
@@ -476,15 +908,17 @@ mod test {
                         meta_data_addr: 0,
                         watcher_addr: 100,
                         watcher_id: 1,
+                        value_addr: None,
                         single_watcher_tasks: [ClauseTask {
                             watcher_id: 1,
                             blocker_addr: 1000,
                             clause_data: Some(ClauseData {
                                 clause_id: 1,
                                 clause_addr: 2000,
-                                clause_processing_time: 200,
+                                clause_processing_time: Some(200),
                                 clause_value_addr: [3000, 4000, 5000].into(),
                                 clause_value_id: [1, 2, 3].into(),
+                                ..Default::default()
                             }),
                         }]
                         .into(),
@@ -495,4 +929,195 @@ mod test {
             .unwrap_or_else(|_| {});
         sim_runner.run().unwrap();
     }
+
+    #[test]
+    fn test_simulator_scratchpad() {
+        test_utils::init();
+
+        let mut config = Config::default();
+        config.scratchpad_config.enable = true;
+        config.scratchpad_config.capacity = 4096;
+
+        let simulator = Simulator::new_from_config(config.clone());
+        let (task_sender, comp) = simulator.build(config.init_running_mode);
+        let status = SataccStatus::new(config);
+        let mut sim_runner = SimRunner::new(comp, status);
+        task_sender
+            .send(test_utils::single_clause_round(200, &[3000, 4000, 5000]))
+            .unwrap_or_else(|_| {});
+        sim_runner.run().unwrap();
+        let stat = &sim_runner
+            .get_shared_status_mut()
+            .statistics
+            .scratchpad_statistics;
+        // the value at 5000 is out of the scratchpad
+        assert_eq!(stat.accesses, 2);
+        assert_eq!(stat.out_of_range, 1);
+    }
+
+    #[test]
+    fn test_simulator_mem_profiler() {
+        test_utils::init();
+
+        let config = Config {
+            mem_profiler: true,
+            ..Default::default()
+        };
+        let simulator = Simulator::new_from_config(config.clone());
+        let (task_sender, comp) = simulator.build(config.init_running_mode);
+        let status = SataccStatus::new(config);
+        let mut sim_runner = SimRunner::new(comp, status);
+        task_sender
+            .send(test_utils::single_clause_round(200, &[3000, 4000, 3000]))
+            .unwrap_or_else(|_| {});
+        sim_runner.run().unwrap();
+        let profile = &sim_runner.get_shared_status().statistics.mem_profile;
+        // meta data, watcher data, blocker, clause data and three values
+        assert_eq!(profile.all.accesses, 7);
+        let value_profile = &profile.per_kind[&MemReqKind::ClauseValue];
+        assert_eq!(value_profile.cold_accesses, 2);
+        assert_eq!(
+            value_profile.stack_distance_histogram.iter().sum::<usize>(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_simulator_dram_model() {
+        test_utils::init();
+
+        let config = Config {
+            l3_cache_type: CacheType::Dram,
+            ..Default::default()
+        };
+        let simulator = Simulator::new_from_config(config.clone());
+        let (task_sender, comp) = simulator.build(config.init_running_mode);
+        let status = SataccStatus::new(config);
+        let mut sim_runner = SimRunner::new(comp, status);
+        task_sender
+            .send(test_utils::single_clause_round(200, &[3000, 4000, 5000]))
+            .unwrap_or_else(|_| {});
+        sim_runner.run().unwrap();
+        let stat = &sim_runner.get_shared_status().statistics;
+        let dram_stat = &stat.dram_statistics;
+        assert_eq!(
+            dram_stat.row_hits + dram_stat.row_misses + dram_stat.row_conflicts,
+            stat.l3_cache_statistics
+                .per_kind
+                .values()
+                .map(|kind_stat| kind_stat.misses)
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_simulator_nuca() {
+        test_utils::init();
+
+        let mut config = Config::default();
+        config.nuca_config.enable = true;
+        config.nuca_config.local_watch_lists = true;
+        config.scratchpad_config.enable = true;
+        config.scratchpad_config.capacity = 4096;
+
+        let simulator = Simulator::new_from_config(config.clone());
+        let (task_sender, comp) = simulator.build(config.init_running_mode);
+        let status = SataccStatus::new(config);
+        let mut sim_runner = SimRunner::new(comp, status);
+        task_sender
+            .send(test_utils::single_clause_round(200, &[3000, 4000, 5000]))
+            .unwrap_or_else(|_| {});
+        sim_runner.run().unwrap();
+        let stat = &sim_runner.get_shared_status_mut().statistics;
+        // the watch list and the blocker are in the local slice, the clause and the value at 5000 are not
+        assert_eq!(stat.nuca_statistics.local_reqs, 3);
+        assert_eq!(stat.nuca_statistics.remote_reqs, 2);
+        assert_eq!(stat.l3_cache_statistics.cache_misses, 5);
+        assert_eq!(stat.scratchpad_statistics.accesses, 2);
+    }
+
+    #[test]
+    fn test_simulator_value_coherence() {
+        test_utils::init();
+
+        let mut config = Config::default();
+        config.coherence_config.private_value_cache = true;
+        config.coherence_config.protocol = CoherenceProtocol::DirectoryInvalidate;
+
+        let simulator = Simulator::new_from_config(config.clone());
+        let (task_sender, comp) = simulator.build(config.init_running_mode);
+        let status = SataccStatus::new(config);
+        let mut sim_runner = SimRunner::new(comp, status);
+        // the values are read in the private cache of tile 0
+        task_sender
+            .send(test_utils::single_clause_round(20, &[3000, 4000]))
+            .unwrap_or_else(|_| {});
+        sim_runner.run().unwrap();
+        let stat = &sim_runner.get_shared_status_mut().statistics;
+        assert_eq!(
+            stat.private_cache_statistics[0].per_kind[&MemReqKind::ClauseValue].misses,
+            2
+        );
+        // the next assignment writes the value at 3000, its line in tile 0 is invalidated
+        task_sender
+            .send(SingleRoundTask {
+                assignments: [WatcherTask {
+                    level: 0,
+                    meta_data_addr: 0,
+                    watcher_addr: 100,
+                    watcher_id: 3,
+                    value_addr: Some(3000),
+                    single_watcher_tasks: [].into(),
+                }]
+                .into(),
+            })
+            .unwrap_or_else(|_| {});
+        sim_runner.run().unwrap();
+        let status = sim_runner.get_shared_status_mut();
+        let stat = &status.statistics.coherence_statistics;
+        assert_eq!(stat.value_writes, 1);
+        assert_eq!(stat.invalidations, 1);
+        assert_eq!(stat.invalidated_lines, 1);
+        assert!(status
+            .value_directory
+            .take_sharers(3000 / 64 * 64)
+            .is_empty());
+        assert_eq!(
+            status.value_directory.take_sharers(4000 / 64 * 64),
+            [0].into()
+        );
+    }
+
+    #[test]
+    fn test_run_trace() {
+        test_utils::init();
+        let path = "test_run_trace.trace";
+        let mut task = SataccMinisatTask::new();
+        task.start_new_assgin();
+        task.add_watcher_task(0, 0, 100, 1);
+        task.add_single_watcher_task(1000, 2000, 1, 20, 1);
+        task.add_single_watcher_clause_value_addr(3000, 1);
+        let mut trace_writer = TraceWriter::create(path).unwrap();
+        trace_writer.write_task(&task).unwrap();
+        trace_writer.finish().unwrap();
+
+        let simulator = Simulator::new_from_config(Config::default());
+        let (status, cycle) = simulator.run_trace(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let (replayed_status, replayed_cycle) = simulator.run_task(&mut task).unwrap();
+        assert_eq!(cycle, replayed_cycle);
+        assert_eq!(
+            status.statistics.l3_cache_statistics.cache_misses,
+            replayed_status.statistics.l3_cache_statistics.cache_misses
+        );
+        assert_eq!(status.statistics.average_clauses.total, 1);
+    }
+
+    #[test]
+    fn test_invalid_trace_expr() {
+        test_utils::init();
+        assert!(!Simulator::run_trace_expr(std::ptr::null()));
+        assert!(!Simulator::run_trace_sampled_expr(std::ptr::null()));
+        assert!(!Simulator::run_trace_expr(c"missing.trace".as_ptr()));
+    }
 }
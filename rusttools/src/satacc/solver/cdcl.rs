@@ -0,0 +1,687 @@
+use eyre::Result;
+
+use crate::satacc::{
+    layout::MemoryLayout,
+    satacc_minisat_task::{SataccMinisatTask, SingleRoundTask},
+    task_source::TaskSource,
+};
+
+use super::{
+    dimacs::{Cnf, Lit},
+    heap::VarHeap,
+};
+
+/// a watcher in the watch list of a literal, the blocker is another literal of the clause
+#[derive(Debug, Clone, Copy)]
+struct Watcher {
+    clause: usize,
+    blocker: Lit,
+}
+
+#[derive(Debug, Clone)]
+struct Clause {
+    /// the first two literals are watched
+    lits: Vec<Lit>,
+    addr: u64,
+}
+
+/// stop the search early, the tasks of a large cnf can be too many to simulate
+#[derive(Debug, Clone, Copy)]
+pub struct SolveLimits {
+    pub max_conflicts: usize,
+    /// the number of propagation rounds, each round is a [`crate::satacc::satacc_minisat_task::SingleRoundTask`]
+    pub max_rounds: usize,
+}
+impl Default for SolveLimits {
+    fn default() -> Self {
+        Self {
+            max_conflicts: usize::MAX,
+            max_rounds: usize::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveResult {
+    Sat,
+    Unsat,
+    /// a limit is reached
+    Unknown,
+}
+
+/// # Solver
+/// a small cdcl solver with two watched literals, it works like minisat without restarts and clause deletion
+/// - each propagation emits a round of tasks, the addresses come from the [`MemoryLayout`]
+/// - the watch list of a literal holds the clauses that watch its negation, like minisat
+pub struct Solver {
+    clauses: Vec<Clause>,
+    watches: Vec<Vec<Watcher>>,
+    assigns: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    activity: Vec<f64>,
+    var_inc: f64,
+    /// the unassigned variables by the activity, the assigned ones are removed lazily
+    order: VarHeap,
+    /// the saved phase, true for negative
+    polarity: Vec<bool>,
+    seen: Vec<bool>,
+    layout: MemoryLayout,
+    /// false if a conflict is found at level 0
+    ok: bool,
+    pub rounds: usize,
+    pub conflicts: usize,
+}
+
+impl Solver {
+    /// fails if the clauses don't fit in the layout
+    pub fn new(cnf: &Cnf, layout: MemoryLayout) -> Result<Self> {
+        let num_vars = cnf.num_vars;
+        let mut solver = Self {
+            clauses: vec![],
+            watches: vec![vec![]; num_vars * 2],
+            assigns: vec![None; num_vars],
+            levels: vec![0; num_vars],
+            reasons: vec![None; num_vars],
+            trail: vec![],
+            trail_lim: vec![],
+            qhead: 0,
+            activity: vec![0.; num_vars],
+            var_inc: 1.,
+            order: VarHeap::new(num_vars),
+            polarity: vec![true; num_vars],
+            seen: vec![false; num_vars],
+            layout,
+            ok: true,
+            rounds: 0,
+            conflicts: 0,
+        };
+        for clause in &cnf.clauses {
+            solver.add_clause(clause)?;
+        }
+        Ok(solver)
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.assigns[lit.var()].map(|value| value != lit.is_negative())
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn add_clause(&mut self, clause: &[Lit]) -> Result<()> {
+        if !self.ok {
+            return Ok(());
+        }
+        let mut lits = clause.to_vec();
+        lits.sort();
+        lits.dedup();
+        // a tautology is always true
+        if lits.windows(2).any(|w| w[0] == !w[1]) {
+            return Ok(());
+        }
+        match lits[..] {
+            [] => self.ok = false,
+            [lit] => match self.value(lit) {
+                Some(false) => self.ok = false,
+                Some(true) => {}
+                None => self.enqueue(lit, None),
+            },
+            _ => {
+                self.attach(lits)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// allocate the clause and watch its first two literals
+    fn attach(&mut self, lits: Vec<Lit>) -> Result<usize> {
+        let clause = self.clauses.len();
+        let addr = self.layout.alloc_clause(lits.len(), (!lits[0]).index())?;
+        self.push_watcher(
+            !lits[0],
+            Watcher {
+                clause,
+                blocker: lits[1],
+            },
+        )?;
+        self.push_watcher(
+            !lits[1],
+            Watcher {
+                clause,
+                blocker: lits[0],
+            },
+        )?;
+        self.clauses.push(Clause { lits, addr });
+        Ok(clause)
+    }
+
+    fn push_watcher(&mut self, lit: Lit, watcher: Watcher) -> Result<()> {
+        self.watches[lit.index()].push(watcher);
+        self.layout
+            .reserve_watchers(lit.index(), self.watches[lit.index()].len())
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        self.assigns[lit.var()] = Some(!lit.is_negative());
+        self.levels[lit.var()] = self.decision_level();
+        self.reasons[lit.var()] = reason;
+        self.trail.push(lit);
+    }
+
+    /// propagate the trail and emit the round, return the conflict clause
+    fn propagate(&mut self, task: &mut SataccMinisatTask) -> Result<Option<usize>> {
+        if self.qhead == self.trail.len() {
+            return Ok(None);
+        }
+        task.start_new_assgin();
+        self.rounds += 1;
+        // the depth of the literals in the implication graph, it's the level of the watcher tasks
+        let mut depths = vec![0; self.trail.len() - self.qhead];
+        let first = self.qhead;
+        let mut conflict = None;
+        while self.qhead < self.trail.len() && conflict.is_none() {
+            let p = self.trail[self.qhead];
+            let depth = depths[self.qhead - first];
+            self.qhead += 1;
+            task.add_watcher_task(
+                depth,
+                self.layout.watch_meta_addr(p.index()),
+                self.layout.watch_data_addr(p.index()),
+                p.index(),
+            );
+            task.set_assignment_value_addr(self.layout.value_addr(p.var()));
+            let false_lit = !p;
+            let watchers = std::mem::take(&mut self.watches[p.index()]);
+            let mut kept = Vec::with_capacity(watchers.len());
+            let mut remaining = watchers.into_iter();
+            for mut watcher in remaining.by_ref() {
+                let blocker_addr = self.layout.value_addr(watcher.blocker.var());
+                if self.value(watcher.blocker) == Some(true) {
+                    task.add_single_watcher_task_no_clause(blocker_addr, p.index());
+                    kept.push(watcher);
+                    continue;
+                }
+                let clause = &mut self.clauses[watcher.clause];
+                if clause.lits[0] == false_lit {
+                    clause.lits.swap(0, 1);
+                }
+                let first_lit = clause.lits[0];
+                let mut inspected = vec![first_lit];
+                let mut new_watch = None;
+                if first_lit == watcher.blocker
+                    || self.assigns[first_lit.var()] != Some(!first_lit.is_negative())
+                {
+                    for k in 2..clause.lits.len() {
+                        let lit = clause.lits[k];
+                        inspected.push(lit);
+                        if self.assigns[lit.var()] != Some(lit.is_negative()) {
+                            clause.lits.swap(1, k);
+                            new_watch = Some(!clause.lits[1]);
+                            break;
+                        }
+                    }
+                }
+                let clause_addr = clause.addr;
+                task.add_single_watcher_task(
+                    blocker_addr,
+                    clause_addr,
+                    watcher.clause,
+                    inspected.len(),
+                    p.index(),
+                );
+                for lit in inspected {
+                    task.add_single_watcher_clause_value_addr(
+                        self.layout.value_addr(lit.var()),
+                        lit.var(),
+                    );
+                }
+                // the values before the implication, for the closed-loop mode
+                for &lit in &self.clauses[watcher.clause].lits {
+                    task.add_single_watcher_clause_lit(lit.index(), self.value(lit).into());
+                }
+                watcher.blocker = first_lit;
+                if let Some(new_watch) = new_watch {
+                    self.push_watcher(new_watch, watcher)?;
+                    // the watcher is appended to the new list, the next kept watcher fills its slot
+                    let append_index = self.watches[new_watch.index()].len() - 1;
+                    task.set_clause_new_watch(
+                        new_watch.index(),
+                        self.layout.watcher_addr(new_watch.index(), append_index),
+                        self.layout.watcher_addr(p.index(), kept.len()),
+                    );
+                    continue;
+                }
+                kept.push(watcher);
+                match self.value(first_lit) {
+                    Some(true) => {}
+                    Some(false) => {
+                        task.set_clause_conflict();
+                        conflict = Some(watcher.clause);
+                        break;
+                    }
+                    None => {
+                        self.enqueue(first_lit, Some(watcher.clause));
+                        depths.push(depth + 1);
+                    }
+                }
+            }
+            kept.extend(remaining);
+            self.watches[p.index()] = kept;
+        }
+        if conflict.is_some() {
+            self.qhead = self.trail.len();
+        }
+        Ok(conflict)
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.var_inc;
+        // the rescale keeps the order of the heap
+        if self.activity[var] > 1e100 {
+            self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+            self.var_inc *= 1e-100;
+        }
+        self.order.increase(var, &self.activity);
+    }
+
+    /// the first uip clause and the level to backjump
+    fn analyze(&mut self, mut conflict: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![];
+        let mut path_count = 0;
+        let mut p = None;
+        let mut index = self.trail.len();
+        loop {
+            // the first literal of a reason clause is the implied literal
+            for i in p.is_some() as usize..self.clauses[conflict].lits.len() {
+                let q = self.clauses[conflict].lits[i];
+                if !self.seen[q.var()] && self.levels[q.var()] > 0 {
+                    self.bump(q.var());
+                    self.seen[q.var()] = true;
+                    if self.levels[q.var()] == self.decision_level() {
+                        path_count += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            self.seen[lit.var()] = false;
+            path_count -= 1;
+            p = Some(lit);
+            if path_count == 0 {
+                break;
+            }
+            conflict = self.reasons[lit.var()].expect("only the decision has no reason");
+        }
+        for lit in &learnt {
+            self.seen[lit.var()] = false;
+        }
+        learnt.insert(0, !p.unwrap());
+        // the literal of the backjump level is watched
+        let mut backjump_level = 0;
+        if let Some((max_index, _)) = learnt
+            .iter()
+            .enumerate()
+            .skip(1)
+            .max_by_key(|(_, lit)| self.levels[lit.var()])
+        {
+            learnt.swap(1, max_index);
+            backjump_level = self.levels[learnt[1].var()];
+        }
+        (learnt, backjump_level)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        for &lit in self.trail[self.trail_lim[level]..].iter().rev() {
+            self.assigns[lit.var()] = None;
+            self.reasons[lit.var()] = None;
+            self.polarity[lit.var()] = lit.is_negative();
+            self.order.insert(lit.var(), &self.activity);
+        }
+        self.trail.truncate(self.trail_lim[level]);
+        self.trail_lim.truncate(level);
+        self.qhead = self.trail.len();
+    }
+
+    /// the unassigned variable with the highest activity
+    fn pick_branch(&mut self) -> Option<Lit> {
+        while let Some(var) = self.order.pop(&self.activity) {
+            if self.assigns[var].is_none() {
+                return Some(Lit::new(var, self.polarity[var]));
+            }
+        }
+        None
+    }
+
+    /// search until the formula is solved or a limit is reached, the rounds are added to `task`
+    /// - fails if the learnt clauses don't fit in the layout
+    pub fn solve(
+        &mut self,
+        limits: SolveLimits,
+        task: &mut SataccMinisatTask,
+    ) -> Result<SolveResult> {
+        loop {
+            if let Some(result) = self.step(limits, task)? {
+                return Ok(result);
+            }
+        }
+    }
+
+    /// propagate and resolve the conflict, or make a decision, the round of the propagation is added to `task`
+    /// - return the result if the formula is solved or a limit is reached
+    fn step(
+        &mut self,
+        limits: SolveLimits,
+        task: &mut SataccMinisatTask,
+    ) -> Result<Option<SolveResult>> {
+        if !self.ok {
+            return Ok(Some(SolveResult::Unsat));
+        }
+        if self.rounds >= limits.max_rounds {
+            return Ok(Some(SolveResult::Unknown));
+        }
+        if let Some(conflict) = self.propagate(task)? {
+            self.conflicts += 1;
+            if self.decision_level() == 0 {
+                self.ok = false;
+                return Ok(Some(SolveResult::Unsat));
+            }
+            let (learnt, backjump_level) = self.analyze(conflict);
+            self.backtrack(backjump_level);
+            if learnt.len() == 1 {
+                self.enqueue(learnt[0], None);
+            } else {
+                let asserting = learnt[0];
+                let clause = self.attach(learnt)?;
+                self.enqueue(asserting, Some(clause));
+            }
+            self.var_inc /= 0.95;
+            if self.conflicts >= limits.max_conflicts {
+                return Ok(Some(SolveResult::Unknown));
+            }
+        } else {
+            match self.pick_branch() {
+                Some(lit) => {
+                    self.trail_lim.push(self.trail.len());
+                    self.enqueue(lit, None);
+                }
+                None => return Ok(Some(SolveResult::Sat)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// the value of each variable, only valid after [`SolveResult::Sat`]
+    pub fn model(&self) -> Vec<bool> {
+        self.assigns
+            .iter()
+            .map(|value| value.unwrap_or(false))
+            .collect()
+    }
+}
+
+/// # SolverTaskSource
+/// the rounds of the search, the solver runs until the next round is emitted, so only one round is in memory
+pub struct SolverTaskSource {
+    solver: Solver,
+    limits: SolveLimits,
+    round: SataccMinisatTask,
+    result: Option<SolveResult>,
+}
+impl SolverTaskSource {
+    pub fn new(solver: Solver, limits: SolveLimits) -> Self {
+        Self {
+            solver,
+            limits,
+            round: SataccMinisatTask::new(),
+            result: None,
+        }
+    }
+    /// parse the cnf at `path`, its data is placed by the `layout`
+    pub fn from_file(path: &str, layout: MemoryLayout, limits: SolveLimits) -> Result<Self> {
+        let cnf = Cnf::from_file(path)?;
+        Ok(Self::new(Solver::new(&cnf, layout)?, limits))
+    }
+    /// the result of the search, `None` until all the rounds are taken
+    pub fn result(&self) -> Option<SolveResult> {
+        self.result
+    }
+    pub fn solver(&self) -> &Solver {
+        &self.solver
+    }
+}
+impl TaskSource for SolverTaskSource {
+    fn next_round(&mut self) -> Result<Option<SingleRoundTask>> {
+        while self.result.is_none() {
+            self.result = self.solver.step(self.limits, &mut self.round)?;
+            if let Some(round) = self.round.pop_next_task() {
+                return Ok(Some(round));
+            }
+        }
+        Ok(self.round.pop_next_task())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        satacc::{layout::LayoutConfig, MemReqKind, Simulator},
+    };
+
+    /// the pigeonhole formula, `holes + 1` pigeons can not be put into `holes` holes
+    fn pigeonhole(holes: usize) -> Cnf {
+        let var = |pigeon: usize, hole: usize| pigeon * holes + hole + 1;
+        let mut dimacs = String::new();
+        let mut num_clauses = 0;
+        for pigeon in 0..=holes {
+            for hole in 0..holes {
+                dimacs += &format!("{} ", var(pigeon, hole));
+            }
+            dimacs += "0\n";
+            num_clauses += 1;
+        }
+        for hole in 0..holes {
+            for a in 0..=holes {
+                for b in a + 1..=holes {
+                    dimacs += &format!("-{} -{} 0\n", var(a, hole), var(b, hole));
+                    num_clauses += 1;
+                }
+            }
+        }
+        let header = format!("p cnf {} {num_clauses}\n", (holes + 1) * holes);
+        Cnf::parse((header + &dimacs).as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_solve() {
+        let cnf =
+            Cnf::parse("p cnf 4 5\n1 2 0\n-1 3 0\n-3 -2 0\n2 4 0\n-4 -1 0\n".as_bytes()).unwrap();
+        let mut task = SataccMinisatTask::new();
+        let mut solver = Solver::new(&cnf, MemoryLayout::new(LayoutConfig::default(), 1)).unwrap();
+        assert_eq!(
+            solver.solve(SolveLimits::default(), &mut task).unwrap(),
+            SolveResult::Sat
+        );
+        let model = solver.model();
+        assert!(cnf.clauses.iter().all(|clause| clause
+            .iter()
+            .any(|lit| model[lit.var()] != lit.is_negative())));
+        assert_eq!(task.decisions.len(), solver.rounds);
+
+        let mut task = SataccMinisatTask::new();
+        let mut solver = Solver::new(
+            &pigeonhole(4),
+            MemoryLayout::new(LayoutConfig::default(), 1),
+        )
+        .unwrap();
+        assert_eq!(
+            solver.solve(SolveLimits::default(), &mut task).unwrap(),
+            SolveResult::Unsat
+        );
+        assert!(solver.conflicts > 0);
+
+        let limits = SolveLimits {
+            max_conflicts: 2,
+            ..Default::default()
+        };
+        let mut solver = Solver::new(
+            &pigeonhole(4),
+            MemoryLayout::new(LayoutConfig::default(), 1),
+        )
+        .unwrap();
+        assert_eq!(
+            solver.solve(limits, &mut SataccMinisatTask::new()).unwrap(),
+            SolveResult::Unknown
+        );
+        assert_eq!(solver.conflicts, 2);
+    }
+
+    #[test]
+    fn test_emitted_tasks() {
+        let layout_config = LayoutConfig::default();
+        let mut task = SataccMinisatTask::new();
+        let limits = SolveLimits {
+            max_rounds: 20,
+            ..Default::default()
+        };
+        let mut solver = Solver::new(&pigeonhole(5), MemoryLayout::new(layout_config, 16)).unwrap();
+        let result = solver.solve(limits, &mut task).unwrap();
+        assert_eq!(result, SolveResult::Unknown);
+        assert_eq!(task.decisions.len(), 20);
+        let watcher = &task.decisions[0].assignments[0];
+        assert!(watcher.meta_data_addr >= layout_config.watch_meta_base);
+        assert!(watcher.watcher_addr >= layout_config.watch_data_base);
+        assert!(watcher.value_addr.unwrap() >= layout_config.value_base);
+        let clause_tasks = task
+            .decisions
+            .iter()
+            .flat_map(|round| &round.assignments)
+            .flat_map(|assignment| &assignment.single_watcher_tasks)
+            .filter_map(|watcher| watcher.clause_data.as_ref());
+        let mut conflicts = 0;
+        let mut new_watches = 0;
+        for clause_data in clause_tasks {
+            conflicts += clause_data.conflict as usize;
+            new_watches += clause_data.new_watch.is_some() as usize;
+            assert!(clause_data.clause_addr >= layout_config.clause_base);
+            assert_eq!(
+                Some(clause_data.clause_value_addr.len()),
+                clause_data.clause_processing_time
+            );
+        }
+        // the round stops at the conflicting clause
+        assert_eq!(conflicts, solver.conflicts);
+        let simulator = Simulator::new_from_config(Config::default());
+        let (status, cycle) = simulator.run_task(&mut task).unwrap();
+        assert!(cycle > 0);
+        assert!(status.statistics.total_cycle > 0);
+        // each new watch appends to one list and compacts another
+        assert!(new_watches > 0);
+        let writes = status
+            .statistics
+            .clause_statistics
+            .iter()
+            .flat_map(|stat| &stat.single_clause)
+            .map(|stat| stat.total_watch_list_write)
+            .sum::<usize>();
+        assert_eq!(writes, 2 * new_watches);
+        let l3_writes =
+            &status.statistics.l3_cache_statistics.per_kind[&MemReqKind::WatchListWrite];
+        assert_eq!(l3_writes.hits + l3_writes.misses, writes);
+    }
+
+    #[test]
+    fn test_closed_loop_tasks() {
+        let mut task = SataccMinisatTask::new();
+        let limits = SolveLimits {
+            max_rounds: 20,
+            ..Default::default()
+        };
+        Solver::new(
+            &pigeonhole(4),
+            MemoryLayout::new(LayoutConfig::default(), 16),
+        )
+        .unwrap()
+        .solve(limits, &mut task)
+        .unwrap();
+        let roots = task
+            .decisions
+            .iter()
+            .flat_map(|round| &round.assignments)
+            .filter(|assignment| assignment.level == 0)
+            .count();
+        let assignments = task
+            .decisions
+            .iter()
+            .map(|round| round.assignments.len())
+            .sum::<usize>();
+        let mut config = Config::default();
+        config.functional_config.enable = true;
+        let simulator = Simulator::new_from_config(config);
+        let (status, _cycle) = simulator.run_task(&mut task).unwrap();
+        let statistics = &status.statistics.functional_statistics;
+        assert!(statistics.implied_assignments > 0);
+        assert_eq!(
+            roots + statistics.implied_assignments + statistics.unimplied_assignments,
+            assignments
+        );
+    }
+
+    #[test]
+    fn test_task_source() {
+        let limits = SolveLimits {
+            max_conflicts: 20,
+            ..Default::default()
+        };
+        let layout = || MemoryLayout::new(LayoutConfig::default(), 16);
+        let mut task = SataccMinisatTask::new();
+        let mut solver = Solver::new(&pigeonhole(4), layout()).unwrap();
+        let result = solver.solve(limits, &mut task).unwrap();
+        // the source emits the same rounds while the solver runs
+        let mut source =
+            SolverTaskSource::new(Solver::new(&pigeonhole(4), layout()).unwrap(), limits);
+        let mut rounds = SataccMinisatTask::new();
+        while let Some(round) = source.next_round().unwrap() {
+            // the solver is only one round ahead
+            assert_eq!(source.solver().rounds, rounds.decisions.len() + 1);
+            rounds.decisions.push_back(round);
+        }
+        assert_eq!(source.result(), Some(result));
+        assert_eq!(rounds.to_records(), task.to_records());
+    }
+
+    #[test]
+    fn test_run_cnf() {
+        let simulator = Simulator::new_from_config(Config::default());
+        let limits = SolveLimits {
+            max_rounds: 30,
+            ..Default::default()
+        };
+        let (status, cycle, result) = simulator
+            .run_cnf("test_data/pigeonhole_5.cnf", limits)
+            .unwrap();
+        assert_eq!(result, SolveResult::Unknown);
+        assert!(cycle > 0);
+        assert_eq!(
+            status.statistics.fast_forward_statistics.detailed_rounds,
+            30
+        );
+        assert!(simulator.run_cnf("test_data/missing.cnf", limits).is_err());
+    }
+}
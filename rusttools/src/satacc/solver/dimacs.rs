@@ -0,0 +1,133 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    ops::Not,
+};
+
+use eyre::{bail, Context, Result};
+
+/// a literal, the index is `var * 2 + sign` like minisat, the sign is 1 for the negative literal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lit(usize);
+impl Lit {
+    pub fn new(var: usize, negative: bool) -> Self {
+        Lit(var * 2 + negative as usize)
+    }
+    /// the literal of a dimacs integer, the variables start from 1 in dimacs and from 0 here
+    pub fn from_dimacs(lit: i64) -> Self {
+        Lit::new(lit.unsigned_abs() as usize - 1, lit < 0)
+    }
+    pub fn var(self) -> usize {
+        self.0 / 2
+    }
+    pub fn is_negative(self) -> bool {
+        self.0 & 1 == 1
+    }
+    /// the index of the watch list of the literal, it's the `watcher_id` of the tasks
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+impl Not for Lit {
+    type Output = Lit;
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+/// # Cnf
+/// the formula in a dimacs file
+#[derive(Debug, Clone)]
+pub struct Cnf {
+    pub num_vars: usize,
+    pub clauses: Vec<Vec<Lit>>,
+}
+impl Cnf {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let file = File::open(path).wrap_err_with(|| format!("cannot open cnf {path}"))?;
+        Self::parse(BufReader::new(file)).wrap_err_with(|| format!("cannot parse cnf {path}"))
+    }
+    /// parse the `p cnf` header and the clauses, each clause ends with 0
+    pub fn parse(reader: impl BufRead) -> Result<Self> {
+        let mut header = None;
+        let mut clauses = vec![];
+        let mut clause = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.starts_with("version https://git-lfs") {
+                bail!("the file is a git lfs pointer, fetch it by `git lfs pull`");
+            }
+            if line.is_empty() || line.starts_with('c') {
+                continue;
+            }
+            // the end of the satlib files
+            if line.starts_with('%') {
+                break;
+            }
+            if line.starts_with('p') {
+                let fields = line.split_whitespace().collect::<Vec<_>>();
+                match fields[..] {
+                    ["p", "cnf", num_vars, num_clauses] => {
+                        header = Some((num_vars.parse::<usize>()?, num_clauses.parse::<usize>()?))
+                    }
+                    _ => bail!("invalid header: {line}"),
+                }
+                continue;
+            }
+            let Some((num_vars, _)) = header else {
+                bail!("the clauses start before the header");
+            };
+            for lit in line.split_whitespace() {
+                let lit: i64 = lit
+                    .parse()
+                    .wrap_err_with(|| format!("invalid literal {lit}"))?;
+                if lit == 0 {
+                    clauses.push(std::mem::take(&mut clause));
+                } else if lit.unsigned_abs() as usize > num_vars {
+                    bail!("the variable of {lit} is larger than {num_vars}");
+                } else {
+                    clause.push(Lit::from_dimacs(lit));
+                }
+            }
+        }
+        let Some((num_vars, num_clauses)) = header else {
+            bail!("no header in the cnf");
+        };
+        // the last clause may not end with 0
+        if !clause.is_empty() {
+            clauses.push(clause);
+        }
+        if clauses.len() != num_clauses {
+            tracing::warn!(
+                "the header has {num_clauses} clauses, but {} are read",
+                clauses.len()
+            );
+        }
+        Ok(Self { num_vars, clauses })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let cnf =
+            Cnf::parse("c a comment\np cnf 3 2\n1 -3 0\n2 3\n-1 0\n%\n0\n".as_bytes()).unwrap();
+        assert_eq!(cnf.num_vars, 3);
+        assert_eq!(
+            cnf.clauses,
+            vec![
+                vec![Lit::new(0, false), Lit::new(2, true)],
+                vec![Lit::new(1, false), Lit::new(2, false), Lit::new(0, true)],
+            ]
+        );
+        assert_eq!(!Lit::from_dimacs(-3), Lit::from_dimacs(3));
+        assert_eq!(Lit::from_dimacs(-3).index(), 5);
+        assert!(Cnf::parse("1 2 0\n".as_bytes()).is_err());
+        assert!(Cnf::parse("p cnf 1 1\n1 2 0\n".as_bytes()).is_err());
+        assert!(Cnf::parse("version https://git-lfs.github.com/spec/v1\n".as_bytes()).is_err());
+    }
+}
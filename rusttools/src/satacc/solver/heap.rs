@@ -0,0 +1,125 @@
+/// # VarHeap
+/// the binary max heap of the variables ordered by the activity, like the order heap of minisat
+/// - the position of each variable is tracked, so a bumped variable is moved up in place
+/// - the variables with the same activity are ordered by the index, the smaller one first
+#[derive(Debug, Clone)]
+pub(super) struct VarHeap {
+    heap: Vec<usize>,
+    /// the position of each variable in `heap`
+    indices: Vec<Option<usize>>,
+}
+
+impl VarHeap {
+    /// the heap of all the variables
+    pub fn new(num_vars: usize) -> Self {
+        Self {
+            heap: (0..num_vars).collect(),
+            indices: (0..num_vars).map(Some).collect(),
+        }
+    }
+    /// `a` should be picked before `b`
+    fn before(activity: &[f64], a: usize, b: usize) -> bool {
+        activity[a].total_cmp(&activity[b]).then(b.cmp(&a)).is_gt()
+    }
+    pub fn contains(&self, var: usize) -> bool {
+        self.indices[var].is_some()
+    }
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.indices[self.heap[i]] = Some(i);
+        self.indices[self.heap[j]] = Some(j);
+    }
+    fn sift_up(&mut self, mut i: usize, activity: &[f64]) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if !Self::before(activity, self.heap[i], self.heap[parent]) {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+    fn sift_down(&mut self, mut i: usize, activity: &[f64]) {
+        loop {
+            let mut first = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.heap.len()
+                    && Self::before(activity, self.heap[child], self.heap[first])
+                {
+                    first = child;
+                }
+            }
+            if first == i {
+                break;
+            }
+            self.swap(i, first);
+            i = first;
+        }
+    }
+    /// add the variable back, it's ignored if it's already in the heap
+    pub fn insert(&mut self, var: usize, activity: &[f64]) {
+        if self.contains(var) {
+            return;
+        }
+        self.heap.push(var);
+        self.indices[var] = Some(self.heap.len() - 1);
+        self.sift_up(self.heap.len() - 1, activity);
+    }
+    /// the activity of the variable is increased, move it up
+    pub fn increase(&mut self, var: usize, activity: &[f64]) {
+        if let Some(i) = self.indices[var] {
+            self.sift_up(i, activity);
+        }
+    }
+    /// remove the variable with the highest activity
+    pub fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        let last = self.heap.len().checked_sub(1)?;
+        self.swap(0, last);
+        let var = self.heap.pop().unwrap();
+        self.indices[var] = None;
+        if !self.heap.is_empty() {
+            self.sift_down(0, activity);
+        }
+        Some(var)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_heap() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut activity = vec![0.; 100];
+        let mut heap = VarHeap::new(100);
+        // the activities only grow like the bumps of the solver
+        for _ in 0..1000 {
+            let var = rng.gen_range(0..100);
+            activity[var] += rng.gen_range(1..4) as f64;
+            heap.increase(var, &activity);
+        }
+        activity[42] = 10000.;
+        heap.increase(42, &activity);
+        assert_eq!(heap.pop(&activity), Some(42));
+        assert!(!heap.contains(42));
+        let mut popped = vec![];
+        while let Some(var) = heap.pop(&activity) {
+            popped.push(var);
+        }
+        let mut expected = (0..100).filter(|&var| var != 42).collect::<Vec<_>>();
+        expected.sort_by(|&a, &b| activity[b].total_cmp(&activity[a]).then(a.cmp(&b)));
+        assert_eq!(popped, expected);
+        heap.insert(7, &activity);
+        heap.insert(3, &activity);
+        heap.insert(7, &activity);
+        assert_eq!(
+            heap.pop(&activity),
+            Some(if activity[7] > activity[3] { 7 } else { 3 })
+        );
+        assert!(heap.pop(&activity).is_some());
+        assert_eq!(heap.pop(&activity), None);
+    }
+}
@@ -0,0 +1,6 @@
+pub(self) mod cdcl;
+pub(self) mod dimacs;
+pub(self) mod heap;
+
+pub use cdcl::{SolveLimits, SolveResult, Solver, SolverTaskSource};
+pub use dimacs::{Cnf, Lit};
@@ -1,12 +1,145 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
 
-use super::{cache::CacheId, satacc_minisat_task::SingleRoundTask};
+use super::{cache::CacheId, satacc_minisat_task::SingleRoundTask, MemReqKind};
+/// the cache statistics of one kind of request
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ReqKindCacheStatistics {
+    pub hits: usize,
+    pub misses: usize,
+    /// the line is missed but already in flight, the request is merged into the mshr,
+    /// it's also counted in `hits` or `misses` by the tag lookup
+    pub mshr_hits: usize,
+    /// the missed request that do not allocate a line, it's also counted in `misses`
+    pub bypasses: usize,
+    /// the cycles from the request arriving at the cache to the response leaving the cache
+    pub latency: AverageStat,
+}
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct CacheStatistics {
     pub cache_hits: usize,
     pub cache_misses: usize,
+    pub per_kind: BTreeMap<MemReqKind, ReqKindCacheStatistics>,
+    /// the request is stalled because all mshr entries are in use
+    pub mshr_entry_full_stalls: usize,
+    /// the request is stalled because the mshr entry cannot merge more requests
+    pub mshr_target_full_stalls: usize,
+    /// the request is stalled because its channel is busy with another access
+    pub channel_conflicts: usize,
+    pub prefetch_issued: usize,
+    /// a demand request hit a prefetched line
+    pub prefetch_useful: usize,
+    /// a demand request arrived while the prefetch is still in flight
+    pub prefetch_late: usize,
+    /// a prefetched line is evicted before any demand request
+    pub prefetch_useless: usize,
+    /// the cycles a missed line waits for the bandwidth of the next level, including the transfer time
+    pub miss_queue_delay: AverageStat,
+    /// the lines written to the next level, the evicted dirty lines and the bypassed writes
+    pub writebacks: usize,
+}
+
+/// the statistics of the built-in dram model
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct DramStatistics {
+    pub row_hits: usize,
+    /// the bank is closed, need to activate the row
+    pub row_misses: usize,
+    /// another row is open, need to precharge and activate
+    pub row_conflicts: usize,
+    /// the cycles from the request arriving at the dram to the data returned
+    pub latency: AverageStat,
+    /// the written lines, they are not returned to the cache
+    pub writes: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ScratchpadStatistics {
+    pub accesses: usize,
+    /// the request is stalled because the bank is accepting another request
+    pub bank_conflicts: usize,
+    /// the value request is out of the scratchpad range and sent to the l3 cache
+    pub out_of_range: usize,
+}
+
+/// the requests to the distributed l3 cache
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct NucaStatistics {
+    /// the request is served by the slice of its own tile
+    pub local_reqs: usize,
+    pub remote_reqs: usize,
+}
+
+/// the messages that keep the value copies in the private caches fresh
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct CoherenceStatistics {
+    /// the values written by the trail
+    pub value_writes: usize,
+    pub invalidations: usize,
+    /// the broadcast updates, each one is delivered to all the tiles
+    pub updates: usize,
+    /// the bytes sent on the coherence network
+    pub bytes: usize,
+    /// the cycles from the write to the message delivered to the tile
+    pub delay: AverageStat,
+    /// the lines removed from the private caches by the invalidations
+    pub invalidated_lines: usize,
+    /// the lines updated in place by the broadcast updates
+    pub updated_lines: usize,
+}
+
+/// the rounds simulated in detail one after another
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct DetailedWindow {
+    pub first_round: usize,
+    pub rounds: usize,
+    pub cycles: usize,
+}
+/// the rounds of the fast-forward mode, see [`super::FastForwardConfig`]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct FastForwardStatistics {
+    /// the rounds that only warm up the caches
+    pub fast_forward_rounds: usize,
+    pub detailed_rounds: usize,
+    pub windows: Vec<DetailedWindow>,
+}
+impl FastForwardStatistics {
+    /// the round `round` took `cycles`, it's added to the last window if it follows the window
+    pub fn add_detailed_round(&mut self, round: usize, cycles: usize) {
+        self.detailed_rounds += 1;
+        match self.windows.last_mut() {
+            Some(window) if window.first_round + window.rounds == round => {
+                window.rounds += 1;
+                window.cycles += cycles;
+            }
+            _ => self.windows.push(DetailedWindow {
+                first_round: round,
+                rounds: 1,
+                cycles,
+            }),
+        }
+    }
+}
+
+/// the reuse profile of a stream of lines
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ReuseProfile {
+    pub accesses: usize,
+    /// the first access to a line, it has no stack distance
+    pub cold_accesses: usize,
+    /// the lru stack distance histogram in lines, bucket 0 is distance 0, bucket i is `[2^(i-1), 2^i)`
+    pub stack_distance_histogram: Vec<usize>,
+    /// the number of distinct lines accessed in each window
+    pub working_set_sizes: Vec<usize>,
+}
+/// the profile of the memory stream at the l3 input, it's empty if the profiler is disabled
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct MemProfileStatistics {
+    pub all: ReuseProfile,
+    pub per_kind: BTreeMap<MemReqKind, ReuseProfile>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
@@ -40,6 +173,8 @@ pub struct ClauseIdleStat {
 pub struct SingleClauseStatistics {
     pub total_clause_received: usize,
     pub total_value_read: usize,
+    /// the writes of the watch lists for the new watches
+    pub total_watch_list_write: usize,
     pub idle_cycle: usize,
     pub busy_cycle: usize,
     pub idle_stat: ClauseIdleStat,
@@ -49,7 +184,7 @@ pub struct SingleClauseStatistics {
 pub struct ClauseStatistics {
     pub single_clause: Vec<SingleClauseStatistics>,
 }
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct AverageStat {
     pub count: usize,
     pub total: usize,
@@ -71,6 +206,35 @@ pub struct IcntStat {
     pub idle_cycle: usize,
     pub busy_cycle: usize,
 }
+/// the clause results and the assignments of the closed-loop mode, see [`super::FunctionalConfig`]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct FunctionalStatistics {
+    pub satisfied: usize,
+    pub unit: usize,
+    pub conflict: usize,
+    pub unresolved: usize,
+    /// the assignments sent after a clause unit implied them
+    pub implied_assignments: usize,
+    /// the assignments sent in the software order because no clause implied them
+    pub unimplied_assignments: usize,
+    /// the implied literals that have no assignment in the round, they have no watch list to process
+    pub implied_without_task: usize,
+}
+/// the conflicts that abort the rounds and the work wasted after them, see [`super::ConflictConfig`]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ConflictStatistics {
+    pub conflicts: usize,
+    /// the assignments cancelled in the trail or in the watchers
+    pub cancelled_assignments: usize,
+    /// the single watchers cancelled in the watchers
+    pub cancelled_watchers: usize,
+    /// the clauses cancelled in the clause units
+    pub cancelled_clauses: usize,
+    /// the memory requests returned after the signal, their results are dropped
+    pub drained_mem_reqs: usize,
+    /// the cycles from the conflicts to the end of their rounds
+    pub flush_cycles: usize,
+}
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Statistics {
     pub total_cycle: usize,
@@ -82,6 +246,14 @@ pub struct Statistics {
     pub private_cache_statistics: Vec<CacheStatistics>,
     pub icnt_statistics: IcntStat,
     pub l3_cache_statistics: CacheStatistics,
+    pub scratchpad_statistics: ScratchpadStatistics,
+    pub nuca_statistics: NucaStatistics,
+    pub coherence_statistics: CoherenceStatistics,
+    pub dram_statistics: DramStatistics,
+    pub mem_profile: MemProfileStatistics,
+    pub fast_forward_statistics: FastForwardStatistics,
+    pub functional_statistics: FunctionalStatistics,
+    pub conflict_statistics: ConflictStatistics,
     pub config: Config,
 }
 impl Default for Statistics {
@@ -106,6 +278,14 @@ impl Statistics {
             ],
             private_cache_statistics: vec![CacheStatistics::default(); n_watchers],
             l3_cache_statistics: Default::default(),
+            scratchpad_statistics: Default::default(),
+            nuca_statistics: Default::default(),
+            coherence_statistics: Default::default(),
+            dram_statistics: Default::default(),
+            mem_profile: Default::default(),
+            fast_forward_statistics: Default::default(),
+            functional_statistics: Default::default(),
+            conflict_statistics: Default::default(),
             total_cycle: 0,
             average_assignments: Default::default(),
             average_watchers: Default::default(),
@@ -113,24 +293,64 @@ impl Statistics {
             icnt_statistics: IcntStat::default(),
         }
     }
-    pub fn update_hit(&mut self, cache_id: &CacheId) {
-        match cache_id {
-            CacheId::PrivateCache(cache_id) => {
-                self.private_cache_statistics[*cache_id].cache_hits += 1;
-            }
-            CacheId::L3Cache => {
-                self.l3_cache_statistics.cache_hits += 1;
-            }
+    pub fn update_hit(&mut self, cache_id: &CacheId, req_kind: MemReqKind) {
+        let stat = self.get_cache_statistics_mut(cache_id);
+        stat.cache_hits += 1;
+        stat.per_kind.entry(req_kind).or_default().hits += 1;
+    }
+    pub fn update_miss(&mut self, cache_id: &CacheId, req_kind: MemReqKind) {
+        let stat = self.get_cache_statistics_mut(cache_id);
+        stat.cache_misses += 1;
+        stat.per_kind.entry(req_kind).or_default().misses += 1;
+    }
+    /// the line is in flight, it's a mshr hit of the kind
+    /// - it's counted as a hit or a miss by the tag lookup, `tag_hit`, so the kinds add up to the totals
+    pub fn update_mshr_hit(&mut self, cache_id: &CacheId, req_kind: MemReqKind, tag_hit: bool) {
+        if tag_hit {
+            self.update_hit(cache_id, req_kind);
+        } else {
+            self.update_miss(cache_id, req_kind);
         }
+        self.get_cache_statistics_mut(cache_id)
+            .per_kind
+            .entry(req_kind)
+            .or_default()
+            .mshr_hits += 1;
+    }
+    pub fn update_bypass(&mut self, cache_id: &CacheId, req_kind: MemReqKind) {
+        self.get_cache_statistics_mut(cache_id)
+            .per_kind
+            .entry(req_kind)
+            .or_default()
+            .bypasses += 1;
+    }
+    pub fn update_cache_latency(
+        &mut self,
+        cache_id: &CacheId,
+        req_kind: MemReqKind,
+        latency: usize,
+    ) {
+        self.get_cache_statistics_mut(cache_id)
+            .per_kind
+            .entry(req_kind)
+            .or_default()
+            .latency
+            .add(latency);
     }
-    pub fn update_miss(&mut self, cache_id: &CacheId) {
+    pub fn update_mshr_entry_full(&mut self, cache_id: &CacheId) {
+        self.get_cache_statistics_mut(cache_id).mshr_entry_full_stalls += 1;
+    }
+    pub fn update_mshr_target_full(&mut self, cache_id: &CacheId) {
+        self.get_cache_statistics_mut(cache_id)
+            .mshr_target_full_stalls += 1;
+    }
+    pub fn update_channel_conflict(&mut self, cache_id: &CacheId) {
+        self.get_cache_statistics_mut(cache_id).channel_conflicts += 1;
+    }
+    pub fn get_cache_statistics_mut(&mut self, cache_id: &CacheId) -> &mut CacheStatistics {
         match cache_id {
-            CacheId::PrivateCache(cache_id) => {
-                self.private_cache_statistics[*cache_id].cache_misses += 1;
-            }
-            CacheId::L3Cache => {
-                self.l3_cache_statistics.cache_misses += 1;
-            }
+            CacheId::PrivateCache(cache_id) => &mut self.private_cache_statistics[*cache_id],
+            CacheId::L3Cache => &mut self.l3_cache_statistics,
         }
     }
 
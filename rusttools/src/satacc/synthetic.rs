@@ -0,0 +1,494 @@
+use std::{collections::BTreeMap, fs::File, io::Read};
+
+use eyre::{bail, Context, Result};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::satstat::{histogram_edges, FinalResult};
+
+use super::{
+    layout::{LayoutConfig, MemoryLayout},
+    satacc_minisat_task::{SataccMinisatTask, SingleRoundTask},
+    task_source::TaskSource,
+};
+
+/// the distribution of a count
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Distribution {
+    Constant(usize),
+    /// uniform in `[min, max]`
+    Uniform {
+        min: usize,
+        max: usize,
+    },
+    /// pick a bin by the counts, then uniform in the bin `[edges[i], edges[i + 1])`
+    Histogram {
+        edges: Vec<usize>,
+        counts: Vec<usize>,
+    },
+}
+impl Distribution {
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        match self {
+            Distribution::Constant(value) => *value,
+            Distribution::Uniform { min, max } => rng.gen_range(*min..=*max),
+            Distribution::Histogram { edges, counts } => {
+                let total: usize = counts.iter().sum();
+                if total == 0 {
+                    return edges[0];
+                }
+                let mut target = rng.gen_range(0..total);
+                let bin = counts
+                    .iter()
+                    .position(|&count| {
+                        if target < count {
+                            true
+                        } else {
+                            target -= count;
+                            false
+                        }
+                    })
+                    .unwrap();
+                rng.gen_range(edges[bin]..edges[bin + 1])
+            }
+        }
+    }
+    /// check the distribution can be sampled
+    fn validate(&self, name: &str) -> Result<()> {
+        match self {
+            Distribution::Constant(_) => {}
+            Distribution::Uniform { min, max } => {
+                if min > max {
+                    bail!("{name}: the uniform min {min} is larger than the max {max}");
+                }
+            }
+            Distribution::Histogram { edges, counts } => {
+                if edges.len() != counts.len() + 1 {
+                    bail!(
+                        "{name}: the histogram has {} edges for {} bins",
+                        edges.len(),
+                        counts.len()
+                    );
+                }
+                if let Some(bin) =
+                    (0..counts.len()).find(|&bin| counts[bin] > 0 && edges[bin] >= edges[bin + 1])
+                {
+                    bail!("{name}: the bin {bin} is empty but has samples");
+                }
+            }
+        }
+        Ok(())
+    }
+    /// the histogram of a `final_result_histo.json`, `None` if it's missing
+    fn from_final_result(result: &FinalResult, name: &str) -> Option<Self> {
+        result
+            .histograms
+            .get(name)
+            .map(|counts| Distribution::Histogram {
+                edges: histogram_edges(),
+                counts: counts.clone(),
+            })
+    }
+    /// the histogram of the count of each value, each value is a bin, `None` if it's empty
+    fn from_counts(counts: &BTreeMap<usize, usize>) -> Option<Self> {
+        let (&min, _) = counts.first_key_value()?;
+        let (&max, _) = counts.last_key_value()?;
+        Some(Distribution::Histogram {
+            edges: (min..=max + 1).collect(),
+            counts: (min..=max)
+                .map(|value| counts.get(&value).copied().unwrap_or(0))
+                .collect(),
+        })
+    }
+}
+
+/// # SyntheticConfig
+/// the parameters of the synthetic rounds
+/// - an assignment is a watch list, a watcher reads its clause with `clause_read_fraction`
+/// - the literals, blockers and clauses are picked near the last ones with `locality`, otherwise uniformly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyntheticConfig {
+    pub seed: u64,
+    pub rounds: usize,
+    pub num_vars: usize,
+    pub num_clauses: usize,
+    pub assignments_per_round: Distribution,
+    pub watchers_per_assignment: Distribution,
+    /// the fraction of the watchers whose blocker is not true, they read the clause
+    pub clause_read_fraction: f64,
+    /// the number of literals of a clause, the clause reads the values of the other literals
+    pub clause_length: Distribution,
+    /// the probability to pick an item in `locality_window` of the last one
+    pub locality: f64,
+    pub locality_window: usize,
+    /// the number of assignments of each level of a round, the first level is the decision
+    pub assignments_per_level: Distribution,
+    /// the probability that an assignment is the huge watch list of literal 0
+    pub hot_watch_list_probability: f64,
+    pub hot_watch_list_len: usize,
+    /// the data is placed by the layout model, the watch lists are partitioned to `num_partitions` pes if configured
+    pub layout: LayoutConfig,
+    pub num_partitions: usize,
+}
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            rounds: 100,
+            num_vars: 10000,
+            num_clauses: 40000,
+            assignments_per_round: Distribution::Uniform { min: 1, max: 32 },
+            watchers_per_assignment: Distribution::Uniform { min: 1, max: 16 },
+            clause_read_fraction: 0.5,
+            clause_length: Distribution::Uniform { min: 2, max: 8 },
+            locality: 0.5,
+            locality_window: 64,
+            assignments_per_level: Distribution::Uniform { min: 1, max: 4 },
+            hot_watch_list_probability: 0.,
+            hot_watch_list_len: 10000,
+            layout: LayoutConfig::default(),
+            num_partitions: 16,
+        }
+    }
+}
+impl SyntheticConfig {
+    /// fit the distributions to the statistics of a real run, the other parameters are the default
+    pub fn from_histo_file(path: &str) -> Result<Self> {
+        let mut content = String::new();
+        File::open(path)
+            .wrap_err_with(|| format!("cannot open {path}"))?
+            .read_to_string(&mut content)?;
+        Self::from_histo(&content).wrap_err_with(|| format!("cannot fit {path}"))
+    }
+    /// fit the distributions to the json of [`crate::satstat::Satstat::save_data`]
+    /// - the clause length and the level structure are fitted if the solver reported them
+    pub fn from_histo(json: &str) -> Result<Self> {
+        let result: FinalResult = serde_json::from_str(json)?;
+        let default = Self::default();
+        Ok(Self {
+            rounds: result.total_decisions.max(1),
+            assignments_per_round: Distribution::from_final_result(
+                &result,
+                "watchers_per_decision",
+            )
+            .unwrap_or(default.assignments_per_round),
+            watchers_per_assignment: Distribution::from_final_result(
+                &result,
+                "clauses_per_watcher",
+            )
+            .unwrap_or(default.watchers_per_assignment),
+            clause_read_fraction: if result.total_clauses == 0 {
+                default.clause_read_fraction
+            } else {
+                result.total_clauses_with_data as f64 / result.total_clauses as f64
+            },
+            clause_length: Distribution::from_counts(&result.clause_lengths)
+                .unwrap_or(default.clause_length),
+            assignments_per_level: Distribution::from_counts(&result.assignments_per_level)
+                .unwrap_or(default.assignments_per_level),
+            ..default
+        })
+    }
+    /// check the config can be generated
+    pub fn validate(&self) -> Result<()> {
+        if self.num_vars == 0 || self.num_clauses == 0 {
+            bail!(
+                "the synthetic task needs variables and clauses, got {} variables and {} clauses",
+                self.num_vars,
+                self.num_clauses
+            );
+        }
+        for (name, probability) in [
+            ("clause_read_fraction", self.clause_read_fraction),
+            ("locality", self.locality),
+            (
+                "hot_watch_list_probability",
+                self.hot_watch_list_probability,
+            ),
+        ] {
+            if !(0. ..=1.).contains(&probability) {
+                bail!("{name} should be in [0, 1], got {probability}");
+            }
+        }
+        for (name, distribution) in [
+            ("assignments_per_round", &self.assignments_per_round),
+            ("watchers_per_assignment", &self.watchers_per_assignment),
+            ("clause_length", &self.clause_length),
+            ("assignments_per_level", &self.assignments_per_level),
+        ] {
+            distribution.validate(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// # SyntheticGenerator
+/// build the rounds of a [`SyntheticConfig`], the same seed builds the same rounds
+/// - the rounds are built by the task builder, so they can be traced by [`SataccMinisatTask::start_trace`]
+pub struct SyntheticGenerator {
+    config: SyntheticConfig,
+    rng: StdRng,
+    layout: MemoryLayout,
+    rounds: usize,
+    last_lit: usize,
+    last_var: usize,
+    last_clause: usize,
+}
+impl SyntheticGenerator {
+    /// fails if the config is invalid, see [`SyntheticConfig::validate`]
+    pub fn new(config: SyntheticConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            layout: MemoryLayout::new(config.layout, config.num_partitions),
+            config,
+            rounds: 0,
+            last_lit: 0,
+            last_var: 0,
+            last_clause: 0,
+        })
+    }
+
+    /// pick an item in `0..total` near `last` with the locality
+    fn pick(rng: &mut StdRng, config: &SyntheticConfig, last: usize, total: usize) -> usize {
+        if rng.gen_bool(config.locality) {
+            let window = config.locality_window.min(total);
+            (last + rng.gen_range(0..window.max(1))) % total
+        } else {
+            rng.gen_range(0..total)
+        }
+    }
+
+    /// add a round to the task, false if all the rounds are built
+    /// - fails if the watch lists or the clauses don't fit in the layout
+    pub fn build_round(&mut self, task: &mut SataccMinisatTask) -> Result<bool> {
+        if self.rounds >= self.config.rounds {
+            return Ok(false);
+        }
+        self.rounds += 1;
+        let config = &self.config;
+        let layout = &mut self.layout;
+        let rng = &mut self.rng;
+        task.start_new_assgin();
+        let assignments = config.assignments_per_round.sample(rng).max(1);
+        let mut level = 0;
+        let mut level_remaining = 1;
+        for _ in 0..assignments {
+            if level_remaining == 0 {
+                level += 1;
+                level_remaining = config.assignments_per_level.sample(rng).max(1);
+            }
+            level_remaining -= 1;
+            let hot = rng.gen_bool(config.hot_watch_list_probability);
+            let (lit, watchers) = if hot {
+                (0, config.hot_watch_list_len)
+            } else {
+                self.last_lit = Self::pick(rng, config, self.last_lit, config.num_vars * 2);
+                (self.last_lit, config.watchers_per_assignment.sample(rng))
+            };
+            layout.reserve_watchers(lit, watchers)?;
+            task.add_watcher_task(
+                level,
+                layout.watch_meta_addr(lit),
+                layout.watch_data_addr(lit),
+                lit,
+            );
+            task.set_assignment_value_addr(layout.value_addr(lit / 2));
+            for _ in 0..watchers {
+                self.last_var = Self::pick(rng, config, self.last_var, config.num_vars);
+                let blocker_addr = layout.value_addr(self.last_var);
+                if !rng.gen_bool(config.clause_read_fraction) {
+                    task.add_single_watcher_task_no_clause(blocker_addr, lit);
+                    continue;
+                }
+                self.last_clause = Self::pick(rng, config, self.last_clause, config.num_clauses);
+                let length = config.clause_length.sample(rng).max(2);
+                task.add_single_watcher_task(
+                    blocker_addr,
+                    layout.clause_addr(self.last_clause, length, lit)?,
+                    self.last_clause,
+                    length,
+                    lit,
+                );
+                for _ in 1..length {
+                    self.last_var = Self::pick(rng, config, self.last_var, config.num_vars);
+                    task.add_single_watcher_clause_value_addr(
+                        layout.value_addr(self.last_var),
+                        self.last_var,
+                    );
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// build all the rounds
+    pub fn generate(mut self) -> Result<SataccMinisatTask> {
+        let mut task = SataccMinisatTask::new();
+        while self.build_round(&mut task)? {}
+        Ok(task)
+    }
+}
+impl TaskSource for SyntheticGenerator {
+    fn next_round(&mut self) -> Result<Option<SingleRoundTask>> {
+        let mut round = SataccMinisatTask::new();
+        self.build_round(&mut round)?;
+        Ok(round.pop_next_task())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{config::Config, satacc::Simulator};
+
+    #[test]
+    fn test_generate() {
+        let config = SyntheticConfig {
+            rounds: 5,
+            assignments_per_round: Distribution::Constant(6),
+            watchers_per_assignment: Distribution::Constant(3),
+            clause_read_fraction: 1.,
+            clause_length: Distribution::Constant(4),
+            assignments_per_level: Distribution::Constant(2),
+            ..Default::default()
+        };
+        let task = SyntheticGenerator::new(config.clone())
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert_eq!(task.decisions.len(), 5);
+        for round in &task.decisions {
+            let levels = round
+                .assignments
+                .iter()
+                .map(|assignment| assignment.level)
+                .collect::<Vec<_>>();
+            assert_eq!(levels, [0, 1, 1, 2, 2, 3]);
+            for assignment in &round.assignments {
+                assert_eq!(assignment.single_watcher_tasks.len(), 3);
+                for watcher in &assignment.single_watcher_tasks {
+                    let clause_data = watcher.clause_data.as_ref().unwrap();
+                    assert_eq!(clause_data.clause_value_addr.len(), 3);
+                    assert!(clause_data.clause_addr >= config.layout.clause_base);
+                }
+            }
+        }
+        // the same seed builds the same rounds
+        let other = SyntheticGenerator::new(config).unwrap().generate().unwrap();
+        assert_eq!(other.to_records(), task.to_records());
+    }
+
+    #[test]
+    fn test_validate() {
+        let invalid_configs = [
+            SyntheticConfig {
+                num_vars: 0,
+                ..Default::default()
+            },
+            SyntheticConfig {
+                num_clauses: 0,
+                ..Default::default()
+            },
+            SyntheticConfig {
+                locality: 1.5,
+                ..Default::default()
+            },
+            SyntheticConfig {
+                clause_read_fraction: -0.1,
+                ..Default::default()
+            },
+            SyntheticConfig {
+                clause_length: Distribution::Uniform { min: 4, max: 2 },
+                ..Default::default()
+            },
+            SyntheticConfig {
+                watchers_per_assignment: Distribution::Histogram {
+                    edges: vec![0, 10],
+                    counts: vec![1, 1],
+                },
+                ..Default::default()
+            },
+            SyntheticConfig {
+                assignments_per_level: Distribution::Histogram {
+                    edges: vec![1, 1, 2],
+                    counts: vec![1, 1],
+                },
+                ..Default::default()
+            },
+        ];
+        for config in invalid_configs {
+            assert!(SyntheticGenerator::new(config).is_err());
+        }
+        assert!(SyntheticGenerator::new(SyntheticConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_hot_watch_list() {
+        let config = SyntheticConfig {
+            rounds: 2,
+            hot_watch_list_probability: 1.,
+            hot_watch_list_len: 500,
+            ..Default::default()
+        };
+        let mut generator = SyntheticGenerator::new(config).unwrap();
+        let mut rounds = 0;
+        while let Some(round) = generator.next_round().unwrap() {
+            assert!(round
+                .assignments
+                .iter()
+                .all(|assignment| assignment.watcher_id == 0
+                    && assignment.single_watcher_tasks.len() == 500));
+            rounds += 1;
+        }
+        assert_eq!(rounds, 2);
+    }
+
+    #[test]
+    fn test_fit_histo() {
+        let mut counts = vec![0; histogram_edges().len() - 1];
+        counts[1] = 10;
+        let mut histograms = BTreeMap::new();
+        histograms.insert("watchers_per_decision".to_string(), counts.clone());
+        histograms.insert("clauses_per_watcher".to_string(), counts);
+        let result = FinalResult {
+            total_conflicts: 1,
+            total_decisions: 10,
+            total_clauses: 400,
+            total_watchers: 40,
+            total_clauses_with_data: 100,
+            histograms,
+            clause_lengths: [(3, 5), (5, 5)].into(),
+            assignments_per_level: [(2, 1)].into(),
+        };
+        let config = SyntheticConfig::from_histo(&serde_json::to_string(&result).unwrap()).unwrap();
+        assert_eq!(config.rounds, 10);
+        assert_eq!(config.clause_read_fraction, 0.25);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            assert!((10..20).contains(&config.assignments_per_round.sample(&mut rng)));
+            assert!([3, 5].contains(&config.clause_length.sample(&mut rng)));
+            assert_eq!(config.assignments_per_level.sample(&mut rng), 2);
+        }
+        // the old result without the clause lengths and the levels keeps the default
+        let mut json = serde_json::to_value(&result).unwrap();
+        json.as_object_mut().unwrap().remove("clause_lengths");
+        json.as_object_mut()
+            .unwrap()
+            .remove("assignments_per_level");
+        let old = SyntheticConfig::from_histo(&json.to_string()).unwrap();
+        assert_eq!(old.clause_length, SyntheticConfig::default().clause_length);
+        let mut task = SyntheticGenerator::new(SyntheticConfig {
+            rounds: 3,
+            ..config
+        })
+        .unwrap()
+        .generate()
+        .unwrap();
+        let simulator = Simulator::new_from_config(Config::default());
+        let (_status, cycle) = simulator.run_task(&mut task).unwrap();
+        assert!(cycle > 0);
+    }
+}
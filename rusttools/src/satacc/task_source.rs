@@ -0,0 +1,113 @@
+use std::{io::BufRead, sync::mpsc::Receiver};
+
+use eyre::Result;
+
+use super::{
+    satacc_minisat_task::{SataccMinisatTask, SingleRoundTask},
+    trace::{apply_record, TraceReader, TraceRecord},
+};
+
+/// # TaskSource
+/// the rounds fed to the simulator, the simulator takes the next round only after the last one is drained
+/// - [`SataccMinisatTask`] holds all the rounds in memory
+/// - [`TraceReader`] reads the rounds lazily from a trace, only one round is in memory
+/// - [`ChannelTaskSource`] receives the rounds from another thread
+pub trait TaskSource {
+    /// the next round, `None` if there are no more rounds
+    fn next_round(&mut self) -> Result<Option<SingleRoundTask>>;
+}
+
+impl TaskSource for SataccMinisatTask {
+    fn next_round(&mut self) -> Result<Option<SingleRoundTask>> {
+        Ok(self.pop_next_task())
+    }
+}
+
+impl<R: BufRead> TaskSource for TraceReader<R> {
+    fn next_round(&mut self) -> Result<Option<SingleRoundTask>> {
+        // a round ends at the start of the next round or at the end of the trace
+        while let Some(record) = self.next_record()? {
+            let finished_round = match record {
+                TraceRecord::NewRound => self.current_round.pop_next_task(),
+                _ => None,
+            };
+            apply_record(&mut self.current_round, record)?;
+            if finished_round.is_some() {
+                return Ok(finished_round);
+            }
+        }
+        Ok(self.current_round.pop_next_task())
+    }
+}
+
+/// the rounds sent by another thread, use a bounded `sync_channel` to limit the rounds in memory
+pub struct ChannelTaskSource {
+    receiver: Receiver<SingleRoundTask>,
+}
+impl ChannelTaskSource {
+    pub fn new(receiver: Receiver<SingleRoundTask>) -> Self {
+        Self { receiver }
+    }
+}
+impl TaskSource for ChannelTaskSource {
+    /// the source ends when the sender is dropped
+    fn next_round(&mut self) -> Result<Option<SingleRoundTask>> {
+        Ok(self.receiver.recv().ok())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::mpsc::sync_channel, thread};
+
+    use super::*;
+    use crate::satacc::trace::TraceWriter;
+
+    fn build_task(rounds: usize) -> SataccMinisatTask {
+        let mut task = SataccMinisatTask::new();
+        for round in 0..rounds {
+            task.start_new_assgin();
+            task.add_watcher_task(0, 64, 128, round);
+            task.add_single_watcher_task(320, 1024, 7, 20, round);
+            task.add_single_watcher_clause_value_addr(4100, 5);
+            task.add_watcher_task(0, 512, 640, round + 1);
+        }
+        task
+    }
+
+    #[test]
+    fn test_trace_source() {
+        let task = build_task(3);
+        let mut writer = TraceWriter::new(Vec::new()).unwrap();
+        writer.write_task(&task).unwrap();
+        let bytes = writer.finish().unwrap();
+        let mut reader = TraceReader::new(bytes.as_slice()).unwrap();
+        let mut rounds = 0;
+        while let Some(round) = reader.next_round().unwrap() {
+            assert_eq!(round.assignments.len(), 2);
+            assert_eq!(round.assignments[0].watcher_id, rounds);
+            // only the started round is kept in the reader
+            assert!(reader.current_round.decisions.len() <= 1);
+            rounds += 1;
+        }
+        assert_eq!(rounds, 3);
+    }
+
+    #[test]
+    fn test_channel_source() {
+        let (sender, receiver) = sync_channel(1);
+        let producer = thread::spawn(move || {
+            let mut task = build_task(4);
+            while let Some(round) = task.pop_next_task() {
+                sender.send(round).unwrap();
+            }
+        });
+        let mut source = ChannelTaskSource::new(receiver);
+        let mut rounds = 0;
+        while source.next_round().unwrap().is_some() {
+            rounds += 1;
+        }
+        producer.join().unwrap();
+        assert_eq!(rounds, 4);
+    }
+}
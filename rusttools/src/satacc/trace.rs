@@ -0,0 +1,335 @@
+use std::{
+    ffi::CStr,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+};
+
+use bincode::Options;
+use eyre::{bail, Context, Result};
+use libc::c_char;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    functional::LitValue,
+    satacc_minisat_task::{BuilderStatus, SataccMinisatTask},
+};
+
+/// the bytes at the start of a trace file
+const TRACE_MAGIC: [u8; 4] = *b"SATR";
+/// the version of the trace format, change it when [`TraceRecord`] changes
+/// - the records are encoded by the index of the variant, so the new variants are only appended
+/// - version 2 adds [`TraceRecord::ClauseLit`], the traces of version 1 are still valid
+/// - version 3 adds [`TraceRecord::ClauseConflict`]
+/// - version 4 adds [`TraceRecord::ClauseNewWatch`]
+/// - version 5 adds [`TraceRecord::SingleWatcherUntimed`]
+pub const TRACE_VERSION: u32 = 5;
+/// the oldest version that can be read
+const MIN_TRACE_VERSION: u32 = 1;
+
+/// the integers are varint encoded, so the small ids and levels take one byte
+fn trace_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// # TraceRecord
+/// one call of the task builder, a trace is the calls that build a [`SataccMinisatTask`]
+/// - the records apply to the last round, assignment and watcher, the same as the ffi builder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceRecord {
+    /// start a new [`super::satacc_minisat_task::SingleRoundTask`]
+    NewRound,
+    WatcherTask {
+        level: usize,
+        meta_data_addr: u64,
+        watcher_addr: u64,
+        watcher_id: usize,
+    },
+    /// the value written by the last assignment
+    AssignmentValueAddr { value_addr: u64 },
+    /// a watcher that only reads the blocker
+    SingleWatcherNoClause {
+        blocker_addr: u64,
+        watcher_id: usize,
+    },
+    SingleWatcher {
+        blocker_addr: u64,
+        clause_addr: u64,
+        clause_id: usize,
+        processing_time: usize,
+        watcher_id: usize,
+    },
+    /// a value read by the clause of the last watcher
+    ClauseValueAddr { value_addr: u64, clause_id: usize },
+    /// a literal of the clause of the last watcher, see [`super::ClauseLit`]
+    ClauseLit { lit: usize, value: LitValue },
+    /// the clause of the last watcher is conflicting
+    ClauseConflict,
+    /// the clause of the last watcher found a new watch, see [`super::satacc_minisat_task::NewWatch`]
+    ClauseNewWatch {
+        lit: usize,
+        append_addr: u64,
+        compact_addr: u64,
+    },
+    /// a watcher whose clause is timed by [`super::ClauseTimingConfig`]
+    SingleWatcherUntimed {
+        blocker_addr: u64,
+        clause_addr: u64,
+        clause_id: usize,
+        watcher_id: usize,
+    },
+}
+
+/// # TraceWriter
+/// write the header and then the records of a trace
+pub struct TraceWriter<W: Write> {
+    writer: W,
+}
+impl TraceWriter<BufWriter<File>> {
+    pub fn create(path: &str) -> Result<Self> {
+        let file = File::create(path).wrap_err_with(|| format!("cannot create trace {path}"))?;
+        Self::new(BufWriter::new(file))
+    }
+}
+impl<W: Write> TraceWriter<W> {
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(&TRACE_MAGIC)?;
+        trace_options().serialize_into(&mut writer, &TRACE_VERSION)?;
+        Ok(Self { writer })
+    }
+    pub fn write(&mut self, record: &TraceRecord) -> Result<()> {
+        trace_options()
+            .serialize_into(&mut self.writer, record)
+            .wrap_err("cannot write the trace record")
+    }
+    /// write the records that rebuild the task
+    pub fn write_task(&mut self, task: &SataccMinisatTask) -> Result<()> {
+        for record in task.to_records() {
+            self.write(&record)?;
+        }
+        Ok(())
+    }
+    /// flush the records and return the inner writer
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// apply the record, it's an error if the builder call is invalid
+pub(super) fn apply_record(task: &mut SataccMinisatTask, record: TraceRecord) -> Result<()> {
+    match task.apply_record(record.clone()) {
+        BuilderStatus::Ok => Ok(()),
+        status => bail!("invalid record {record:?}: {status:?}"),
+    }
+}
+
+/// # TraceReader
+/// check the header and then read the records of a trace
+/// - the rounds can be read one by one as a [`super::task_source::TaskSource`]
+pub struct TraceReader<R: BufRead> {
+    reader: R,
+    /// the round that is being read by the task source
+    pub(super) current_round: SataccMinisatTask,
+}
+impl TraceReader<BufReader<File>> {
+    pub fn open(path: &str) -> Result<Self> {
+        let file = File::open(path).wrap_err_with(|| format!("cannot open trace {path}"))?;
+        Self::new(BufReader::new(file))
+    }
+}
+impl<R: BufRead> TraceReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader
+            .read_exact(&mut magic)
+            .wrap_err("cannot read the trace header")?;
+        if magic != TRACE_MAGIC {
+            bail!("not a satacc trace");
+        }
+        let version: u32 = trace_options().deserialize_from(&mut reader)?;
+        if !(MIN_TRACE_VERSION..=TRACE_VERSION).contains(&version) {
+            bail!("unsupported trace version {version}, expected {MIN_TRACE_VERSION} to {TRACE_VERSION}");
+        }
+        Ok(Self {
+            reader,
+            current_round: SataccMinisatTask::new(),
+        })
+    }
+    /// the next record, `None` at the end of the trace
+    pub fn next_record(&mut self) -> Result<Option<TraceRecord>> {
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let record = trace_options()
+            .deserialize_from(&mut self.reader)
+            .wrap_err("the trace is truncated or corrupted")?;
+        Ok(Some(record))
+    }
+    /// read all the records into a task
+    pub fn read_task(mut self) -> Result<SataccMinisatTask> {
+        let mut task = std::mem::replace(&mut self.current_round, SataccMinisatTask::new());
+        while let Some(record) = self.next_record()? {
+            apply_record(&mut task, record)?;
+        }
+        Ok(task)
+    }
+}
+
+/// the path passed by the ffi, an error instead of a panic if it's null or not utf-8
+pub(crate) fn path_from_c<'a>(path: *const c_char) -> Result<&'a str> {
+    if path.is_null() {
+        bail!("the path is null");
+    }
+    unsafe { CStr::from_ptr(path) }
+        .to_str()
+        .wrap_err("the path is not utf-8")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build_task() -> SataccMinisatTask {
+        let mut task = SataccMinisatTask::new();
+        task.start_new_assgin();
+        task.add_watcher_task(0, 64, 128, 3);
+        task.set_assignment_value_addr(4096);
+        task.add_single_watcher_task_no_clause(256, 3);
+        task.add_single_watcher_task(320, 1024, 7, 20, 3);
+        task.add_single_watcher_clause_value_addr(4100, 5);
+        task.add_single_watcher_clause_value_addr(4200, 6);
+        task.add_single_watcher_clause_lit(7, LitValue::False);
+        task.set_clause_conflict();
+        task.start_new_assgin();
+        task.add_watcher_task(1, 512, 640, 4);
+        task
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let task = build_task();
+        let mut writer = TraceWriter::new(Vec::new()).unwrap();
+        writer.write_task(&task).unwrap();
+        let bytes = writer.finish().unwrap();
+        let read_task = TraceReader::new(bytes.as_slice())
+            .unwrap()
+            .read_task()
+            .unwrap();
+        assert_eq!(read_task.to_records(), task.to_records());
+        assert_eq!(read_task.decisions.len(), 2);
+        // the varint records are compact
+        assert!(bytes.len() < 64);
+    }
+
+    #[test]
+    fn test_invalid_ffi_path() {
+        let mut task = SataccMinisatTask::new();
+        assert!(!task.start_trace(std::ptr::null()));
+        assert!(SataccMinisatTask::load_task_from_trace(std::ptr::null()).is_null());
+        let missing = c"missing_dir/missing.trace";
+        assert!(!task.start_trace(missing.as_ptr()));
+        assert!(SataccMinisatTask::load_task_from_trace(missing.as_ptr()).is_null());
+    }
+
+    #[test]
+    fn test_new_watch_round_trip() {
+        let mut task = build_task();
+        task.add_watcher_task(1, 512, 640, 4);
+        task.add_single_watcher_task(384, 2048, 8, 10, 4);
+        task.set_clause_new_watch(9, 768, 136);
+        task.add_single_watcher_task_untimed(448, 3072, 10, 4);
+        let mut writer = TraceWriter::new(Vec::new()).unwrap();
+        writer.write_task(&task).unwrap();
+        let bytes = writer.finish().unwrap();
+        let read_task = TraceReader::new(bytes.as_slice())
+            .unwrap()
+            .read_task()
+            .unwrap();
+        assert_eq!(read_task.to_records(), task.to_records());
+        let new_watch = read_task.decisions[1].assignments[1].single_watcher_tasks[0]
+            .clause_data
+            .as_ref()
+            .unwrap()
+            .new_watch
+            .unwrap();
+        assert_eq!(new_watch.lit, 9);
+        assert_eq!(new_watch.append_addr, 768);
+        assert_eq!(new_watch.compact_addr, 136);
+        let untimed = read_task.decisions[1].assignments[1].single_watcher_tasks[1]
+            .clause_data
+            .as_ref()
+            .unwrap();
+        assert_eq!(untimed.clause_processing_time, None);
+    }
+
+    /// a trace written by version 4, the records of the older versions keep their encoding
+    #[test]
+    fn test_read_version_4() {
+        let bytes = [
+            83, 65, 84, 82, 4, 0, 1, 0, 64, 128, 3, 2, 96, 3, 32, 3, 4, 40, 200, 7, 20, 3, 5, 100,
+            5, 6, 7, 0, 7, 8, 9, 160, 136,
+        ];
+        let task = TraceReader::new(bytes.as_slice())
+            .unwrap()
+            .read_task()
+            .unwrap();
+        let records = task.to_records();
+        assert_eq!(
+            records[4..],
+            [
+                TraceRecord::SingleWatcher {
+                    blocker_addr: 40,
+                    clause_addr: 200,
+                    clause_id: 7,
+                    processing_time: 20,
+                    watcher_id: 3,
+                },
+                TraceRecord::ClauseValueAddr {
+                    value_addr: 100,
+                    clause_id: 5,
+                },
+                TraceRecord::ClauseLit {
+                    lit: 7,
+                    value: LitValue::True,
+                },
+                TraceRecord::ClauseConflict,
+                TraceRecord::ClauseNewWatch {
+                    lit: 9,
+                    append_addr: 160,
+                    compact_addr: 136,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bad_trace() {
+        assert!(TraceReader::new(b"NOPE".as_slice()).is_err());
+        let mut writer = TraceWriter::new(Vec::new()).unwrap();
+        writer.write_task(&build_task()).unwrap();
+        let mut bytes = writer.finish().unwrap();
+        bytes.pop();
+        let mut reader = TraceReader::new(bytes.as_slice()).unwrap();
+        assert!(std::iter::from_fn(|| reader.next_record().transpose()).any(|r| r.is_err()));
+        // the other version is rejected
+        bytes[4] = TRACE_VERSION as u8 + 1;
+        assert!(TraceReader::new(bytes.as_slice()).is_err());
+        bytes[4] = MIN_TRACE_VERSION as u8;
+        assert!(TraceReader::new(bytes.as_slice()).is_ok());
+    }
+
+    #[test]
+    fn test_ffi_writer_hook() {
+        let path = "test_ffi_writer_hook.trace";
+        let mut task = SataccMinisatTask::new();
+        task.trace_writer = Some(TraceWriter::create(path).unwrap());
+        task.start_new_assgin();
+        task.add_watcher_task(0, 64, 128, 3);
+        task.add_single_watcher_task(320, 1024, 7, 20, 3);
+        task.add_single_watcher_clause_value_addr(4100, 5);
+        assert!(task.finish_trace());
+        let read_task = TraceReader::open(path).unwrap().read_task().unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(read_task.to_records(), task.to_records());
+    }
+}
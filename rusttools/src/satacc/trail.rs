@@ -1,6 +1,38 @@
+use std::collections::VecDeque;
+
 use crate::sim::{SimComponent, SimReciver, SimSender};
 
-use super::satacc_minisat_task::{SingleRoundTask, WatcherTask};
+use super::{
+    satacc_minisat_task::{SingleRoundTask, WatcherTask},
+    SataccStatus,
+};
+
+/// the round in the closed-loop mode, see [`super::FunctionalConfig`]
+struct FunctionalRound {
+    /// the assignments waiting for a clause to imply them, in the software order
+    pending: VecDeque<WatcherTask>,
+    /// the assignments that can be sent
+    ready: VecDeque<WatcherTask>,
+}
+impl FunctionalRound {
+    /// the first level is ready, the others are pending
+    fn new(round: SingleRoundTask, shared_status: &mut SataccStatus) -> Self {
+        let first_level = round
+            .assignments
+            .iter()
+            .map(|assignment| assignment.level)
+            .min()
+            .unwrap_or_default();
+        let (ready, pending): (VecDeque<_>, VecDeque<_>) = round
+            .assignments
+            .into_iter()
+            .partition(|assignment| assignment.level == first_level);
+        for assignment in ready.iter() {
+            shared_status.functional_state.assign(assignment.watcher_id);
+        }
+        Self { pending, ready }
+    }
+}
 
 /// the task sender, which will send tasks to the watcher list unit
 pub struct Trail {
@@ -11,6 +43,14 @@ pub struct Trail {
     level_sync: bool,
     current_level_remaining: usize,
     current_processing_level: usize,
+    /// send the value writes of the assignments to the coherence unit
+    value_write_sender: Option<SimSender<u64>>,
+    pending_value_writes: VecDeque<u64>,
+    /// the current round of the closed-loop mode, the level sync is ignored in this mode
+    functional: bool,
+    current_functional_round: Option<FunctionalRound>,
+    /// the level tasks of all the sent assignments, compared with `finished_level_tasks`
+    sent_level_tasks: usize,
 }
 impl Trail {
     pub fn new(
@@ -27,12 +67,159 @@ impl Trail {
             level_sync,
             current_level_remaining: 0,
             current_processing_level: 0,
+            value_write_sender: None,
+            pending_value_writes: VecDeque::new(),
+            functional: false,
+            current_functional_round: None,
+            sent_level_tasks: 0,
         }
     }
+    /// send an assignment only when it's implied by a clause unit
+    pub fn with_functional(mut self, functional: bool) -> Self {
+        self.functional = functional;
+        self
+    }
+    /// the closed-loop mode, return `(busy, updated, sent_value_addr)`
+    /// - the next round starts after all the work of this round is finished
+    fn update_functional(
+        &mut self,
+        shared_status: &mut SataccStatus,
+        current_cycle: usize,
+    ) -> (bool, bool, Option<u64>) {
+        let Some(round) = self.current_functional_round.as_mut() else {
+            let Ok(single_round_task) = self.task_receiver.recv() else {
+                return (false, false, None);
+            };
+            // a new round begin, update the statistics
+            shared_status.update_single_round_task(&single_round_task);
+            shared_status
+                .functional_state
+                .start_round(&single_round_task);
+            self.current_functional_round =
+                Some(FunctionalRound::new(single_round_task, shared_status));
+            return (true, true, None);
+        };
+        let mut updated = false;
+        // the implied literals from the clause units
+        while let Some(lit) = shared_status.functional_state.pop_implied(current_cycle) {
+            updated = true;
+            if !shared_status.functional_state.assign(lit) {
+                // already assigned by another clause
+                continue;
+            }
+            let statistics = &mut shared_status.statistics.functional_statistics;
+            match round
+                .pending
+                .iter()
+                .position(|assignment| assignment.watcher_id == lit)
+            {
+                Some(index) => {
+                    statistics.implied_assignments += 1;
+                    round.ready.extend(round.pending.remove(index));
+                }
+                None => statistics.implied_without_task += 1,
+            }
+        }
+        let mut sent_value_addr = None;
+        if let Some(watcher_task) = round.ready.pop_front() {
+            let watcher_unit_id = watcher_task.get_watcher_pe_id(self.total_watcher);
+            let total_level_tasks = watcher_task.get_total_level_tasks();
+            let value_addr = watcher_task.value_addr;
+            match self.watcher_sender[watcher_unit_id].send(watcher_task) {
+                Ok(_) => {
+                    updated = true;
+                    self.sent_level_tasks += total_level_tasks;
+                    sent_value_addr = value_addr;
+                }
+                Err(watcher_task) => {
+                    round.ready.push_front(watcher_task);
+                    tracing::debug!("send task to watcher {} failed", watcher_unit_id);
+                }
+            }
+        } else if self.sent_level_tasks == shared_status.finished_level_tasks
+            && !shared_status.functional_state.has_implied()
+            && shared_status.conflict_cycle.is_none()
+        {
+            // all the sent work is finished, nothing can be implied anymore
+            updated = true;
+            match round.pending.pop_front() {
+                Some(watcher_task) => {
+                    shared_status
+                        .functional_state
+                        .assign(watcher_task.watcher_id);
+                    shared_status
+                        .statistics
+                        .functional_statistics
+                        .unimplied_assignments += 1;
+                    round.ready.push_back(watcher_task);
+                }
+                None => {
+                    tracing::debug!(current_cycle, "the closed-loop round is finished");
+                    self.current_functional_round = None;
+                }
+            }
+        }
+        // the implied literals and the conflict signal on the way only wait for the latency, so they are updates,
+        // but only waiting for the watchers and the clause units is not, so the lost work is reported as stuck
+        updated |=
+            shared_status.functional_state.has_implied() || shared_status.conflict_cycle.is_some();
+        (true, updated, sent_value_addr)
+    }
+    /// all the sent work is finished or cancelled, a round is finished only after it when the conflict abort is enabled,
+    /// so the conflict of a round never cancels the next round
+    fn round_drained(&self, shared_status: &SataccStatus) -> bool {
+        !shared_status.statistics.config.conflict_config.enable
+            || self.sent_level_tasks == shared_status.finished_level_tasks
+    }
+    /// the conflict is signaled, cancel the assignments not sent yet and wait for the flush and the drain,
+    /// return `(busy, updated)`
+    fn update_aborting(
+        &mut self,
+        shared_status: &mut SataccStatus,
+        current_cycle: usize,
+    ) -> (bool, bool) {
+        let cancelled = self
+            .current_working_task
+            .iter_mut()
+            .map(|round| std::mem::take(&mut round.assignments).len())
+            .chain(self.current_functional_round.iter_mut().map(|round| {
+                std::mem::take(&mut round.pending).len() + std::mem::take(&mut round.ready).len()
+            }))
+            .sum::<usize>();
+        shared_status
+            .statistics
+            .conflict_statistics
+            .cancelled_assignments += cancelled;
+        let conflict_cycle = shared_status.conflict_cycle.unwrap();
+        let flush_end = shared_status
+            .statistics
+            .config
+            .conflict_config
+            .flush_end(conflict_cycle);
+        // the flush only waits for the latency, so it's an update,
+        // but only waiting for the cancellations of the pes is not, so the lost cancellation is reported as stuck
+        let mut updated = cancelled > 0 || current_cycle < flush_end;
+        if current_cycle >= flush_end && self.sent_level_tasks == shared_status.finished_level_tasks
+        {
+            tracing::debug!(current_cycle, conflict_cycle, "the round is aborted");
+            shared_status.statistics.conflict_statistics.flush_cycles +=
+                current_cycle - conflict_cycle;
+            shared_status.conflict_cycle = None;
+            self.current_working_task = None;
+            self.current_functional_round = None;
+            updated = true;
+        }
+        (true, updated)
+    }
+    /// write the value of each sent assignment to the coherence unit
+    pub fn with_value_write_sender(mut self, value_write_sender: SimSender<u64>) -> Self {
+        self.value_write_sender = Some(value_write_sender);
+        self
+    }
 }
 
 impl SimComponent for Trail {
-    type SharedStatus = super::SataccStatus;
+    type SharedStatus = SataccStatus;
     fn update(
         &mut self,
         shared_status: &mut Self::SharedStatus,
@@ -40,8 +227,16 @@ impl SimComponent for Trail {
     ) -> (bool, bool) {
         let mut busy = self.current_working_task.is_some();
         let mut updated = false;
+        let mut sent_value_addr = None;
 
         match self.level_sync {
+            _ if shared_status.is_aborting(_current_cycle) => {
+                (busy, updated) = self.update_aborting(shared_status, _current_cycle);
+            }
+            _ if self.functional => {
+                (busy, updated, sent_value_addr) =
+                    self.update_functional(shared_status, _current_cycle);
+            }
             true => {
                 // update current running task
                 if let Some(current_task) = self.current_working_task.as_mut() {
@@ -67,11 +262,14 @@ impl SimComponent for Trail {
                             let watcher_unit_id =
                                 watcher_task.get_watcher_pe_id(self.total_watcher);
                             let total_level_tasks = watcher_task.get_total_level_tasks();
+                            let value_addr = watcher_task.value_addr;
                             match self.watcher_sender[watcher_unit_id].send(watcher_task) {
                                 Ok(_) => {
                                     updated = true;
                                     busy = true;
                                     self.current_level_remaining += total_level_tasks;
+                                    self.sent_level_tasks += total_level_tasks;
+                                    sent_value_addr = value_addr;
                                 }
                                 Err(watcher_task) => {
                                     current_task.ret_task(watcher_task);
@@ -83,10 +281,12 @@ impl SimComponent for Trail {
                             }
                         }
                     } else {
-                        // no more tasks, finish the current task
+                        // no more tasks, finish the current task after the drain
                         busy = true;
-                        updated = true;
-                        self.current_working_task = None;
+                        if self.round_drained(shared_status) {
+                            self.current_working_task = None;
+                            updated = true;
+                        }
                     }
                 }
                 // get new task
@@ -106,10 +306,14 @@ impl SimComponent for Trail {
                     if let Some(watcher_task) = current_task.pop_next_task() {
                         busy = true;
                         let watcher_unit_id = watcher_task.get_watcher_pe_id(self.total_watcher);
+                        let total_level_tasks = watcher_task.get_total_level_tasks();
+                        let value_addr = watcher_task.value_addr;
                         match self.watcher_sender[watcher_unit_id].send(watcher_task) {
                             Ok(_) => {
                                 updated = true;
                                 busy = true;
+                                self.sent_level_tasks += total_level_tasks;
+                                sent_value_addr = value_addr;
                             }
                             Err(watcher_task) => {
                                 current_task.ret_task(watcher_task);
@@ -117,10 +321,12 @@ impl SimComponent for Trail {
                             }
                         }
                     } else {
-                        // no more tasks, finish the current task
+                        // no more tasks, finish the current task after the drain
                         busy = true;
-                        updated = true;
-                        self.current_working_task = None;
+                        if self.round_drained(shared_status) {
+                            self.current_working_task = None;
+                            updated = true;
+                        }
                     }
                 }
                 // get new task
@@ -135,6 +341,17 @@ impl SimComponent for Trail {
                 }
             }
         };
+        // write the value of the sent assignment to the coherence unit
+        if let Some(value_write_sender) = &self.value_write_sender {
+            self.pending_value_writes.extend(sent_value_addr);
+            if let Some(value_addr) = self.pending_value_writes.pop_front() {
+                busy = true;
+                match value_write_sender.send(value_addr) {
+                    Ok(_) => updated = true,
+                    Err(value_addr) => self.pending_value_writes.push_front(value_addr),
+                }
+            }
+        }
 
         if busy && !updated {
             tracing::debug!("trail is busy but not updated");
@@ -142,3 +359,63 @@ impl SimComponent for Trail {
         (busy, updated)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        satacc::SataccMinisatTask,
+        sim::{ChannelBuilder, SimRunner},
+        test_utils,
+    };
+
+    /// the second assignment waits for the first one to imply it
+    fn build_round() -> SingleRoundTask {
+        let mut task = SataccMinisatTask::new();
+        task.start_new_assgin();
+        task.add_watcher_task(0, 0, 4096, 0);
+        task.add_watcher_task(1, 64, 8192, 2);
+        task.pop_next_task().unwrap()
+    }
+
+    #[test]
+    fn test_functional_stuck() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (watcher_senders, _watcher_receivers) = channel_builder.sim_channel_array(10, 1);
+        let (task_sender, task_receiver) = channel_builder.sim_channel(10);
+        let trail = Trail::new(watcher_senders, task_receiver, false, 1).with_functional(true);
+        let mut config = Config::default();
+        config.functional_config.enable = true;
+        let mut sim_runner = SimRunner::new(trail, SataccStatus::new(config));
+        assert!(task_sender.send(build_round()).is_ok());
+        // the watcher never finishes the first assignment, the trail is stuck instead of waiting forever
+        assert!(sim_runner.run().is_err());
+        assert!(sim_runner.get_current_cycle() < 100);
+    }
+
+    #[test]
+    fn test_aborting_stuck() {
+        test_utils::init();
+        let channel_builder = ChannelBuilder::new();
+        let (watcher_senders, _watcher_receivers) = channel_builder.sim_channel_array(10, 1);
+        let (task_sender, task_receiver) = channel_builder.sim_channel(10);
+        let trail = Trail::new(watcher_senders, task_receiver, false, 1);
+        let mut config = Config::default();
+        config.conflict_config.enable = true;
+        let conflict_config = config.conflict_config;
+        let mut sim_runner = SimRunner::new(trail, SataccStatus::new(config));
+        assert!(task_sender.send(build_round()).is_ok());
+        // the assignments are sent, but the watcher never finishes them
+        assert!(sim_runner.run().is_err());
+        let conflict_cycle = sim_runner.get_current_cycle();
+        sim_runner.get_shared_status_mut().conflict_cycle = Some(conflict_cycle);
+        // the watcher never cancels them either, the abort is stuck after the flush instead of waiting forever
+        assert!(sim_runner.run().is_err());
+        let flush_end = conflict_config.flush_end(conflict_cycle);
+        assert!(sim_runner.get_current_cycle() >= flush_end);
+        assert!(sim_runner.get_current_cycle() < flush_end + 100);
+        assert!(sim_runner.get_shared_status().conflict_cycle.is_some());
+    }
+}
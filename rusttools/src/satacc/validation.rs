@@ -0,0 +1,286 @@
+use std::fmt::Display;
+
+use eyre::{bail, Result};
+use libc::c_char;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    satacc_minisat_task::{BuilderStatus, SataccMinisatTask, SingleRoundTask},
+    trace::{path_from_c, TraceReader, TraceRecord},
+};
+
+/// the problem of a task that the simulator cannot run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValidationErrorKind {
+    /// the clause reads no value, the clause unit needs at least one value request
+    EmptyClauseValues,
+    /// the `clause_value_id` and `clause_value_addr` have different lengths
+    MismatchedClauseValues { addrs: usize, ids: usize },
+    /// the record of the trace cannot be applied
+    InvalidRecord {
+        record: usize,
+        status: BuilderStatus,
+    },
+}
+
+/// # ValidationError
+/// a problem of a task, located by the indices of the round, the assignment and the watcher
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationError {
+    pub round: usize,
+    pub assignment: Option<usize>,
+    pub watcher: Option<usize>,
+    pub kind: ValidationErrorKind,
+}
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "round {}", self.round)?;
+        if let Some(assignment) = self.assignment {
+            write!(f, " assignment {assignment}")?;
+        }
+        if let Some(watcher) = self.watcher {
+            write!(f, " watcher {watcher}")?;
+        }
+        match &self.kind {
+            ValidationErrorKind::EmptyClauseValues => write!(f, ": the clause reads no value"),
+            ValidationErrorKind::MismatchedClauseValues { addrs, ids } => write!(
+                f,
+                ": the clause has {addrs} value addrs but {ids} value ids"
+            ),
+            ValidationErrorKind::InvalidRecord { record, status } => {
+                write!(f, ": record {record} is invalid: {status:?}")
+            }
+        }
+    }
+}
+
+/// check the round, `round_index` is used in the errors
+pub fn validate_round(round_index: usize, round: &SingleRoundTask) -> Vec<ValidationError> {
+    let mut errors = vec![];
+    for (assignment_index, assignment) in round.assignments.iter().enumerate() {
+        for (watcher_index, watcher) in assignment.single_watcher_tasks.iter().enumerate() {
+            let mut error = |kind| {
+                errors.push(ValidationError {
+                    round: round_index,
+                    assignment: Some(assignment_index),
+                    watcher: Some(watcher_index),
+                    kind,
+                })
+            };
+            let Some(clause_data) = &watcher.clause_data else {
+                continue;
+            };
+            if clause_data.clause_value_addr.is_empty() {
+                error(ValidationErrorKind::EmptyClauseValues);
+            }
+            if clause_data.clause_value_addr.len() != clause_data.clause_value_id.len() {
+                error(ValidationErrorKind::MismatchedClauseValues {
+                    addrs: clause_data.clause_value_addr.len(),
+                    ids: clause_data.clause_value_id.len(),
+                });
+            }
+        }
+    }
+    errors
+}
+
+/// check all the rounds of the task
+pub fn validate_task(task: &SataccMinisatTask) -> Vec<ValidationError> {
+    task.decisions
+        .iter()
+        .enumerate()
+        .flat_map(|(round_index, round)| validate_round(round_index, round))
+        .collect()
+}
+
+/// check the records and the rounds of the trace file, only one round is in memory
+/// - an invalid record is reported and skipped, the following records are still checked
+pub fn validate_trace(path: &str) -> Result<Vec<ValidationError>> {
+    let mut reader = TraceReader::open(path)?;
+    let mut errors = vec![];
+    let mut round = SataccMinisatTask::new();
+    let mut round_index = 0;
+    let mut record_index = 0;
+    while let Some(record) = reader.next_record()? {
+        if let TraceRecord::NewRound = record {
+            if let Some(finished) = round.pop_next_task() {
+                errors.extend(validate_round(round_index, &finished));
+                round_index += 1;
+            }
+        }
+        let status = round.apply_record(record);
+        if status != BuilderStatus::Ok {
+            errors.push(ValidationError {
+                round: round_index,
+                assignment: None,
+                watcher: None,
+                kind: ValidationErrorKind::InvalidRecord {
+                    record: record_index,
+                    status,
+                },
+            });
+        }
+        record_index += 1;
+    }
+    if let Some(finished) = round.pop_next_task() {
+        errors.extend(validate_round(round_index, &finished));
+    }
+    Ok(errors)
+}
+
+/// an error that reports all the problems together, so they can be fixed in one pass
+pub fn ensure_valid(errors: &[ValidationError]) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let report = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    bail!(
+        "invalid task with {} problems:\n{}",
+        errors.len(),
+        report.join("\n")
+    );
+}
+
+impl SataccMinisatTask {
+    /// check the task before simulating it, log each problem and return the number of problems
+    #[no_mangle]
+    pub extern "C" fn check_task(&self) -> usize {
+        let errors = validate_task(self);
+        for error in &errors {
+            tracing::error!("{error}");
+        }
+        errors.len()
+    }
+    /// check the trace file, log each problem and return the number of problems, -1 if the trace cannot be read
+    #[no_mangle]
+    pub extern "C" fn check_trace(path: *const c_char) -> isize {
+        match path_from_c(path).and_then(validate_trace) {
+            Ok(errors) => {
+                for error in &errors {
+                    tracing::error!("{error}");
+                }
+                errors.len() as isize
+            }
+            Err(e) => {
+                tracing::error!("cannot read the trace: {e:?}");
+                -1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        satacc::{trace::TraceWriter, Simulator},
+    };
+
+    #[test]
+    fn test_validate_task() {
+        let mut task = SataccMinisatTask::new();
+        assert_eq!(task.add_watcher_task(0, 64, 128, 3), BuilderStatus::NoRound);
+        task.start_new_assgin();
+        assert_eq!(
+            task.add_single_watcher_task(320, 1024, 7, 20, 3),
+            BuilderStatus::NoAssignment
+        );
+        task.add_watcher_task(0, 64, 128, 3);
+        task.add_single_watcher_task_no_clause(256, 3);
+        assert_eq!(
+            task.add_single_watcher_clause_value_addr(4100, 5),
+            BuilderStatus::NoClauseWatcher
+        );
+        assert_eq!(
+            task.add_watcher_task_by_id(0, 3, 1),
+            BuilderStatus::NoLayout
+        );
+        task.add_single_watcher_task(320, 1024, 7, 20, 3);
+        task.start_new_assgin();
+        task.add_watcher_task(0, 64, 128, 3);
+        task.add_single_watcher_task(320, 1024, 7, 20, 3);
+        task.add_single_watcher_clause_value_addr(4100, 5);
+        task.decisions[1].assignments[0].single_watcher_tasks[0]
+            .clause_data
+            .as_mut()
+            .unwrap()
+            .clause_value_id
+            .push(6);
+        let errors = validate_task(&task);
+        assert_eq!(
+            errors,
+            [
+                ValidationError {
+                    round: 0,
+                    assignment: Some(0),
+                    watcher: Some(1),
+                    kind: ValidationErrorKind::EmptyClauseValues,
+                },
+                ValidationError {
+                    round: 1,
+                    assignment: Some(0),
+                    watcher: Some(0),
+                    kind: ValidationErrorKind::MismatchedClauseValues { addrs: 1, ids: 2 },
+                },
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "round 0 assignment 0 watcher 1: the clause reads no value"
+        );
+        // the simulator reports all the problems before simulating any round
+        let simulator = Simulator::new_from_config(Config::default());
+        let error = simulator.run_task(&mut task).unwrap_err().to_string();
+        assert!(error.contains("2 problems"));
+        assert!(error.contains("round 1 assignment 0 watcher 0"));
+        assert_eq!(task.decisions.len(), 2);
+    }
+
+    #[test]
+    fn test_validate_trace() {
+        let path = "test_validate_trace.trace";
+        let mut writer = TraceWriter::create(path).unwrap();
+        for record in [
+            TraceRecord::WatcherTask {
+                level: 0,
+                meta_data_addr: 64,
+                watcher_addr: 128,
+                watcher_id: 3,
+            },
+            TraceRecord::NewRound,
+            TraceRecord::WatcherTask {
+                level: 0,
+                meta_data_addr: 64,
+                watcher_addr: 128,
+                watcher_id: 3,
+            },
+            TraceRecord::SingleWatcher {
+                blocker_addr: 320,
+                clause_addr: 1024,
+                clause_id: 7,
+                processing_time: 20,
+                watcher_id: 3,
+            },
+        ] {
+            writer.write(&record).unwrap();
+        }
+        writer.finish().unwrap();
+        let errors = validate_trace(path).unwrap();
+        assert!(TraceReader::open(path).unwrap().read_task().is_err());
+        let simulator = Simulator::new_from_config(Config::default());
+        let error = simulator.run_trace(path).unwrap_err().to_string();
+        assert!(error.contains("2 problems"));
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(SataccMinisatTask::check_trace(std::ptr::null()), -1);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].kind,
+            ValidationErrorKind::InvalidRecord {
+                record: 0,
+                status: BuilderStatus::NoRound
+            }
+        );
+        assert_eq!(errors[1].kind, ValidationErrorKind::EmptyClauseValues);
+    }
+}
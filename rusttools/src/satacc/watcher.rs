@@ -7,8 +7,9 @@ use crate::{
 
 use super::{
     icnt::IcntMsgWrapper,
+    pipeline::{Pipeline, PipelineConfig},
     satacc_minisat_task::{ClauseTask, WatcherTask},
-    MemReq, SataccStatus,
+    MemReq, MemReqKind, SataccStatus,
 };
 
 pub struct Watcher {
@@ -22,7 +23,8 @@ pub struct Watcher {
     single_watcher_task_queue: VecDeque<ClauseTask>,
     single_watcher_value_finished_queue: VecDeque<ClauseTask>,
     single_watcher_process_finished_queue: VecDeque<ClauseTask>,
-    current_processing_task: Option<(usize, ClauseTask)>,
+    /// the single watchers that are checking the blocker
+    processing_tasks: Pipeline<ClauseTask>,
     total_watchers: usize,
     watcher_pe_id: usize,
     mem_req_id_to_watcher_task: BTreeMap<usize, WatcherTask>,
@@ -53,7 +55,8 @@ impl Watcher {
             single_watcher_task_queue: VecDeque::new(),
             single_watcher_value_finished_queue: VecDeque::new(),
             single_watcher_process_finished_queue: VecDeque::new(),
-            current_processing_task: None,
+            // a watcher need 2 cycle to test if it's time to read the clause
+            processing_tasks: Pipeline::new(PipelineConfig::single(2)),
             total_watchers,
             watcher_pe_id,
             mem_req_id_to_watcher_task: BTreeMap::new(),
@@ -63,6 +66,34 @@ impl Watcher {
             total_blocker_requests_ongoing: 0,
         }
     }
+    /// check the blockers in the pipeline
+    pub fn with_pipeline(mut self, pipeline_config: PipelineConfig) -> Self {
+        self.processing_tasks = Pipeline::new(pipeline_config);
+        self
+    }
+    /// cancel the watcher lists and the single watchers that are not issued, return true if any is cancelled
+    /// - the requests in flight are not cancelled, their tasks are cancelled when they return
+    fn cancel_pending_tasks(&mut self, context: &mut SataccStatus) -> bool {
+        let watcher_tasks = std::iter::from_fn(|| self.watcher_task_receiver.recv().ok())
+            .chain(self.meta_finished_queue.drain(..))
+            .chain(self.data_finished_queue.drain(..))
+            .collect::<Vec<_>>();
+        let single_tasks = self.single_watcher_task_queue.len()
+            + self.single_watcher_value_finished_queue.len()
+            + self.single_watcher_process_finished_queue.len()
+            + self.processing_tasks.clear();
+        self.single_watcher_task_queue.clear();
+        self.single_watcher_value_finished_queue.clear();
+        self.single_watcher_process_finished_queue.clear();
+        for watcher_task in watcher_tasks.iter() {
+            context.finish_level_tasks(watcher_task.get_total_level_tasks());
+        }
+        context.finish_level_tasks(single_tasks);
+        let statistics = &mut context.statistics.conflict_statistics;
+        statistics.cancelled_assignments += watcher_tasks.len();
+        statistics.cancelled_watchers += single_tasks;
+        !watcher_tasks.is_empty() || single_tasks > 0
+    }
 }
 #[derive(Debug)]
 enum IdleReason {
@@ -79,6 +110,11 @@ impl SimComponent for Watcher {
         let mut busy = false;
         let mut updated = false;
         let mut reason = IdleReason::NoTask;
+        // a conflict is signaled, drop the work of the round
+        if context.is_aborting(current_cycle) && self.cancel_pending_tasks(context) {
+            busy = true;
+            updated = true;
+        }
         // first check the new arrived watcher tasks
         if self.total_ongoing_meta_mem_reqs < 256 && self.meta_finished_queue.len() < 256 {
             if let Ok(watcher_task) = self.watcher_task_receiver.recv() {
@@ -161,7 +197,7 @@ impl SimComponent for Watcher {
                 context.statistics.watcher_statistics[self.watcher_pe_id].total_watchers +=
                     signale_watcher_tasks.len();
                 self.single_watcher_task_queue.extend(signale_watcher_tasks);
-                context.current_level_finished_tasks += 1;
+                context.finish_level_tasks(1);
             }
         } else {
             let single_watcher_task_queue_len = self.single_watcher_task_queue.len();
@@ -178,7 +214,8 @@ impl SimComponent for Watcher {
             if let Some(single_task) = self.single_watcher_task_queue.pop_front() {
                 let blocker_req = single_task.get_blocker_req(self.total_watchers, context);
                 let addr = blocker_req.addr;
-                let mem_id = ((addr >> 6) & ((1 << 3) - 1)) as usize;
+                let mem_id =
+                    context.get_l3_partition(addr, blocker_req.watcher_pe_id, MemReqKind::Blocker);
 
                 let id = blocker_req.id;
                 busy = true;
@@ -210,20 +247,19 @@ impl SimComponent for Watcher {
             );
         }
 
-        // update current processing task
+        // update the processing tasks
         if self.single_watcher_process_finished_queue.len() < 256 {
-            if let Some((finished_cycle, single_task)) = self.current_processing_task.take() {
+            if !self.processing_tasks.is_empty() {
                 // currently processing task, so busy is true
                 busy = true;
                 updated = true;
-
-                if finished_cycle > current_cycle {
-                    // not finished yet
-                    self.current_processing_task = Some((finished_cycle, single_task));
-                } else {
-                    self.single_watcher_process_finished_queue
-                        .push_back(single_task);
-                }
+            }
+            while self.single_watcher_process_finished_queue.len() < 256 {
+                let Some(single_task) = self.processing_tasks.pop_finished(current_cycle) else {
+                    break;
+                };
+                self.single_watcher_process_finished_queue
+                    .push_back(single_task);
             }
         } else {
             let single_watcher_process_finished_queue_len =
@@ -234,15 +270,16 @@ impl SimComponent for Watcher {
             )
         }
 
-        // process the watcher
-        if self.current_processing_task.is_none() {
-            if let Some(single_task) = self.single_watcher_value_finished_queue.pop_front() {
-                busy = true;
-                updated = true;
-                // a watcher need 2 cycle to test if it's time to read the clause
-                let process_time = 2;
-                self.current_processing_task = Some((current_cycle + process_time, single_task));
-            }
+        // process the watchers, test if it's time to read the clause
+        while self.processing_tasks.can_issue(current_cycle) {
+            let Some(single_task) = self.single_watcher_value_finished_queue.pop_front() else {
+                break;
+            };
+            busy = true;
+            updated = true;
+            let finished_cycle = current_cycle + self.processing_tasks.latency();
+            self.processing_tasks
+                .issue(single_task, current_cycle, finished_cycle);
         }
 
         // then send the task to clause unit
@@ -269,7 +306,7 @@ impl SimComponent for Watcher {
                     }
                 }
             } else {
-                context.current_level_finished_tasks += 1;
+                context.finish_level_tasks(1);
             }
         }
 
@@ -277,6 +314,9 @@ impl SimComponent for Watcher {
 
         if let Ok(mem_req) = self.cache_mem_icnt_sender.in_port.recv() {
             busy = true;
+            if context.is_aborting(current_cycle) {
+                context.statistics.conflict_statistics.drained_mem_reqs += 1;
+            }
             match mem_req.msg.req_type {
                 MemReqType::WatcherReadMetaData => {
                     updated = true;
@@ -1,14 +1,30 @@
 use crate::sim::{ChannelBuilder, InOutPort, SimComponent, SimReciver, SimSender};
 
 use super::{
-    cache::{CacheId, CacheWithFixTime},
+    cache::{AccessResult, CacheId, CacheWithFixTime},
     clause::ClauseUnit,
+    coherence::CoherenceMsg,
     icnt::IcntMsgWrapper,
     satacc_minisat_task::{ClauseTask, WatcherTask},
     watcher::Watcher,
-    CacheConfig, MemReq, MemReqType, SataccStatus,
+    CacheConfig, CoherenceProtocol, MemReq, MemReqKind, MemReqType, PipelineConfig, SataccStatus,
 };
 
+/// the parameters shared by the watcher interfaces of all the pes
+#[derive(Debug, Clone)]
+pub struct WatcherInterfaceConfig {
+    pub queue_size: usize,
+    /// the private cache
+    pub cache_config: CacheConfig,
+    pub hit_latency: usize,
+    pub private_cache_miss_latency: usize,
+    pub num_clauses_per_watcher: usize,
+    pub total_watchers: usize,
+    pub pipeline_clause_value_read: bool,
+    pub watcher_pipeline: PipelineConfig,
+    pub clause_pipeline: PipelineConfig,
+}
+
 pub struct WatcherInterface {
     /// the interface for sending and receiving mem requests
     mem_icnt_interface_receiver: SimReciver<IcntMsgWrapper<MemReq>>,
@@ -23,12 +39,14 @@ pub struct WatcherInterface {
     watcher_mem_sender: SimSender<IcntMsgWrapper<MemReq>>,
     clause_mem_senders: Vec<SimSender<IcntMsgWrapper<MemReq>>>,
     // watcher_private_cache_sender: SimSender<IcntMsgWrapper<MemReq>>,
-    // clause_private_cache_senders: Vec<SimSender<IcntMsgWrapper<MemReq>>>,
+    clause_private_cache_senders: Vec<SimSender<IcntMsgWrapper<MemReq>>>,
     clause_task_senders: Vec<SimSender<IcntMsgWrapper<ClauseTask>>>,
 
     // private cache
     private_cache: CacheWithFixTime,
     private_cache_out_receiver: SimReciver<IcntMsgWrapper<MemReq>>,
+    /// the invalidations and updates of the value lines in the private cache
+    coherence_receiver: Option<SimReciver<CoherenceMsg>>,
     num_clauses_per_watcher: usize,
 }
 
@@ -38,15 +56,20 @@ impl WatcherInterface {
         task_icnt: InOutPort<IcntMsgWrapper<ClauseTask>>,
         watcher_task_in: SimReciver<WatcherTask>,
         channel_builder: &ChannelBuilder,
-        queue_size: usize,
-        cache_config: &CacheConfig,
-        hit_latency: usize,
-        private_cache_miss_latency: usize,
-        num_clauses_per_watcher: usize,
         watcher_pe_id: usize,
-        total_watchers: usize,
-        pipeline_clause_value_read: bool,
+        config: &WatcherInterfaceConfig,
     ) -> Self {
+        let &WatcherInterfaceConfig {
+            queue_size,
+            ref cache_config,
+            hit_latency,
+            private_cache_miss_latency,
+            num_clauses_per_watcher,
+            total_watchers,
+            pipeline_clause_value_read,
+            watcher_pipeline,
+            clause_pipeline,
+        } = config;
         let (watcher_mem_sender, watcher_mem_receiver) = channel_builder.sim_channel(queue_size);
         let watcher_icnt_interface = InOutPort {
             in_port: watcher_mem_receiver,
@@ -74,7 +97,8 @@ impl WatcherInterface {
             watcher_private_cache_in.1,
             total_watchers,
             watcher_pe_id,
-        );
+        )
+        .with_pipeline(watcher_pipeline);
         let (clause_task_senders, clause_mem_senders, clauses) = (0..num_clauses_per_watcher)
             .zip(clauses_private_cache_in.1)
             .enumerate()
@@ -97,7 +121,8 @@ impl WatcherInterface {
                     total_watchers,
                     clause_pe_id,
                     pipeline_clause_value_read,
-                );
+                )
+                .with_pipeline(clause_pipeline);
                 (clause_task_sender, claause_mem_sender, clause)
             })
             .fold(
@@ -118,6 +143,8 @@ impl WatcherInterface {
             hit_latency,
             private_cache_miss_latency,
             CacheId::PrivateCache(watcher_pe_id),
+            // the private cache has one link to the l3 cache
+            1,
         );
 
         Self {
@@ -131,10 +158,44 @@ impl WatcherInterface {
             private_cache,
             private_cache_out_receiver: private_cache_out.1,
             // watcher_private_cache_sender: watcher_private_cache_in.0,
-            // clause_private_cache_senders: clauses_private_cache_in.0,
+            clause_private_cache_senders: clauses_private_cache_in.0,
+            coherence_receiver: None,
             num_clauses_per_watcher,
         }
     }
+    /// receive the coherence messages of the value lines in the private cache
+    pub fn with_coherence_receiver(mut self, coherence_receiver: SimReciver<CoherenceMsg>) -> Self {
+        self.coherence_receiver = Some(coherence_receiver);
+        self
+    }
+    /// the lines of the private cache, see [`super::cache::FastCache::resident_lines`]
+    pub fn private_cache_lines(&self) -> Vec<(u64, bool)> {
+        self.private_cache.fast_cache.resident_lines()
+    }
+    /// access the private cache without timing, the directory still tracks the value lines
+    pub fn warm_up_private_cache(
+        &mut self,
+        addr: u64,
+        kind: MemReqKind,
+        shared_status: &mut SataccStatus,
+        watcher_pe_id: usize,
+    ) {
+        let (result, evicted) = self.private_cache.fast_cache.access_with_evict(addr, kind);
+        if shared_status.statistics.config.coherence_config.protocol
+            != CoherenceProtocol::DirectoryInvalidate
+        {
+            return;
+        }
+        let directory = &mut shared_status.value_directory;
+        if let Some(evicted) = evicted {
+            directory.remove_sharer(evicted.addr, watcher_pe_id);
+        }
+        if let AccessResult::Miss(line_addr) = result {
+            if kind == MemReqKind::ClauseValue && !self.private_cache.fast_cache.is_bypass(kind) {
+                directory.add_sharer(line_addr, watcher_pe_id);
+            }
+        }
+    }
 }
 
 impl SimComponent for WatcherInterface {
@@ -165,7 +226,8 @@ impl SimComponent for WatcherInterface {
         if let Ok(mem_req) = self.mem_icnt_interface_receiver.recv() {
             busy = true;
             match mem_req.msg.req_type {
-                MemReqType::ClauseReadData(clause_inner_id) => {
+                MemReqType::ClauseReadData(clause_inner_id)
+                | MemReqType::ClauseWriteWatchList(clause_inner_id) => {
                     match self.clause_mem_senders[clause_inner_id].send(mem_req) {
                         Ok(_) => {
                             tracing::debug!(
@@ -219,26 +281,24 @@ impl SimComponent for WatcherInterface {
         }
         // recv the private cache, it should contains clause value and watcher
         if let Ok(mem_req) = self.private_cache_out_receiver.recv() {
-            // busy = true;
-            // let msg_id = mem_req.msg.id;
+            busy = true;
             tracing::debug!(
                 "WatcherInterface Recv mem req from private cache! id: {} cycle: {current_cycle}",
                 mem_req.msg.id,
             );
             match mem_req.msg.req_type {
-                MemReqType::ClauseReadValue(_clause_inner_id) => {
-                    unreachable!();
-                    // match self.clause_private_cache_senders[clause_inner_id].send(mem_req) {
-                    //     Ok(_) => {
-                    //         tracing::debug!(
-                    //             "WatcherInterface Send mem req id {msg_id} to clause:{clause_inner_id}! {current_cycle}",
-                    //         );
-                    //         updated = true;
-                    //     }
-                    //     Err(mem_req) => {
-                    //         self.private_cache_out_receiver.ret(mem_req);
-                    //     }
-                    // }
+                MemReqType::ClauseReadValue(clause_inner_id) => {
+                    match self.clause_private_cache_senders[clause_inner_id].send(mem_req) {
+                        Ok(_) => {
+                            tracing::debug!(
+                                "WatcherInterface Send private cache value to clause:{clause_inner_id}! {current_cycle}",
+                            );
+                            updated = true;
+                        }
+                        Err(mem_req) => {
+                            self.private_cache_out_receiver.ret(mem_req);
+                        }
+                    }
                 }
                 MemReqType::WatcherReadBlocker => {
                     // match self.watcher_private_cache_sender.send(mem_req) {
@@ -255,6 +315,26 @@ impl SimComponent for WatcherInterface {
                 _ => unreachable!(),
             }
         }
+        // the write of the value makes the copy in the private cache stale
+        if let Some(coherence_receiver) = &self.coherence_receiver {
+            if let Ok(msg) = coherence_receiver.recv() {
+                busy = true;
+                updated = true;
+                let stat = &mut shared_status.statistics.coherence_statistics;
+                match msg {
+                    CoherenceMsg::Invalidate(line_addr) => {
+                        if self.private_cache.fast_cache.invalidate(line_addr) {
+                            stat.invalidated_lines += 1;
+                        }
+                    }
+                    CoherenceMsg::Update(line_addr) => {
+                        if self.private_cache.fast_cache.probe(line_addr) {
+                            stat.updated_lines += 1;
+                        }
+                    }
+                }
+            }
+        }
         // let (watcher_busy, watcher_updated) = self.watcher.update(shared_status, current_cycle);
         // let (clause_busy, clause_updated) = self.clauses.update(shared_status, current_cycle);
         // let (cache_busy, cache_updated) = self.private_cache.update(shared_status, current_cycle);
@@ -288,13 +368,27 @@ mod test {
         config::Config,
         satacc::{
             satacc_minisat_task::{ClauseData, ClauseTask, WatcherTask},
-            watcher_interface::WatcherInterface,
-            CacheConfig, SataccStatus,
+            watcher_interface::{WatcherInterface, WatcherInterfaceConfig},
+            SataccStatus,
         },
         sim::{ChannelBuilder, SimRunner},
         test_utils,
     };
 
+    fn build_config() -> WatcherInterfaceConfig {
+        WatcherInterfaceConfig {
+            queue_size: 10,
+            cache_config: test_utils::cache_config(),
+            hit_latency: 10,
+            private_cache_miss_latency: 120,
+            num_clauses_per_watcher: 2,
+            total_watchers: 1,
+            pipeline_clause_value_read: true,
+            watcher_pipeline: Config::default().watcher_pipeline,
+            clause_pipeline: Config::default().clause_pipeline,
+        }
+    }
+
     #[test]
     fn test_watcher_interface() {
         test_utils::init();
@@ -308,20 +402,8 @@ mod test {
             task_port_in,
             watcher_task_receiver,
             &channel_builder,
-            10,
-            &CacheConfig {
-                sets: 2,
-                associativity: 2,
-                block_size: 4,
-                channels: 1,
-                alway_hit: false,
-            },
-            10,
-            120,
-            2,
             0,
-            1,
-            true,
+            &build_config(),
         );
         let mut config = Config::default();
         config.n_clauses = 2;
@@ -334,6 +416,7 @@ mod test {
                 meta_data_addr: 0,
                 watcher_addr: 0,
                 watcher_id: 0,
+                value_addr: None,
                 single_watcher_tasks: VecDeque::new(),
             })
             .unwrap();
@@ -365,20 +448,8 @@ mod test {
             task_port_in,
             watcher_task_receiver,
             &channel_builder,
-            10,
-            &CacheConfig {
-                sets: 2,
-                associativity: 2,
-                block_size: 4,
-                channels: 1,
-                alway_hit: false,
-            },
-            10,
-            120,
-            2,
             0,
-            1,
-            true,
+            &build_config(),
         );
         let mut config = Config::default();
         config.n_clauses = 2;
@@ -396,6 +467,7 @@ mod test {
                 meta_data_addr: 0,
                 watcher_addr: 0,
                 watcher_id: 0,
+                value_addr: None,
                 single_watcher_tasks: [clause_task].into(),
             })
             .unwrap();
@@ -427,20 +499,8 @@ mod test {
             task_port_in,
             watcher_task_receiver,
             &channel_builder,
-            10,
-            &CacheConfig {
-                sets: 2,
-                associativity: 2,
-                block_size: 4,
-                channels: 1,
-                alway_hit: false,
-            },
-            10,
-            120,
-            2,
             0,
-            1,
-            true,
+            &build_config(),
         );
         let mut config = Config::default();
         config.n_clauses = 2;
@@ -453,9 +513,10 @@ mod test {
             clause_data: Some(ClauseData {
                 clause_id: 1,
                 clause_addr: 100,
-                clause_processing_time: 100,
+                clause_processing_time: Some(100),
                 clause_value_addr: vec![200, 300, 400],
                 clause_value_id: vec![2, 3, 4],
+                ..Default::default()
             }),
         };
         watcher_task_sender
@@ -464,6 +525,7 @@ mod test {
                 meta_data_addr: 0,
                 watcher_addr: 0,
                 watcher_id: 0,
+                value_addr: None,
                 single_watcher_tasks: [clause_task].into(),
             })
             .unwrap();
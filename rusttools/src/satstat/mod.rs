@@ -1,11 +1,24 @@
-use std::{collections::BTreeMap, fs::File};
+use std::{collections::BTreeMap, fs::File, path::Path};
 
 use ndarray::array;
 use ndarray_stats::histogram::{Bins, Edges, Grid, Histogram};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::init_tracing;
 
+/// the edges of the bins of all the histograms
+pub(crate) fn histogram_edges() -> Vec<usize> {
+    vec![
+        0, 10, 20, 30, 40, 50, 60, 70, 80, 90, 100, 200, 300, 400, 500, 600, 700, 800, 900, 1000,
+        2000, 3000, 4000, 5000, 6000, 7000, 8000, 9000, 10000, 20000, 30000, 40000, 50000, 60000,
+        70000, 80000, 90000, 100000, 200000, 300000, 400000, 500000, 600000, 700000, 800000,
+        900000, 1000000, 2000000, 3000000, 4000000, 5000000, 6000000, 7000000, 8000000, 9000000,
+        10000000, 20000000, 30000000, 40000000, 50000000, 60000000, 70000000, 80000000, 90000000,
+        100000000, 200000000, 300000000, 400000000, 500000000, 600000000, 700000000, 800000000,
+        900000000, 1000000000,
+    ]
+}
+
 /// the sat runtime statistics
 pub struct Satstat {
     current_decision_cf: usize,
@@ -16,6 +29,8 @@ pub struct Satstat {
     current_watcher_dc: usize,
     current_clause_dc: usize,
     current_clause_with_data_dc: usize,
+    /// the assignments of each level of the current decision
+    current_level_assignments_dc: BTreeMap<usize, usize>,
 
     /// per watcher clauses
     clauses_per_watcher: Histogram<usize>,
@@ -35,6 +50,10 @@ pub struct Satstat {
     clauses_per_conflict: Histogram<usize>,
     // per conflict clause with data
     clauses_per_conflict_with_data: Histogram<usize>,
+    /// the count of each length of the read clauses, the lengths are too small for the histogram bins
+    clause_lengths: BTreeMap<usize, usize>,
+    /// the count of each number of assignments in a level of a decision
+    assignments_per_level: BTreeMap<usize, usize>,
 
     total_conflicts: usize,
     total_decisions: usize,
@@ -51,15 +70,7 @@ impl Satstat {
 
 impl Default for Satstat {
     fn default() -> Self {
-        let edges = Edges::from(vec![
-            0, 10, 20, 30, 40, 50, 60, 70, 80, 90, 100, 200, 300, 400, 500, 600, 700, 800, 900,
-            1000, 2000, 3000, 4000, 5000, 6000, 7000, 8000, 9000, 10000, 20000, 30000, 40000,
-            50000, 60000, 70000, 80000, 90000, 100000, 200000, 300000, 400000, 500000, 600000,
-            700000, 800000, 900000, 1000000, 2000000, 3000000, 4000000, 5000000, 6000000, 7000000,
-            8000000, 9000000, 10000000, 20000000, 30000000, 40000000, 50000000, 60000000, 70000000,
-            80000000, 90000000, 100000000, 200000000, 300000000, 400000000, 500000000, 600000000,
-            700000000, 800000000, 900000000, 1000000000,
-        ]);
+        let edges = Edges::from(histogram_edges());
         let bins = Bins::new(edges);
         let grid = Grid::from(vec![bins]);
         Self {
@@ -76,6 +87,7 @@ impl Default for Satstat {
             current_clause_dc: 0,
             current_clause_with_data_dc: 0,
             current_watcher_dc: 0,
+            current_level_assignments_dc: BTreeMap::new(),
             watchers_per_decision: Histogram::new(grid.clone()),
             clauses_per_watcher: Histogram::new(grid.clone()),
             clause_read_per_watcher: Histogram::new(grid.clone()),
@@ -85,18 +97,28 @@ impl Default for Satstat {
             watchers_per_conflict: Histogram::new(grid.clone()),
             clauses_per_conflict: Histogram::new(grid.clone()),
             clauses_per_conflict_with_data: Histogram::new(grid.clone()),
+            clause_lengths: BTreeMap::new(),
+            assignments_per_level: BTreeMap::new(),
         }
     }
 }
 
-#[derive(Serialize)]
-struct FinalResult {
-    total_conflicts: usize,
-    total_decisions: usize,
-    total_clauses: usize,
-    total_watchers: usize,
-    total_clauses_with_data: usize,
-    histograms: BTreeMap<String, Vec<usize>>,
+/// the content of `final_result_histo.json`
+#[derive(Serialize, Deserialize)]
+pub(crate) struct FinalResult {
+    pub total_conflicts: usize,
+    pub total_decisions: usize,
+    pub total_clauses: usize,
+    pub total_watchers: usize,
+    pub total_clauses_with_data: usize,
+    /// the counts of the bins of [`histogram_edges`]
+    pub histograms: BTreeMap<String, Vec<usize>>,
+    /// the count of each clause length, empty if the solver does not report it
+    #[serde(default)]
+    pub clause_lengths: BTreeMap<usize, usize>,
+    /// the count of each number of assignments in a level, empty if the solver does not report it
+    #[serde(default)]
+    pub assignments_per_level: BTreeMap<usize, usize>,
 }
 impl Satstat {
     #[no_mangle]
@@ -108,7 +130,7 @@ impl Satstat {
     #[no_mangle]
     pub extern "C" fn delete_satstat_pointer(satstat: *mut Satstat) {
         unsafe {
-            Box::from_raw(satstat);
+            drop(Box::from_raw(satstat));
         }
     }
     #[no_mangle]
@@ -157,6 +179,10 @@ impl Satstat {
     }
     #[no_mangle]
     pub extern "C" fn save_data(&self) {
+        self.save_data_to("final_result_histo.json");
+    }
+    /// save the histograms to the json file at `path`
+    pub fn save_data_to(&self, path: impl AsRef<Path>) {
         let mut final_histograms = BTreeMap::new();
         Self::add_to_final_result(
             "clauses_per_watcher",
@@ -210,8 +236,10 @@ impl Satstat {
             total_watchers: self.total_watchers,
             total_clauses_with_data: self.total_clauses_with_data,
             histograms: final_histograms,
+            clause_lengths: self.clause_lengths.clone(),
+            assignments_per_level: self.assignments_per_level.clone(),
         };
-        let mut file = File::create("final_result_histo.json").unwrap();
+        let mut file = File::create(path).unwrap();
         serde_json::to_writer_pretty(&mut file, &final_result).unwrap();
     }
     /// called every time a propagation occurs, that is, one watcher
@@ -240,10 +268,23 @@ impl Satstat {
             .add_observation(&array![num_clause_read])
             .unwrap();
     }
+    /// called for each clause read by a watcher, with its number of literals
+    #[no_mangle]
+    pub extern "C" fn satstat_add_clause_length(&mut self, clause_len: usize) {
+        *self.clause_lengths.entry(clause_len).or_default() += 1;
+    }
+    /// called for each watcher with its implication level in the decision, the decision is level 0
+    #[no_mangle]
+    pub extern "C" fn satstat_add_watcher_level(&mut self, level: usize) {
+        *self.current_level_assignments_dc.entry(level).or_default() += 1;
+    }
     #[no_mangle]
     pub extern "C" fn end_decision(&mut self, conflict: bool) {
         self.total_decisions += 1;
         self.current_decision_cf += 1;
+        for assignments in std::mem::take(&mut self.current_level_assignments_dc).into_values() {
+            *self.assignments_per_level.entry(assignments).or_default() += 1;
+        }
 
         self.watchers_per_decision
             .add_observation(&array![self.current_watcher_dc])
@@ -285,7 +326,7 @@ mod test {
     use ndarray::array;
     use ndarray_stats::histogram::{Bins, Edges, Grid, Histogram};
 
-    use crate::test_utils::init;
+    use crate::{satacc::SyntheticConfig, test_utils::init};
 
     #[test]
     fn test_histogram() {
@@ -307,12 +348,21 @@ mod test {
         init();
         let mut satstat = super::Satstat::default();
         satstat.satstat_add_watcher(10, 4);
+        satstat.satstat_add_watcher_level(0);
         satstat.satstat_add_watcher(20, 3);
+        satstat.satstat_add_watcher_level(1);
+        satstat.satstat_add_clause_length(3);
         satstat.end_decision(false);
         satstat.satstat_add_watcher(20, 3);
         satstat.satstat_add_watcher(20, 3);
         satstat.end_decision(true);
+        assert_eq!(satstat.assignments_per_level, [(1, 2)].into());
+        assert_eq!(satstat.clause_lengths, [(3, 1)].into());
         satstat.show_data();
-        satstat.save_data();
+        let path = std::env::temp_dir().join("satstat_test_interface.json");
+        satstat.save_data_to(&path);
+        let config = SyntheticConfig::from_histo_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.rounds, satstat.total_decisions);
     }
 }
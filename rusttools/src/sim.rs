@@ -90,6 +90,23 @@ where
     }
 }
 
+impl<T, C> SimComponent for Option<T>
+where
+    T: SimComponent<SharedStatus = C>,
+{
+    type SharedStatus = C;
+    fn update(
+        &mut self,
+        shared_status: &mut Self::SharedStatus,
+        current_cycle: usize,
+    ) -> (bool, bool) {
+        match self {
+            Some(item) => item.update(shared_status, current_cycle),
+            None => (false, false),
+        }
+    }
+}
+
 impl<T, C> SimComponent for Vec<T>
 where
     T: SimComponent<SharedStatus = C>,
@@ -139,6 +156,10 @@ where
     pub fn get_shared_status_mut(&mut self) -> &mut S {
         &mut self.shared_status
     }
+    /// borrow the component and the shared status at the same time
+    pub fn get_sim_and_status_mut(&mut self) -> (&mut T, &mut S) {
+        (&mut self.sim, &mut self.shared_status)
+    }
     pub fn run(&mut self) -> eyre::Result<()> {
         loop {
             let result = self.sim.update(&mut self.shared_status, self.current_cycle);
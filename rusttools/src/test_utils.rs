@@ -1,3 +1,9 @@
+#[cfg(test)]
+use crate::satacc::{
+    satacc_minisat_task::{ClauseData, ClauseTask, SingleRoundTask, WatcherTask},
+    CacheConfig, ChannelMapping, PrefetcherType,
+};
+
 #[cfg(test)]
 pub fn init() {
     tracing_subscriber::fmt::SubscriberBuilder::default()
@@ -6,6 +12,57 @@ pub fn init() {
         .unwrap_or_default();
 }
 
+/// a round with one watcher that reads one clause, the clause reads the values at `clause_value_addr`
+#[cfg(test)]
+pub fn single_clause_round(
+    clause_processing_time: usize,
+    clause_value_addr: &[u64],
+) -> SingleRoundTask {
+    SingleRoundTask {
+        assignments: [WatcherTask {
+            level: 0,
+            meta_data_addr: 0,
+            watcher_addr: 100,
+            watcher_id: 1,
+            value_addr: None,
+            single_watcher_tasks: [ClauseTask {
+                watcher_id: 1,
+                blocker_addr: 1000,
+                clause_data: Some(ClauseData {
+                    clause_id: 1,
+                    clause_addr: 2000,
+                    clause_processing_time: Some(clause_processing_time),
+                    clause_value_addr: clause_value_addr.to_vec(),
+                    clause_value_id: (1..=clause_value_addr.len()).collect(),
+                    ..Default::default()
+                }),
+            }]
+            .into(),
+        }]
+        .into(),
+    }
+}
+
+/// a small cache without prefetcher and bandwidth limit, the tests override the fields they need
+#[cfg(test)]
+pub fn cache_config() -> CacheConfig {
+    CacheConfig {
+        sets: 2,
+        associativity: 2,
+        block_size: 4,
+        channels: 1,
+        channel_mapping: ChannelMapping::BitSlice,
+        channel_busy_cycles: 0,
+        alway_hit: false,
+        mshr_entries: 16,
+        mshr_max_targets: 4,
+        prefetcher: PrefetcherType::NoPrefetch,
+        prefetch_degree: 1,
+        miss_bytes_per_cycle: 0,
+        req_kind_policies: Default::default(),
+    }
+}
+
 #[cfg(test)]
 
 mod test {